@@ -1,4 +1,4 @@
-use crate::{Pixel, Rows, RowsMut, PIXEL_SIZE};
+use crate::{premultiply, Pixel, Rows, RowsMut, PIXEL_SIZE};
 
 // TODO: The methods for this trait rely on a Font argument. I think I want Font to be specified by
 // whatever Self it is implemented for.
@@ -43,6 +43,23 @@ impl Block {
         self.buf.chunks_exact_mut(self.width as usize)
     }
 
+    /// Returns a new [`Block`] with at most `height` rows of this one, starting at row `top`.
+    ///
+    /// Rows past the bottom of this `Block` do not exist, so the returned `Block` is shorter than
+    /// `height` when cropping near the end. This allows for scrolling by single pixels, where the
+    /// first visible line of text is only partially shown.
+    pub(crate) fn crop_rows(&self, top: u32, height: u32) -> Self {
+        let top = top.min(self.height);
+        let height = height.min(self.height - top);
+        let start = top as usize * self.width as usize;
+        let end = start + height as usize * self.width as usize;
+        Self {
+            width: self.width,
+            height,
+            buf: self.buf[start..end].to_vec(),
+        }
+    }
+
     // TODO: Improve this weirdly worded doc comment.
     // TODO: Doc comment out of date because we now also do height.
     /// Paint another [`Block`] onto this one.
@@ -73,23 +90,92 @@ impl Block {
         }
     }
 
+    /// Draw a frame of `thickness` pixels along the edges of this [`Block`].
+    pub(crate) fn frame(&mut self, color: Pixel, thickness: u32) {
+        if self.width == 0 {
+            return;
+        }
+        let (width, height) = (self.width, self.height);
+        for (y, row) in self.rows_mut().enumerate() {
+            let y = y as u32;
+            if y < thickness || y >= height.saturating_sub(thickness) {
+                row.fill(color);
+            } else {
+                let side = thickness.min(width) as usize;
+                row[..side].fill(color);
+                row[width as usize - side..].fill(color);
+            }
+        }
+    }
+
     /// Draws this [`Block`]s contents onto the provided pixel buffer.
     ///
-    /// The pixel buffer is provided as a mutable slice of bytes. It is assumed that this buffer
-    /// uses the same pixel representation as [`Block`], which is 32-bit rgba pixels.
+    /// The pixel buffer is provided as a mutable slice of bytes, holding rows of `frame_width`
+    /// pixels. It is assumed that this buffer uses the same pixel representation as [`Block`],
+    /// which is 32-bit rgba pixels. The color channels are written [premultiplied](premultiply) by
+    /// their alpha.
+    ///
+    /// Only the part of the `Block` that overlaps the frame is drawn. Rows below the bottom of the
+    /// frame are left out, and rows that are wider than the frame are cut off at its right edge.
     ///
     /// See also: [`Pixel`].
-    pub(crate) fn draw_onto_pixels(&self, pixels: &mut [u8]) {
-        assert!(
-            pixels.len() >= self.buf.len(),
-            "pixel buffer is not large enough"
-        );
-        for (y, row) in self.rows().enumerate() {
-            let idx = y * self.width as usize * PIXEL_SIZE;
+    pub(crate) fn draw_onto_pixels(&self, pixels: &mut [u8], frame_width: u32) {
+        if self.width == 0 || frame_width == 0 {
+            return;
+        }
+        let frame_row_len = frame_width as usize * PIXEL_SIZE;
+        let visible_width = self.width.min(frame_width) as usize;
+        for (row, frame_row) in self.rows().zip(pixels.chunks_exact_mut(frame_row_len)) {
             // TODO: See if we can get rid of this iter(). Perhaps through feature(slice_flatten)?
             // TODO: Where should the .copied() go, ideally?
-            let row_bytes: Vec<_> = row.iter().copied().flatten().collect();
-            pixels[idx..idx + row_bytes.len()].copy_from_slice(&row_bytes);
+            let row_bytes: Vec<_> = row[..visible_width]
+                .iter()
+                .copied()
+                .flat_map(premultiply)
+                .collect();
+            frame_row[..row_bytes.len()].copy_from_slice(&row_bytes);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a [`Block`] of `width` by `height` opaque pixels, each of which has its column in
+    /// red and its row in green.
+    fn numbered(width: u32, height: u32) -> Block {
+        let mut block = Block::new(width, height, [0; PIXEL_SIZE]);
+        for (y, row) in block.rows_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = [x as u8, y as u8, 0, 0xff];
+            }
+        }
+        block
+    }
+
+    #[test]
+    fn oversized_block_is_clipped_to_the_frame() {
+        let block = numbered(5, 4);
+        let mut pixels = vec![0xaa; 3 * 2 * PIXEL_SIZE];
+        block.draw_onto_pixels(&mut pixels, 3);
+        let expected: Vec<u8> = (0..2)
+            .flat_map(|y| (0..3).flat_map(move |x| [x, y, 0, 0xff]))
+            .collect();
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn small_block_leaves_the_rest_of_the_frame_alone() {
+        let block = numbered(2, 1);
+        let mut pixels = vec![0xaa; 3 * 2 * PIXEL_SIZE];
+        block.draw_onto_pixels(&mut pixels, 3);
+        let mut expected = vec![0xaa; 3 * 2 * PIXEL_SIZE];
+        expected[..2 * PIXEL_SIZE].copy_from_slice(&[0, 0, 0, 0xff, 1, 0, 0, 0xff]);
+        assert_eq!(pixels, expected);
+        // Neither an empty block nor an empty frame is drawn at all.
+        Block::new(0, 3, [0xff; PIXEL_SIZE]).draw_onto_pixels(&mut pixels, 3);
+        block.draw_onto_pixels(&mut [], 0);
+        assert_eq!(pixels, expected);
+    }
+}
@@ -0,0 +1,65 @@
+//! Handing URLs that are not ours to show over to the system browser.
+
+use std::process::Command;
+
+use log::{error, info};
+
+/// The schemes that are shown in this client rather than in an external browser.
+const INTERNAL_SCHEMES: &[&str] = &["gemini", "titan", "data", "file"];
+
+/// The program that opens a URL with whatever the system prefers for it.
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const OPENER: &str = "xdg-open";
+
+/// Returns whether `url` is meant to be opened in an external browser rather than followed here.
+pub fn opens_externally(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => !INTERNAL_SCHEMES
+            .iter()
+            .any(|internal| scheme.eq_ignore_ascii_case(internal)),
+        None => false,
+    }
+}
+
+/// Returns the [`Command`] that opens `url` in the system browser, without running it.
+pub fn browser_command(url: &str) -> Command {
+    let mut command = Command::new(OPENER);
+    command.arg(url);
+    command
+}
+
+/// Open `url` in the system browser.
+///
+/// The browser is left running on its own, and a failure to launch it is only logged.
+pub fn open_in_browser(url: &str) {
+    info!("Opening {url} in the system browser");
+    if let Err(err) = browser_command(url).spawn() {
+        error!("Could not launch {OPENER:?} for {url}: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_foreign_schemes_open_externally() {
+        assert!(opens_externally("https://example.org/"));
+        assert!(opens_externally("mailto:someone@example.org"));
+        assert!(!opens_externally("gemini://example.org/"));
+        assert!(!opens_externally("GEMINI://example.org/"));
+        assert!(!opens_externally("data:,hello"));
+        // A relative link is on the same capsule.
+        assert!(!opens_externally("../page.gmi"));
+    }
+
+    #[test]
+    fn browser_is_the_system_opener() {
+        let command = browser_command("https://example.org/");
+        assert_eq!(command.get_program(), OPENER);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["https://example.org/"]);
+    }
+}
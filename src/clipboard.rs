@@ -0,0 +1,51 @@
+//! Copying text to the system clipboard, by handing it to the program that the system keeps it
+//! with.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Result};
+
+/// The program that puts what it reads on its standard input on the clipboard, with its arguments.
+#[cfg(target_os = "macos")]
+const COPIERS: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(not(target_os = "macos"))]
+const COPIERS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Put `text` on the system clipboard.
+///
+/// The copiers are tried in turn, until one of them takes the text, since which of them works
+/// depends on the display server as much as on what is installed. Under Wayland and X11 they stay
+/// around in the background to hand the text out.
+pub fn copy(text: &str) -> Result<()> {
+    let mut failure = None;
+    for copier in COPIERS {
+        let mut child = match Command::new(copier[0])
+            .args(&copier[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // A copier that has no display to copy to may quit before it read anything, in which case
+        // its exit status tells why.
+        let _ = stdin.write_all(text.as_bytes());
+        // The copier only knows it has read everything once its standard input is closed.
+        drop(stdin);
+        let status = child.wait()?;
+        if status.success() {
+            return Ok(());
+        }
+        failure = Some(anyhow!("{} exited with {}", copier[0], status));
+    }
+    let names: Vec<_> = COPIERS.iter().map(|copier| copier[0]).collect();
+    Err(failure.unwrap_or_else(|| anyhow!("none of {} is installed", names.join(", "))))
+}
@@ -0,0 +1,102 @@
+//! Laying out a page in two columns when the window is wide enough for it.
+
+/// The narrowest room in pixels that is split into two columns.
+pub const TWO_COLUMN_MIN_WIDTH: u32 = 1000;
+/// The empty space in pixels between the two columns.
+pub const COLUMN_GAP: u32 = 24;
+
+/// Returns the number of columns that fit in `width`, when two columns are `wanted`.
+pub fn column_count(width: u32, wanted: bool) -> u32 {
+    if wanted && width >= TWO_COLUMN_MIN_WIDTH {
+        2
+    } else {
+        1
+    }
+}
+
+/// Returns the width of each of `count` columns that share `width`, with gaps in between.
+pub fn column_width(width: u32, count: u32) -> u32 {
+    width.saturating_sub(COLUMN_GAP * (count - 1)) / count
+}
+
+/// Returns the `width` of a column, but no wider than `cap`, unless the cap is 0.
+pub fn cap_width(width: u32, cap: u32) -> u32 {
+    match cap {
+        0 => width,
+        cap => width.min(cap),
+    }
+}
+
+/// Returns how far `count` columns that are `width` wide each are indented to center them in the
+/// `available` room.
+pub fn centering_indent(available: u32, width: u32, count: u32) -> u32 {
+    let used = width * count + COLUMN_GAP * (count - 1);
+    available.saturating_sub(used) / 2
+}
+
+/// Returns the index of the first line that goes in the right column, given the `heights` of all
+/// lines in order.
+///
+/// Lines are not split between columns. The left column is filled first, such that the taller of
+/// the two columns is as short as possible. When both ways of splitting are equally tall, the
+/// left column gets the extra line.
+pub fn split_columns(heights: &[u32]) -> usize {
+    let total: u32 = heights.iter().sum();
+    let mut left = 0;
+    for (idx, &height) in heights.iter().enumerate() {
+        // Taking this line would make the left column taller than the right one is now.
+        if left + height > total - left {
+            return idx;
+        }
+        left += height;
+    }
+    heights.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_columns_need_room() {
+        assert_eq!(column_count(TWO_COLUMN_MIN_WIDTH - 1, true), 1);
+        assert_eq!(column_count(TWO_COLUMN_MIN_WIDTH, true), 2);
+        assert_eq!(column_count(TWO_COLUMN_MIN_WIDTH, false), 1);
+        assert_eq!(column_width(1000, 1), 1000);
+        assert_eq!(column_width(1000 + COLUMN_GAP, 2), 500);
+    }
+
+    #[test]
+    fn columns_are_split_to_be_as_short_as_possible() {
+        assert_eq!(split_columns(&[]), 0);
+        assert_eq!(split_columns(&[10]), 1);
+        assert_eq!(split_columns(&[10, 10]), 1);
+        // The extra line goes on the left.
+        assert_eq!(split_columns(&[10, 10, 10]), 2);
+        assert_eq!(split_columns(&[30, 10, 10]), 1);
+        assert_eq!(split_columns(&[10, 10, 10, 10, 40]), 4);
+        assert_eq!(split_columns(&[40, 10, 10, 10, 10]), 1);
+    }
+
+    #[test]
+    fn split_leaves_no_shorter_taller_column() {
+        let heights = [12, 3, 27, 8, 8, 15, 1, 30, 4, 9];
+        let taller = |idx: usize| {
+            let left: u32 = heights[..idx].iter().sum();
+            let right: u32 = heights[idx..].iter().sum();
+            left.max(right)
+        };
+        let best = (0..=heights.len()).map(taller).min().unwrap();
+        assert_eq!(taller(split_columns(&heights)), best);
+    }
+
+    #[test]
+    fn capped_columns_are_centered() {
+        assert_eq!(cap_width(1000, 600), 600);
+        assert_eq!(cap_width(400, 600), 400);
+        assert_eq!(cap_width(1000, 0), 1000);
+        assert_eq!(centering_indent(1000, 600, 1), 200);
+        assert_eq!(centering_indent(1000, 1000, 1), 0);
+        assert_eq!(centering_indent(1000 + COLUMN_GAP, 300, 2), 200);
+    }
+}
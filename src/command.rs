@@ -0,0 +1,101 @@
+//! The commands that can be typed at the `:` prompt, like in vi.
+
+use crate::config::{parse_color, Pixel};
+
+/// Something to do that was typed at the `:` prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Go to the address, which may be relative to the current page.
+    Open(String),
+    /// Close the window.
+    Quit,
+    /// Load the current page again.
+    Reload,
+    /// Change a setting while running.
+    Set(Setting),
+    /// Add the current page to the bookmarks.
+    Bookmark,
+    /// Make a new client certificate for the host of the current page, and present it from then on.
+    Identity,
+}
+
+/// A setting that can be changed with [`Command::Set`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Foreground(Pixel),
+    Background(Pixel),
+}
+
+/// Parse the `line` typed at the prompt, without the leading `:`.
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut tokens = line.split_whitespace();
+    let Some(name) = tokens.next() else {
+        return Err(String::from("expected a command"));
+    };
+    let arguments: Vec<_> = tokens.collect();
+    let command = match (name, arguments.as_slice()) {
+        ("open" | "o", [address]) => Command::Open(address.to_string()),
+        ("quit" | "q", []) => Command::Quit,
+        ("reload", []) => Command::Reload,
+        ("bookmark", []) => Command::Bookmark,
+        ("identity", []) => Command::Identity,
+        ("set", [setting, value]) => {
+            let color = parse_color(value)?.to_be_bytes();
+            match *setting {
+                "fg" | "foreground" => Command::Set(Setting::Foreground(color)),
+                "bg" | "background" => Command::Set(Setting::Background(color)),
+                unknown => return Err(format!("unknown setting '{unknown}'")),
+            }
+        }
+        ("open" | "o" | "quit" | "q" | "reload" | "bookmark" | "identity" | "set", _) => {
+            return Err(format!("wrong number of arguments to '{name}'"))
+        }
+        (unknown, _) => return Err(format!("unknown command '{unknown}'")),
+    };
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed_with_their_arguments() {
+        let open = Command::Open("gemini://example.org/".to_string());
+        assert_eq!(
+            parse_command("open gemini://example.org/"),
+            Ok(open.clone())
+        );
+        assert_eq!(parse_command("  o   gemini://example.org/ "), Ok(open));
+        assert_eq!(parse_command("q"), Ok(Command::Quit));
+        assert_eq!(parse_command("reload"), Ok(Command::Reload));
+        assert_eq!(parse_command("bookmark"), Ok(Command::Bookmark));
+        let red = [0xff, 0x00, 0x00, 0xff];
+        let foreground = Command::Set(Setting::Foreground(red));
+        assert_eq!(parse_command("set fg 0xff0000ff"), Ok(foreground));
+        let background = Command::Set(Setting::Background(red));
+        assert_eq!(parse_command("set background 0xff0000ff"), Ok(background));
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        assert_eq!(parse_command(""), Err("expected a command".to_string()));
+        assert_eq!(
+            parse_command("fly away"),
+            Err("unknown command 'fly'".to_string())
+        );
+        assert_eq!(
+            parse_command("quit now"),
+            Err("wrong number of arguments to 'quit'".to_string())
+        );
+        assert_eq!(
+            parse_command("open"),
+            parse_command("open a b").map(|_| Command::Quit)
+        );
+        assert_eq!(
+            parse_command("set size 0xff0000ff"),
+            Err("unknown setting 'size'".to_string())
+        );
+        assert!(parse_command("set fg red").is_err());
+    }
+}
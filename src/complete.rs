@@ -0,0 +1,100 @@
+//! Completing addresses that are being typed from the ones that were seen before.
+
+/// How well an address matches what is typed, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    /// The address, or its host if the scheme is left out, starts with what is typed.
+    HostPrefix,
+    /// What is typed is somewhere in the address.
+    Substring,
+}
+
+/// Returns how well the `address` matches the typed `prefix`, if at all. Both are expected to be
+/// lowercase already.
+fn match_address(prefix: &str, address: &str) -> Option<Match> {
+    let without_scheme = match address.split_once("://") {
+        Some((_, rest)) => rest,
+        None => address,
+    };
+    if address.starts_with(prefix) || without_scheme.starts_with(prefix) {
+        Some(Match::HostPrefix)
+    } else if address.contains(prefix) {
+        Some(Match::Substring)
+    } else {
+        None
+    }
+}
+
+/// Returns the best completion for the typed `prefix` from the addresses in `sources`, ignoring
+/// case.
+///
+/// Addresses whose host, or the address as a whole, starts with the prefix are preferred over
+/// those that only contain it. Among equally good matches, the shortest wins, and then the first in
+/// alphabetical order, so that the same suggestion comes up however the `sources` are ordered.
+/// Nothing is typed for an empty prefix, and an address that is already typed in full needs no
+/// completion.
+pub fn complete(prefix: &str, sources: &[&str]) -> Option<String> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let prefix = prefix.to_lowercase();
+    sources
+        .iter()
+        .filter(|address| address.to_lowercase() != prefix)
+        .filter_map(|&address| {
+            let quality = match_address(&prefix, &address.to_lowercase())?;
+            Some((quality, address.len(), address))
+        })
+        .min()
+        .map(|(_, _, address)| address.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &[&str] = &[
+        "gemini://station.example/blog/",
+        "gemini://example.org/",
+        "gemini://example.org/long/page.gmi",
+        "gemini://other.net/example/",
+    ];
+
+    #[test]
+    fn host_prefix_wins_over_substring() {
+        assert_eq!(
+            complete("exa", SOURCES).as_deref(),
+            Some("gemini://example.org/")
+        );
+        assert_eq!(
+            complete("gemini://o", SOURCES).as_deref(),
+            Some("gemini://other.net/example/")
+        );
+        assert_eq!(
+            complete("blog", SOURCES).as_deref(),
+            Some("gemini://station.example/blog/")
+        );
+    }
+
+    #[test]
+    fn completion_ignores_case_and_order() {
+        let reversed: Vec<_> = SOURCES.iter().rev().copied().collect();
+        assert_eq!(
+            complete("EXAMPLE.org/l", SOURCES),
+            complete("example.org/l", &reversed)
+        );
+        assert_eq!(
+            complete("EXAMPLE.org/l", SOURCES).as_deref(),
+            Some("gemini://example.org/long/page.gmi")
+        );
+    }
+
+    #[test]
+    fn nothing_to_complete() {
+        assert_eq!(complete("", SOURCES), None);
+        assert_eq!(complete("nowhere", SOURCES), None);
+        assert_eq!(complete("anything", &[]), None);
+        // An address that is typed in full is not suggested again.
+        assert_eq!(complete("gemini://other.net/example/", SOURCES), None);
+    }
+}
@@ -1,49 +1,249 @@
+use std::ffi::OsStr;
 use std::fs::File;
-use std::io::Read;
+use std::io::{ErrorKind, Read};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use gemininini::elements::Padding;
 use lexopt::{Arg, Parser, ValueExt};
+use log::{debug, warn};
+use url::Url;
+
+use crate::keymap::{Action, Binding, KeyMap};
+use crate::local::STDIN_ADDRESS;
 
 use std::iter::FromIterator;
 
 const CONFIG_FILE_PATH: &str = "/etc/tid/gemininini.config";
 const DEFAULT_FONT_DIR: &str = "/etc/tid/fonts";
 const DEFAULT_FONT: &str = "cream12.uf2";
+/// The extension of font files, by which a font given by itself on the command line is told apart
+/// from the address of the start page.
+const FONT_EXTENSION: &str = "uf2";
 
 const DEFAULT_BACKGROUND: Pixel = [0x00; PIXEL_SIZE];
 const DEFAULT_FOREGROUND: Pixel = [0xff; PIXEL_SIZE];
+const DEFAULT_ERROR_COLOR: Pixel = [0xcc, 0x22, 0x22, 0xff];
+const DEFAULT_LINE_SPACING: u32 = 0;
+const DEFAULT_MARGIN: u32 = 2;
+const DEFAULT_MAX_CONTENT_WIDTH: u32 = 70;
+const DEFAULT_NATURAL_SCROLLING: bool = false;
+const DEFAULT_SCROLL_STEP: usize = 8;
+const DEFAULT_TWO_COLUMNS: bool = false;
+const DEFAULT_LINE_NUMBERS: bool = false;
+const DEFAULT_ALIGN_TABLES: bool = false;
+const DEFAULT_TINT_CODE: bool = false;
+const DEFAULT_ADDED_COLOR: Pixel = [0x22, 0x88, 0x22, 0xff];
+const DEFAULT_REMOVED_COLOR: Pixel = [0xcc, 0x22, 0x22, 0xff];
+const DEFAULT_LINK_HINTS: bool = true;
+const DEFAULT_LINKIFY: bool = false;
+const DEFAULT_FAVICONS: bool = false;
+const DEFAULT_WORDS_PER_MINUTE: u32 = 200;
+const DEFAULT_DOWNLOAD_DIR: &str = ".";
+const DEFAULT_RESIZE_DEBOUNCE_MS: u64 = 100;
+const DEFAULT_MAX_PAGE_SIZE: usize = 10 * 1024 * 1024;
+const DEFAULT_TRUNCATE_LARGE_PAGES: bool = true;
+const DEFAULT_RETRIES: u32 = 2;
+const DEFAULT_RETRY_DELAY_MS: u64 = 500;
+const DEFAULT_DUMP_WIDTH: u32 = 80;
+const DEFAULT_START_URL: &str = "gemini://gemini.cyberbot.space/";
+const DEFAULT_RESTORE_SESSION: bool = true;
+/// The directory under the user's config directory that things such as the session are kept in.
+const USER_DIR: &str = "gemininini";
 
 pub type Pixel = [u8; PIXEL_SIZE];
 pub const PIXEL_SIZE: usize = 4;
 const COLOR_PREFIX: &str = "0x";
 
+/// The built-in color themes, as their name with a foreground and background color.
+const THEMES: &[(&str, Pixel, Pixel)] = &[
+    ("light", DEFAULT_FOREGROUND, DEFAULT_BACKGROUND),
+    ("dark", [0xdd, 0xdd, 0xdd, 0xff], [0x11, 0x11, 0x11, 0xff]),
+    (
+        "solarized",
+        [0x83, 0x94, 0x96, 0xff],
+        [0x00, 0x2b, 0x36, 0xff],
+    ),
+];
+
 pub struct Config {
-    pub font_path: Box<Path>,
+    pub font_path: Vec<Box<Path>>,
     pub foreground: Pixel,
     pub background: Pixel,
+    /// The color of link lines, if it differs from the `foreground`.
+    pub link_color: Option<Pixel>,
+    /// The color of heading lines, if it differs from the `foreground`.
+    pub heading_color: Option<Pixel>,
+    /// The color of the banner that tells why a page could not be loaded.
+    pub error_color: Pixel,
+    /// Extra vertical space in pixels between lines of text.
+    pub line_spacing: u32,
+    /// Empty space in pixels between the edges of the window and everything in it.
+    pub margin: Padding,
+    /// How many characters wide the text of a page is wrapped at most, however wide the window is.
+    /// A width of 0 puts no cap on it.
+    pub max_content_width: u32,
+    /// Whether the mouse wheel moves the content rather than the view.
+    pub natural_scrolling: bool,
+    /// How far in pixels a press of a scroll key or a line of the mouse wheel scrolls.
+    pub scroll_step: usize,
+    /// How far in pixels a scroll key scrolls at most while it is held down. The step grows
+    /// towards it with every repeat of the key. Without it, the step does not grow.
+    pub scroll_max_step: Option<usize>,
+    /// Whether pages are shown in two columns when the window is wide enough.
+    pub two_columns: bool,
+    /// Whether the lines of a page are numbered in a gutter.
+    pub line_numbers: bool,
+    /// Whether the columns of tables drawn with pipes in preformatted blocks are lined up.
+    pub align_tables: bool,
+    /// Whether the lines of preformatted blocks are tinted after the language in their alt text,
+    /// such as the added and removed lines of a diff.
+    pub tint_code: bool,
+    /// The color of the lines that a diff adds.
+    pub added_color: Pixel,
+    /// The color of the lines that a diff removes.
+    pub removed_color: Pixel,
+    /// The color of comments in shell scripts, if it differs from a dimmed `foreground`.
+    pub comment_color: Option<Pixel>,
+    /// Whether link lines are followed by a hint of the scheme and host that they lead to.
+    pub link_hints: bool,
+    /// Whether bare URLs in the text of gemtext pages can be picked and followed like links.
+    pub linkify: bool,
+    /// Whether capsules are asked for a `favicon.txt`, to show its emoji next to their address.
+    pub favicons: bool,
+    /// How many words are read per minute, to estimate how long a page takes to read.
+    pub words_per_minute: u32,
+    /// The directory that saved pages are written to.
+    pub download_dir: Box<Path>,
+    /// How long in milliseconds the window size must stay put before the content is laid out anew.
+    pub resize_debounce_ms: u64,
+    /// How large in bytes the body of a page may be.
+    pub max_page_size: usize,
+    /// Whether a page that is larger than `max_page_size` is cut off there, rather than failing
+    /// to load.
+    pub truncate_large_pages: bool,
+    /// How many more times a request that failed transiently is tried, such as when the
+    /// connection was dropped.
+    pub retries: u32,
+    /// How long in milliseconds to wait before the first retry. The wait doubles with every retry.
+    pub retry_delay_ms: u64,
+    /// The address of the page that is shown on startup.
+    pub start_url: String,
+    /// Whether to show the page that was shown when the last session ended, instead of the
+    /// `start_url`. A `start_url` given on the command line takes precedence.
+    pub restore_session: bool,
+    /// Whether to browse privately, without writing anything about the session to disk.
+    pub private: bool,
+    /// The address of the home page, if one is set.
+    pub home: Option<String>,
+    pub keymap: KeyMap,
+    /// How many levels more verbose than the default the log is, or less verbose if negative.
+    pub verbosity: i8,
+    /// Whether to print the start page to stdout and exit, rather than opening a window.
+    pub dump: bool,
+    /// How many columns wide a dumped page is wrapped to.
+    pub dump_width: u32,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            font_path: PathBuf::from_iter([DEFAULT_FONT_DIR, DEFAULT_FONT]).into_boxed_path(),
+            font_path: vec![PathBuf::from_iter([DEFAULT_FONT_DIR, DEFAULT_FONT]).into_boxed_path()],
             foreground: DEFAULT_FOREGROUND,
             background: DEFAULT_BACKGROUND,
+            link_color: None,
+            heading_color: None,
+            error_color: DEFAULT_ERROR_COLOR,
+            line_spacing: DEFAULT_LINE_SPACING,
+            margin: Padding {
+                top: DEFAULT_MARGIN,
+                bottom: DEFAULT_MARGIN,
+                left: DEFAULT_MARGIN,
+                right: DEFAULT_MARGIN,
+            },
+            max_content_width: DEFAULT_MAX_CONTENT_WIDTH,
+            natural_scrolling: DEFAULT_NATURAL_SCROLLING,
+            scroll_step: DEFAULT_SCROLL_STEP,
+            scroll_max_step: None,
+            two_columns: DEFAULT_TWO_COLUMNS,
+            line_numbers: DEFAULT_LINE_NUMBERS,
+            align_tables: DEFAULT_ALIGN_TABLES,
+            tint_code: DEFAULT_TINT_CODE,
+            added_color: DEFAULT_ADDED_COLOR,
+            removed_color: DEFAULT_REMOVED_COLOR,
+            comment_color: None,
+            link_hints: DEFAULT_LINK_HINTS,
+            linkify: DEFAULT_LINKIFY,
+            favicons: DEFAULT_FAVICONS,
+            words_per_minute: DEFAULT_WORDS_PER_MINUTE,
+            download_dir: PathBuf::from(DEFAULT_DOWNLOAD_DIR).into_boxed_path(),
+            resize_debounce_ms: DEFAULT_RESIZE_DEBOUNCE_MS,
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            truncate_large_pages: DEFAULT_TRUNCATE_LARGE_PAGES,
+            retries: DEFAULT_RETRIES,
+            retry_delay_ms: DEFAULT_RETRY_DELAY_MS,
+            start_url: DEFAULT_START_URL.to_string(),
+            restore_session: DEFAULT_RESTORE_SESSION,
+            private: false,
+            home: None,
+            keymap: KeyMap::default(),
+            verbosity: 0,
+            dump: false,
+            dump_width: DEFAULT_DUMP_WIDTH,
         }
     }
 }
 
 #[derive(Default)]
 struct ConfigBuilder {
-    pub font_path: Option<PathBuf>,
+    /// The fonts that were named, in the order they are tried in.
+    pub font_path: Vec<PathBuf>,
     pub foreground: Option<Pixel>,
     pub background: Option<Pixel>,
+    pub link_color: Option<Pixel>,
+    pub heading_color: Option<Pixel>,
+    pub error_color: Option<Pixel>,
+    /// The foreground and background colors of the chosen theme.
+    pub theme: Option<(Pixel, Pixel)>,
+    pub line_spacing: Option<u32>,
+    pub margin_top: Option<u32>,
+    pub margin_right: Option<u32>,
+    pub margin_bottom: Option<u32>,
+    pub margin_left: Option<u32>,
+    pub max_content_width: Option<u32>,
+    pub natural_scrolling: Option<bool>,
+    pub scroll_step: Option<usize>,
+    pub scroll_max_step: Option<usize>,
+    pub two_columns: Option<bool>,
+    pub line_numbers: Option<bool>,
+    pub align_tables: Option<bool>,
+    pub tint_code: Option<bool>,
+    pub added_color: Option<Pixel>,
+    pub removed_color: Option<Pixel>,
+    pub comment_color: Option<Pixel>,
+    pub link_hints: Option<bool>,
+    pub linkify: Option<bool>,
+    pub favicons: Option<bool>,
+    pub words_per_minute: Option<u32>,
+    pub download_dir: Option<PathBuf>,
+    pub resize_debounce_ms: Option<u64>,
+    pub max_page_size: Option<usize>,
+    pub truncate_large_pages: Option<bool>,
+    pub retries: Option<u32>,
+    pub retry_delay_ms: Option<u64>,
+    pub start_url: Option<String>,
+    pub restore_session: Option<bool>,
+    pub private: Option<bool>,
+    pub home: Option<String>,
+    pub bindings: Vec<(Action, Vec<Binding>)>,
+    pub verbosity: Option<i8>,
+    pub dump: Option<bool>,
+    pub dump_width: Option<u32>,
 }
 
 impl ConfigBuilder {
-    fn set_font_path(&mut self, font_path: PathBuf) {
-        self.font_path = Some(font_path);
+    fn add_font_path(&mut self, font_path: PathBuf) {
+        self.font_path.push(font_path);
     }
 
     fn set_foreground(&mut self, foreground: Pixel) {
@@ -53,13 +253,222 @@ impl ConfigBuilder {
     fn set_background(&mut self, background: Pixel) {
         self.background = Some(background);
     }
+
+    fn set_link_color(&mut self, link_color: Pixel) {
+        self.link_color = Some(link_color);
+    }
+
+    fn set_heading_color(&mut self, heading_color: Pixel) {
+        self.heading_color = Some(heading_color);
+    }
+
+    fn set_error_color(&mut self, error_color: Pixel) {
+        self.error_color = Some(error_color);
+    }
+
+    fn set_theme(&mut self, theme: (Pixel, Pixel)) {
+        self.theme = Some(theme);
+    }
+
+    fn set_line_spacing(&mut self, line_spacing: u32) {
+        self.line_spacing = Some(line_spacing);
+    }
+
+    fn set_margin(&mut self, margin: u32) {
+        self.set_margin_top(margin);
+        self.set_margin_right(margin);
+        self.set_margin_bottom(margin);
+        self.set_margin_left(margin);
+    }
+
+    fn set_margin_top(&mut self, margin: u32) {
+        self.margin_top = Some(margin);
+    }
+
+    fn set_margin_right(&mut self, margin: u32) {
+        self.margin_right = Some(margin);
+    }
+
+    fn set_margin_bottom(&mut self, margin: u32) {
+        self.margin_bottom = Some(margin);
+    }
+
+    fn set_margin_left(&mut self, margin: u32) {
+        self.margin_left = Some(margin);
+    }
+
+    fn set_max_content_width(&mut self, max_content_width: u32) {
+        self.max_content_width = Some(max_content_width);
+    }
+
+    fn set_natural_scrolling(&mut self, natural_scrolling: bool) {
+        self.natural_scrolling = Some(natural_scrolling);
+    }
+
+    fn set_scroll_step(&mut self, scroll_step: usize) {
+        self.scroll_step = Some(scroll_step);
+    }
+
+    fn set_scroll_max_step(&mut self, scroll_max_step: usize) {
+        self.scroll_max_step = Some(scroll_max_step);
+    }
+
+    fn set_two_columns(&mut self, two_columns: bool) {
+        self.two_columns = Some(two_columns);
+    }
+
+    fn set_line_numbers(&mut self, line_numbers: bool) {
+        self.line_numbers = Some(line_numbers);
+    }
+
+    fn set_align_tables(&mut self, align_tables: bool) {
+        self.align_tables = Some(align_tables);
+    }
+
+    fn set_tint_code(&mut self, tint_code: bool) {
+        self.tint_code = Some(tint_code);
+    }
+
+    fn set_added_color(&mut self, added_color: Pixel) {
+        self.added_color = Some(added_color);
+    }
+
+    fn set_removed_color(&mut self, removed_color: Pixel) {
+        self.removed_color = Some(removed_color);
+    }
+
+    fn set_comment_color(&mut self, comment_color: Pixel) {
+        self.comment_color = Some(comment_color);
+    }
+
+    fn set_link_hints(&mut self, link_hints: bool) {
+        self.link_hints = Some(link_hints);
+    }
+
+    fn set_linkify(&mut self, linkify: bool) {
+        self.linkify = Some(linkify);
+    }
+
+    fn set_favicons(&mut self, favicons: bool) {
+        self.favicons = Some(favicons);
+    }
+
+    fn set_words_per_minute(&mut self, words_per_minute: u32) {
+        self.words_per_minute = Some(words_per_minute);
+    }
+
+    fn set_download_dir(&mut self, download_dir: PathBuf) {
+        self.download_dir = Some(download_dir);
+    }
+
+    fn set_resize_debounce_ms(&mut self, resize_debounce_ms: u64) {
+        self.resize_debounce_ms = Some(resize_debounce_ms);
+    }
+
+    fn set_max_page_size(&mut self, max_page_size: usize) {
+        self.max_page_size = Some(max_page_size);
+    }
+
+    fn set_truncate_large_pages(&mut self, truncate_large_pages: bool) {
+        self.truncate_large_pages = Some(truncate_large_pages);
+    }
+
+    fn set_retries(&mut self, retries: u32) {
+        self.retries = Some(retries);
+    }
+
+    fn set_retry_delay_ms(&mut self, retry_delay_ms: u64) {
+        self.retry_delay_ms = Some(retry_delay_ms);
+    }
+
+    fn set_start_url(&mut self, start_url: String) {
+        self.start_url = Some(start_url);
+    }
+
+    /// Make the log `step` levels more verbose, or less verbose if negative.
+    fn adjust_verbosity(&mut self, step: i8) {
+        self.verbosity = Some(self.verbosity.unwrap_or_default().saturating_add(step));
+    }
+
+    fn set_restore_session(&mut self, restore_session: bool) {
+        self.restore_session = Some(restore_session);
+    }
+
+    fn set_private(&mut self, private: bool) {
+        self.private = Some(private);
+    }
+
+    fn set_home(&mut self, home: String) {
+        self.home = Some(home);
+    }
+
+    fn set_dump(&mut self, dump: bool) {
+        self.dump = Some(dump);
+    }
+
+    fn set_dump_width(&mut self, dump_width: u32) {
+        self.dump_width = Some(dump_width);
+    }
+
+    fn add_binding(&mut self, action: Action, bindings: Vec<Binding>) {
+        self.bindings.push((action, bindings));
+    }
 }
 
-fn parse_color(hex: &str) -> Result<u32, String> {
+/// Parse an rgba hex color such as `0xff0000ff`. An rgb color such as `0xff0000` is opaque.
+pub fn parse_color(hex: &str) -> Result<u32, String> {
     let stripped = hex.strip_prefix(COLOR_PREFIX).ok_or(format!(
         "color values must be prefixed with '{COLOR_PREFIX}'"
     ))?;
-    u32::from_str_radix(stripped, 16).map_err(|e| e.to_string())
+    let color = u32::from_str_radix(stripped, 16).map_err(|e| e.to_string())?;
+    match stripped.len() {
+        6 => Ok((color << 8) | 0xff),
+        _ => Ok(color),
+    }
+}
+
+/// Returns the foreground and background color of the theme called `name`.
+fn parse_theme(name: &str) -> Result<(Pixel, Pixel), String> {
+    THEMES
+        .iter()
+        .find(|(theme, _, _)| *theme == name)
+        .map(|&(_, foreground, background)| (foreground, background))
+        .ok_or_else(|| {
+            let names: Vec<_> = THEMES.iter().map(|(theme, _, _)| *theme).collect();
+            format!(
+                "unknown theme '{name}', expected one of {}",
+                names.join(", ")
+            )
+        })
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        other => Err(format!("expected 'true' or 'false', found '{other}'")),
+    }
+}
+
+/// Returns whether the `value` of a command line argument is the path of a font file.
+fn is_font_file(value: &OsStr) -> bool {
+    Path::new(value).extension() == Some(OsStr::new(FONT_EXTENSION))
+}
+
+/// Parse the address of a page. Besides URLs, that is `-` for standard input, or the path of a
+/// file, which is turned into its `file://` URL.
+fn parse_url(address: &str) -> Result<String, String> {
+    if address == STDIN_ADDRESS {
+        return Ok(address.to_string());
+    }
+    Url::parse(address)
+        .map(|_| address.to_string())
+        .or_else(|e| match std::fs::canonicalize(address) {
+            Ok(path) => Url::from_file_path(path)
+                .map(|url| url.to_string())
+                .map_err(|()| format!("invalid path '{address}'")),
+            Err(_) => Err(format!("invalid URL '{address}': {e}")),
+        })
 }
 
 fn parse_config(config: &str) -> Result<ConfigBuilder, String> {
@@ -89,11 +498,79 @@ fn parse_config(config: &str) -> Result<ConfigBuilder, String> {
 
         match keyword {
             "font_name" => {
-                cfg.set_font_path(PathBuf::from_iter([DEFAULT_FONT_DIR, first_argument]))
+                cfg.add_font_path(PathBuf::from_iter([DEFAULT_FONT_DIR, first_argument]))
             }
-            "font_path" => cfg.set_font_path(PathBuf::from(first_argument)),
+            "font_path" => cfg.add_font_path(PathBuf::from(first_argument)),
             "foreground" => cfg.set_foreground(parse_color(first_argument)?.to_be_bytes()),
             "background" => cfg.set_background(parse_color(first_argument)?.to_be_bytes()),
+            "link_color" => cfg.set_link_color(parse_color(first_argument)?.to_be_bytes()),
+            "heading_color" => cfg.set_heading_color(parse_color(first_argument)?.to_be_bytes()),
+            "error_color" => cfg.set_error_color(parse_color(first_argument)?.to_be_bytes()),
+            "theme" => cfg.set_theme(parse_theme(first_argument)?),
+            "line_spacing" => {
+                cfg.set_line_spacing(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "margin" => cfg.set_margin(first_argument.parse().map_err(|e| format!("{e}"))?),
+            "margin_top" => cfg.set_margin_top(first_argument.parse().map_err(|e| format!("{e}"))?),
+            "margin_right" => {
+                cfg.set_margin_right(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "margin_bottom" => {
+                cfg.set_margin_bottom(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "margin_left" => {
+                cfg.set_margin_left(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "max_content_width" => {
+                cfg.set_max_content_width(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "natural_scrolling" => cfg.set_natural_scrolling(parse_bool(first_argument)?),
+            "scroll_step" => {
+                cfg.set_scroll_step(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "scroll_max_step" => {
+                cfg.set_scroll_max_step(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "two_columns" => cfg.set_two_columns(parse_bool(first_argument)?),
+            "line_numbers" => cfg.set_line_numbers(parse_bool(first_argument)?),
+            "align_tables" => cfg.set_align_tables(parse_bool(first_argument)?),
+            "tint_code" => cfg.set_tint_code(parse_bool(first_argument)?),
+            "added_color" => cfg.set_added_color(parse_color(first_argument)?.to_be_bytes()),
+            "removed_color" => cfg.set_removed_color(parse_color(first_argument)?.to_be_bytes()),
+            "comment_color" => cfg.set_comment_color(parse_color(first_argument)?.to_be_bytes()),
+            "link_hints" => cfg.set_link_hints(parse_bool(first_argument)?),
+            "linkify" => cfg.set_linkify(parse_bool(first_argument)?),
+            "favicons" => cfg.set_favicons(parse_bool(first_argument)?),
+            "words_per_minute" => {
+                cfg.set_words_per_minute(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "download_dir" => cfg.set_download_dir(PathBuf::from(first_argument)),
+            "resize_debounce" => {
+                cfg.set_resize_debounce_ms(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "max_page_size" => {
+                cfg.set_max_page_size(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "truncate_large_pages" => cfg.set_truncate_large_pages(parse_bool(first_argument)?),
+            "retries" => cfg.set_retries(first_argument.parse().map_err(|e| format!("{e}"))?),
+            "retry_delay" => {
+                cfg.set_retry_delay_ms(first_argument.parse().map_err(|e| format!("{e}"))?)
+            }
+            "home" => cfg.set_home(parse_url(first_argument)?),
+            "restore_session" => cfg.set_restore_session(parse_bool(first_argument)?),
+            "bind" => {
+                let action = first_argument.parse()?;
+                let bindings = arguments[1..]
+                    .iter()
+                    .map(|key| key.parse())
+                    .collect::<Result<Vec<_>, _>>()?;
+                if bindings.is_empty() {
+                    return Err(format!(
+                        "expected at least one key to bind '{first_argument}' to"
+                    ));
+                }
+                cfg.add_binding(action, bindings)
+            }
 
             unknown => return Err(format!("unknown keyword '{unknown}'")),
         }
@@ -103,17 +580,21 @@ fn parse_config(config: &str) -> Result<ConfigBuilder, String> {
 }
 
 fn parse_args() -> Result<ConfigBuilder, lexopt::Error> {
+    parse_args_from(Parser::from_env())
+}
+
+/// Parse the command line arguments that the `parser` goes through.
+fn parse_args_from(mut parser: Parser) -> Result<ConfigBuilder, lexopt::Error> {
     let mut cfg = ConfigBuilder::default();
 
-    let mut parser = Parser::from_env();
     while let Some(arg) = parser.next()? {
         match arg {
-            Arg::Short('n') | Arg::Long("font-name") => cfg.set_font_path(PathBuf::from_iter([
+            Arg::Short('n') | Arg::Long("font-name") => cfg.add_font_path(PathBuf::from_iter([
                 DEFAULT_FONT_DIR,
                 &parser.value()?.string()?,
             ])),
             Arg::Short('p') | Arg::Long("font-path") => {
-                cfg.set_font_path(PathBuf::from(parser.value()?))
+                cfg.add_font_path(PathBuf::from(parser.value()?))
             }
             Arg::Long("fg") => {
                 let hex = parser.value()?.string()?;
@@ -123,6 +604,68 @@ fn parse_args() -> Result<ConfigBuilder, lexopt::Error> {
                 let hex = parser.value()?.string()?;
                 cfg.set_background(parse_color(&hex)?.to_be_bytes());
             }
+            Arg::Long("link-color") => {
+                let hex = parser.value()?.string()?;
+                cfg.set_link_color(parse_color(&hex)?.to_be_bytes());
+            }
+            Arg::Long("heading-color") => {
+                let hex = parser.value()?.string()?;
+                cfg.set_heading_color(parse_color(&hex)?.to_be_bytes());
+            }
+            Arg::Long("error-color") => {
+                let hex = parser.value()?.string()?;
+                cfg.set_error_color(parse_color(&hex)?.to_be_bytes());
+            }
+            Arg::Long("theme") => cfg.set_theme(parse_theme(&parser.value()?.string()?)?),
+            Arg::Long("line-spacing") => cfg.set_line_spacing(parser.value()?.parse()?),
+            Arg::Long("margin") => cfg.set_margin(parser.value()?.parse()?),
+            Arg::Long("max-content-width") => cfg.set_max_content_width(parser.value()?.parse()?),
+            Arg::Long("natural-scrolling") => cfg.set_natural_scrolling(true),
+            Arg::Long("scroll-step") => cfg.set_scroll_step(parser.value()?.parse()?),
+            Arg::Long("scroll-max-step") => cfg.set_scroll_max_step(parser.value()?.parse()?),
+            Arg::Long("two-columns") => cfg.set_two_columns(true),
+            Arg::Long("line-numbers") => cfg.set_line_numbers(true),
+            Arg::Long("align-tables") => cfg.set_align_tables(true),
+            Arg::Long("tint-code") => cfg.set_tint_code(true),
+            Arg::Long("added-color") => {
+                let hex = parser.value()?.string()?;
+                cfg.set_added_color(parse_color(&hex)?.to_be_bytes());
+            }
+            Arg::Long("removed-color") => {
+                let hex = parser.value()?.string()?;
+                cfg.set_removed_color(parse_color(&hex)?.to_be_bytes());
+            }
+            Arg::Long("comment-color") => {
+                let hex = parser.value()?.string()?;
+                cfg.set_comment_color(parse_color(&hex)?.to_be_bytes());
+            }
+            Arg::Long("no-link-hints") => cfg.set_link_hints(false),
+            Arg::Long("linkify") => cfg.set_linkify(true),
+            Arg::Long("favicons") => cfg.set_favicons(true),
+            Arg::Long("words-per-minute") => cfg.set_words_per_minute(parser.value()?.parse()?),
+            Arg::Long("download-dir") => cfg.set_download_dir(PathBuf::from(parser.value()?)),
+            Arg::Long("resize-debounce") => cfg.set_resize_debounce_ms(parser.value()?.parse()?),
+            Arg::Long("max-page-size") => cfg.set_max_page_size(parser.value()?.parse()?),
+            Arg::Long("fail-large-pages") => cfg.set_truncate_large_pages(false),
+            Arg::Long("retries") => cfg.set_retries(parser.value()?.parse()?),
+            Arg::Long("retry-delay") => cfg.set_retry_delay_ms(parser.value()?.parse()?),
+            // Asking for a page to start on means not wanting the last one.
+            Arg::Short('u') | Arg::Long("url") => {
+                cfg.set_start_url(parse_url(&parser.value()?.string()?)?);
+                cfg.set_restore_session(false);
+            }
+            Arg::Long("verbose") => cfg.adjust_verbosity(1),
+            Arg::Short('q') | Arg::Long("quiet") => cfg.adjust_verbosity(-1),
+            Arg::Long("no-restore") => cfg.set_restore_session(false),
+            Arg::Long("private") => cfg.set_private(true),
+            Arg::Long("dump") => cfg.set_dump(true),
+            Arg::Long("width") => cfg.set_dump_width(parser.value()?.parse()?),
+            // A font file may be given by itself, as it could be before there was a config file.
+            Arg::Value(path) if is_font_file(&path) => cfg.add_font_path(PathBuf::from(path)),
+            Arg::Value(address) => {
+                cfg.set_start_url(parse_url(&address.string()?)?);
+                cfg.set_restore_session(false);
+            }
             Arg::Short('v') | Arg::Long("version") => {
                 println!("{}", env!("CARGO_PKG_VERSION"));
                 std::process::exit(0);
@@ -138,6 +681,16 @@ fn parse_args() -> Result<ConfigBuilder, lexopt::Error> {
     Ok(cfg)
 }
 
+/// Returns the directory that things such as the session are kept in, in `$XDG_CONFIG_HOME` or
+/// else in `$HOME/.config`.
+pub fn user_dir() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join(USER_DIR))
+}
+
 /// Create a configuration based on defaults, followed by config files, and finally command line
 /// arguments.
 pub fn configure() -> Result<Config, Box<dyn std::error::Error>> {
@@ -150,28 +703,161 @@ pub fn configure() -> Result<Config, Box<dyn std::error::Error>> {
                 format!("problem parsing config file {config_file_path:?}: {err}")
             })?)
         }
+        // Going without a config file is fine, so there is little point in mentioning it.
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            debug!("No config file at {config_file_path:?}");
+            None
+        }
         Err(err) => {
-            eprintln!("ERROR: problem reading {config_file_path:?}: {err}");
+            warn!("Problem reading {config_file_path:?}: {err}");
             None
         }
     };
     let command_line_cfg =
         Some(parse_args().map_err(|err| format!("problem reading command line arguments: {err}"))?);
 
+    let cfgs = [config_file_cfg, command_line_cfg];
+    Ok(merge(cfgs.into_iter().flatten().collect()))
+}
+
+/// Returns the [`Config`] that the `cfgs` add up to, of which the later ones take precedence.
+fn merge(cfgs: Vec<ConfigBuilder>) -> Config {
     let mut config = Config::default();
-    for cfg in [config_file_cfg, command_line_cfg].into_iter().flatten() {
-        if let Some(font_path) = cfg.font_path {
-            config.font_path = font_path.into_boxed_path()
-        }
+    // Colors that are set explicitly take precedence over those of a theme, wherever either of
+    // them is set, so the theme goes first.
+    if let Some((foreground, background)) = cfgs.iter().rev().find_map(|cfg| cfg.theme) {
+        config.foreground = foreground;
+        config.background = background;
+    }
+    let mut font_path = Vec::new();
+    for cfg in cfgs {
+        // Fonts add up rather than replace each other. Those named later, such as on the command
+        // line, are tried first, and fall back on the ones named before.
+        let named = cfg.font_path.into_iter().map(PathBuf::into_boxed_path);
+        font_path.splice(0..0, named);
         if let Some(foreground) = cfg.foreground {
             config.foreground = foreground
         }
         if let Some(background) = cfg.background {
             config.background = background
         }
+        if let Some(link_color) = cfg.link_color {
+            config.link_color = Some(link_color)
+        }
+        if let Some(heading_color) = cfg.heading_color {
+            config.heading_color = Some(heading_color)
+        }
+        if let Some(error_color) = cfg.error_color {
+            config.error_color = error_color
+        }
+        if let Some(line_spacing) = cfg.line_spacing {
+            config.line_spacing = line_spacing
+        }
+        if let Some(top) = cfg.margin_top {
+            config.margin.top = top
+        }
+        if let Some(right) = cfg.margin_right {
+            config.margin.right = right
+        }
+        if let Some(bottom) = cfg.margin_bottom {
+            config.margin.bottom = bottom
+        }
+        if let Some(left) = cfg.margin_left {
+            config.margin.left = left
+        }
+        if let Some(max_content_width) = cfg.max_content_width {
+            config.max_content_width = max_content_width
+        }
+        if let Some(natural_scrolling) = cfg.natural_scrolling {
+            config.natural_scrolling = natural_scrolling
+        }
+        if let Some(scroll_step) = cfg.scroll_step {
+            config.scroll_step = scroll_step
+        }
+        if let Some(scroll_max_step) = cfg.scroll_max_step {
+            config.scroll_max_step = Some(scroll_max_step)
+        }
+        if let Some(two_columns) = cfg.two_columns {
+            config.two_columns = two_columns
+        }
+        if let Some(line_numbers) = cfg.line_numbers {
+            config.line_numbers = line_numbers
+        }
+        if let Some(align_tables) = cfg.align_tables {
+            config.align_tables = align_tables
+        }
+        if let Some(tint_code) = cfg.tint_code {
+            config.tint_code = tint_code
+        }
+        if let Some(added_color) = cfg.added_color {
+            config.added_color = added_color
+        }
+        if let Some(removed_color) = cfg.removed_color {
+            config.removed_color = removed_color
+        }
+        if let Some(comment_color) = cfg.comment_color {
+            config.comment_color = Some(comment_color)
+        }
+        if let Some(link_hints) = cfg.link_hints {
+            config.link_hints = link_hints
+        }
+        if let Some(linkify) = cfg.linkify {
+            config.linkify = linkify
+        }
+        if let Some(favicons) = cfg.favicons {
+            config.favicons = favicons
+        }
+        if let Some(words_per_minute) = cfg.words_per_minute {
+            config.words_per_minute = words_per_minute
+        }
+        if let Some(download_dir) = cfg.download_dir {
+            config.download_dir = download_dir.into_boxed_path()
+        }
+        if let Some(resize_debounce_ms) = cfg.resize_debounce_ms {
+            config.resize_debounce_ms = resize_debounce_ms
+        }
+        if let Some(max_page_size) = cfg.max_page_size {
+            config.max_page_size = max_page_size
+        }
+        if let Some(truncate_large_pages) = cfg.truncate_large_pages {
+            config.truncate_large_pages = truncate_large_pages
+        }
+        if let Some(retries) = cfg.retries {
+            config.retries = retries
+        }
+        if let Some(retry_delay_ms) = cfg.retry_delay_ms {
+            config.retry_delay_ms = retry_delay_ms
+        }
+        if let Some(restore_session) = cfg.restore_session {
+            config.restore_session = restore_session
+        }
+        if let Some(private) = cfg.private {
+            config.private = private
+        }
+        if let Some(start_url) = cfg.start_url {
+            config.start_url = start_url
+        }
+        if let Some(home) = cfg.home {
+            config.home = Some(home)
+        }
+        if let Some(verbosity) = cfg.verbosity {
+            config.verbosity = verbosity
+        }
+        if let Some(dump) = cfg.dump {
+            config.dump = dump
+        }
+        if let Some(dump_width) = cfg.dump_width {
+            config.dump_width = dump_width
+        }
+        for (action, bindings) in cfg.bindings {
+            config.keymap.bind(action, bindings)
+        }
+    }
+    if !font_path.is_empty() {
+        config.font_path = font_path;
     }
 
-    Ok(config)
+    config
 }
 
 fn usage(bin: &str) {
@@ -181,22 +867,331 @@ fn usage(bin: &str) {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
     const DEFAULT_FG: u32 = u32::from_be_bytes(DEFAULT_FOREGROUND);
     const DEFAULT_BG: u32 = u32::from_be_bytes(DEFAULT_BACKGROUND);
+    const DEFAULT_ERROR: u32 = u32::from_be_bytes(DEFAULT_ERROR_COLOR);
+    const DEFAULT_ADDED: u32 = u32::from_be_bytes(DEFAULT_ADDED_COLOR);
+    const DEFAULT_REMOVED: u32 = u32::from_be_bytes(DEFAULT_REMOVED_COLOR);
 
     eprintln!("{DESCRIPTION}");
     eprintln!();
     eprintln!("Usage:");
-    eprintln!("    {bin} [OPTIONS]");
+    eprintln!("    {bin} [OPTIONS] [FONT_PATH] [URL]");
+    eprintln!("    The FONT_PATH is told apart from the URL by its '.{FONT_EXTENSION}' extension.");
     eprintln!();
     eprintln!("Options:");
     eprintln!("    --font-name -n    Set the font name from the default directory.");
     eprintln!("                      (default: '{DEFAULT_FONT}' in '{DEFAULT_FONT_DIR}')");
-    eprintln!("    --font-path -p    Set the font path.");
+    eprintln!("    --font-path -p    Set the font path. Given more than once, the fonts are");
+    eprintln!("                      tried in order until one of them loads.");
     eprintln!("    --fg              Specify the foreground color as an rgba hex string.");
     eprintln!("                      (default: {COLOR_PREFIX}{DEFAULT_FG:08x})");
     eprintln!("    --bg              Specify the background color as an rgba hex string.");
     eprintln!("                      (default: {COLOR_PREFIX}{DEFAULT_BG:08x})");
+    eprintln!("    --link-color      Specify the color of links as an rgba hex string.");
+    eprintln!("                      (default: the foreground color)");
+    eprintln!("    --heading-color   Specify the color of headings as an rgba hex string.");
+    eprintln!("                      (default: the foreground color)");
+    eprintln!("    --error-color     Specify the color of the banner of a page that failed to");
+    eprintln!("                      load as an rgba hex string.");
+    eprintln!("                      (default: {COLOR_PREFIX}{DEFAULT_ERROR:08x})");
+    eprintln!("    --theme           Set the foreground and background colors to a theme.");
+    eprintln!("                      Either 'light', 'dark', or 'solarized'.");
+    eprintln!("                      (default: 'light')");
+    eprintln!("    --line-spacing    Set the extra space between lines in pixels.");
+    eprintln!("                      (default: {DEFAULT_LINE_SPACING})");
+    eprintln!("    --margin          Set the space around the window contents in pixels.");
+    eprintln!("                      (default: {DEFAULT_MARGIN})");
+    eprintln!("    --max-content-width");
+    eprintln!("                      Set how many characters wide the text is at most, and");
+    eprintln!("                      center it in wider windows. 0 puts no cap on it.");
+    eprintln!("                      (default: {DEFAULT_MAX_CONTENT_WIDTH})");
+    eprintln!("    --natural-scrolling");
+    eprintln!("                      Let the mouse wheel move the content instead of the view.");
+    eprintln!("    --scroll-step     Set how far a scroll key or the mouse wheel scrolls in");
+    eprintln!("                      pixels.");
+    eprintln!("                      (default: {DEFAULT_SCROLL_STEP})");
+    eprintln!("    --scroll-max-step Let holding a scroll key speed up scrolling, up to this");
+    eprintln!("                      many pixels per key repeat.");
+    eprintln!("                      (default: no speeding up)");
+    eprintln!("    --two-columns     Show pages in two columns when the window is wide enough.");
+    eprintln!("    --line-numbers    Number the lines of a page in a gutter.");
+    eprintln!("    --align-tables    Line up the columns of tables drawn with pipes in");
+    eprintln!("                      preformatted blocks.");
+    eprintln!("    --tint-code       Tint the lines of preformatted blocks whose alt text is");
+    eprintln!("                      'diff' or 'shell', such as added and removed lines.");
+    eprintln!("    --added-color     Specify the color of added diff lines as an rgba hex");
+    eprintln!("                      string.");
+    eprintln!("                      (default: {COLOR_PREFIX}{DEFAULT_ADDED:08x})");
+    eprintln!("    --removed-color   Specify the color of removed diff lines as an rgba hex");
+    eprintln!("                      string.");
+    eprintln!("                      (default: {COLOR_PREFIX}{DEFAULT_REMOVED:08x})");
+    eprintln!("    --comment-color   Specify the color of shell comments as an rgba hex string.");
+    eprintln!("                      (default: the foreground color, dimmed)");
+    eprintln!("    --no-link-hints   Show links by their label alone, without the scheme and");
+    eprintln!("                      host that they lead to.");
+    eprintln!("    --linkify         Let bare URLs in the text of a page be picked in link mode");
+    eprintln!("                      and followed like links.");
+    eprintln!("    --favicons        Show the favicon.txt emoji of capsules next to their");
+    eprintln!("                      address, if the font can draw them.");
+    eprintln!("    --words-per-minute");
+    eprintln!("                      Set the reading speed that reading times are estimated");
+    eprintln!("                      with.");
+    eprintln!("                      (default: {DEFAULT_WORDS_PER_MINUTE})");
+    eprintln!("    --download-dir    Set the directory that saved pages are written to.");
+    eprintln!("                      (default: '{DEFAULT_DOWNLOAD_DIR}')");
+    eprintln!("    --resize-debounce");
+    eprintln!("                      Set how many milliseconds to wait for resizing to stop");
+    eprintln!("                      before laying out the page again.");
+    eprintln!("                      (default: {DEFAULT_RESIZE_DEBOUNCE_MS})");
+    eprintln!("    --max-page-size   Set how many bytes the body of a page may have. Larger");
+    eprintln!("                      pages are cut off, marked as truncated.");
+    eprintln!("                      (default: {DEFAULT_MAX_PAGE_SIZE})");
+    eprintln!("    --fail-large-pages");
+    eprintln!("                      Fail to load pages larger than the maximum page size,");
+    eprintln!("                      instead of cutting them off.");
+    eprintln!("    --retries         Set how many more times a request is tried when the");
+    eprintln!("                      connection drops or the server fails for now.");
+    eprintln!("                      (default: {DEFAULT_RETRIES})");
+    eprintln!("    --retry-delay     Set how many milliseconds to wait before the first retry,");
+    eprintln!("                      which doubles with every retry after it.");
+    eprintln!("                      (default: {DEFAULT_RETRY_DELAY_MS})");
+    eprintln!("    --url       -u    Set the page to show on startup. It may also be given as");
+    eprintln!("                      the last argument. A file may be given by its path, and");
+    eprintln!("                      '-' reads the page from standard input.");
+    eprintln!("                      (default: '{DEFAULT_START_URL}')");
+    eprintln!("    --no-restore      Do not pick up where the last session left off when no");
+    eprintln!("                      URL is given.");
+    eprintln!("    --private         Browse without writing the session to disk.");
+    eprintln!("    --dump            Print the page to stdout as text and exit, without");
+    eprintln!("                      opening a window.");
+    eprintln!("    --width           Set how many columns wide a dumped page is wrapped to.");
+    eprintln!("                      (default: {DEFAULT_DUMP_WIDTH})");
+    eprintln!("    --verbose         Log more about what is going on. May be repeated.");
+    eprintln!("    --quiet     -q    Log less about what is going on. May be repeated.");
+    eprintln!("                      Without either, the RUST_LOG environment variable");
+    eprintln!("                      sets the level of the log.");
+    eprintln!("                      (default: 'info')");
     eprintln!("    --version   -v    Display function.");
     eprintln!("    --help      -h    Display help.");
     eprintln!();
     eprintln!("{BIN} {VERSION} by {AUTHORS}, 2023.");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns what the command line `args` configure.
+    fn args(args: &[&str]) -> ConfigBuilder {
+        parse_args_from(Parser::from_args(args)).unwrap()
+    }
+
+    #[test]
+    fn line_spacing_is_read_from_file_and_command_line() {
+        assert_eq!(
+            parse_config("line_spacing 4").unwrap().line_spacing,
+            Some(4)
+        );
+        assert_eq!(args(&["--line-spacing", "2"]).line_spacing, Some(2));
+        assert_eq!(args(&[]).line_spacing, None);
+        assert!(parse_config("line_spacing wide").is_err());
+    }
+
+    #[test]
+    fn keys_are_bound_in_the_config_file() {
+        let cfg = parse_config("bind scroll_down n shift+j").unwrap();
+        assert_eq!(
+            cfg.bindings,
+            [(
+                Action::ScrollDown,
+                vec!["n".parse().unwrap(), "shift+j".parse().unwrap()]
+            )]
+        );
+        assert!(parse_config("bind scroll_down").is_err());
+        assert!(parse_config("bind scroll_down hyper").is_err());
+        assert!(parse_config("bind fly j").is_err());
+    }
+
+    #[test]
+    fn font_file_may_be_given_by_itself() {
+        let cfg = args(&["fonts/other.uf2"]);
+        assert_eq!(cfg.font_path, [PathBuf::from("fonts/other.uf2")]);
+        assert_eq!(cfg.start_url, None);
+    }
+
+    #[test]
+    fn natural_scrolling_is_read_from_file_and_command_line() {
+        assert!(!Config::default().natural_scrolling);
+        let file = parse_config("natural_scrolling yes").unwrap();
+        assert_eq!(file.natural_scrolling, Some(true));
+        assert_eq!(args(&["--natural-scrolling"]).natural_scrolling, Some(true));
+        assert!(parse_config("natural_scrolling sometimes").is_err());
+    }
+
+    #[test]
+    fn code_tinting_is_opted_into_with_its_colors() {
+        let default = merge(vec![]);
+        assert!(!default.tint_code);
+        assert_eq!(default.added_color, DEFAULT_ADDED_COLOR);
+        let config = merge(vec![
+            parse_config("tint_code on\nadded_color 0xff0000ff").unwrap()
+        ]);
+        assert!(config.tint_code);
+        assert_eq!(config.added_color, RED);
+        assert!(merge(vec![args(&["--tint-code"])]).tint_code);
+    }
+
+    #[test]
+    fn start_page_is_taken_from_the_command_line() {
+        let url = "gemini://example.org/";
+        assert_eq!(args(&["--url", url]).start_url.as_deref(), Some(url));
+        assert_eq!(args(&["-u", url]).start_url.as_deref(), Some(url));
+        assert_eq!(args(&[url]).start_url.as_deref(), Some(url));
+        assert_eq!(args(&[]).start_url, None);
+        assert_eq!(Config::default().start_url, DEFAULT_START_URL);
+    }
+
+    #[test]
+    fn start_page_must_be_an_address() {
+        let args = ["--url", "no such page"];
+        assert!(parse_args_from(Parser::from_args(args)).is_err());
+    }
+
+    #[test]
+    fn home_page_is_read_from_the_config_file() {
+        let url = "gemini://example.org/home.gmi";
+        let cfg = parse_config(&format!("home {url}")).unwrap();
+        assert_eq!(cfg.home.as_deref(), Some(url));
+        assert_eq!(parse_config("").unwrap().home, None);
+        assert_eq!(Config::default().home, None);
+        assert!(parse_config("home").is_err());
+    }
+
+    #[test]
+    fn resize_debounce_is_read_from_file_and_command_line() {
+        let default = Config::default();
+        assert_eq!(default.resize_debounce_ms, DEFAULT_RESIZE_DEBOUNCE_MS);
+        let file = parse_config("resize_debounce 250").unwrap();
+        assert_eq!(file.resize_debounce_ms, Some(250));
+        let cli = args(&["--resize-debounce", "0"]);
+        assert_eq!(cli.resize_debounce_ms, Some(0));
+        assert!(parse_config("resize_debounce soon").is_err());
+    }
+
+    #[test]
+    fn margin_is_set_for_every_side_and_then_per_side() {
+        assert_eq!(Config::default().margin.left, DEFAULT_MARGIN);
+        let sides = |cfg: ConfigBuilder| {
+            [
+                cfg.margin_top,
+                cfg.margin_right,
+                cfg.margin_bottom,
+                cfg.margin_left,
+            ]
+        };
+        assert_eq!(sides(args(&["--margin", "1"])), [Some(1); 4]);
+        let cfg = parse_config("margin 4\nmargin_top 9").unwrap();
+        assert_eq!(sides(cfg), [Some(9), Some(4), Some(4), Some(4)]);
+    }
+
+    const RED: Pixel = [0xff, 0x00, 0x00, 0xff];
+
+    #[test]
+    fn explicit_colors_win_over_a_later_theme() {
+        let file = parse_config("foreground 0xff0000ff").unwrap();
+        let mut command_line = ConfigBuilder::default();
+        command_line.set_theme(parse_theme("dark").unwrap());
+        let config = merge(vec![file, command_line]);
+        let (_, dark_foreground, dark_background) = THEMES[1];
+        assert_eq!(config.foreground, RED);
+        assert_ne!(config.foreground, dark_foreground);
+        assert_eq!(config.background, dark_background);
+    }
+
+    #[test]
+    fn later_theme_wins_over_earlier_theme() {
+        let file = parse_config("theme dark").unwrap();
+        let command_line = parse_config("theme solarized").unwrap();
+        let config = merge(vec![file, command_line]);
+        let (_, foreground, background) = THEMES[2];
+        assert_eq!(
+            (config.foreground, config.background),
+            (foreground, background)
+        );
+    }
+
+    #[test]
+    fn link_and_heading_colors_are_parsed_like_the_others() {
+        let config = merge(vec![parse_config("heading_color 0xff0000ff").unwrap()]);
+        assert_eq!(config.heading_color, Some(RED));
+        assert_eq!(config.link_color, None);
+        let config = merge(vec![args(&["--link-color", "0xff0000ff"])]);
+        assert_eq!(config.link_color, Some(RED));
+        assert!(parse_config("link_color red").is_err());
+    }
+
+    #[test]
+    fn verbosity_adds_up_from_the_flags() {
+        assert_eq!(merge(vec![args(&[])]).verbosity, 0);
+        assert_eq!(merge(vec![args(&["--verbose", "--verbose"])]).verbosity, 2);
+        assert_eq!(
+            merge(vec![args(&["-q", "--quiet", "--verbose"])]).verbosity,
+            -1
+        );
+    }
+
+    #[test]
+    fn scroll_step_is_read_from_file_and_command_line() {
+        let config = merge(vec![]);
+        assert_eq!(
+            (config.scroll_step, config.scroll_max_step),
+            (DEFAULT_SCROLL_STEP, None)
+        );
+        let file = parse_config("scroll_step 20\nscroll_max_step 60").unwrap();
+        let config = merge(vec![file, args(&["--scroll-step", "4"])]);
+        assert_eq!((config.scroll_step, config.scroll_max_step), (4, Some(60)));
+    }
+
+    #[test]
+    fn session_is_restored_unless_a_page_is_asked_for() {
+        assert!(merge(vec![args(&[])]).restore_session);
+        assert!(!merge(vec![args(&["--no-restore"])]).restore_session);
+        assert!(!merge(vec![args(&["gemini://example.org/"])]).restore_session);
+        assert!(!merge(vec![args(&["--url", "gemini://example.org/"])]).restore_session);
+        assert!(!merge(vec![parse_config("restore_session false").unwrap()]).restore_session);
+    }
+
+    #[test]
+    fn private_browsing_is_asked_for_on_the_command_line() {
+        assert!(!merge(vec![args(&[])]).private);
+        assert!(merge(vec![args(&["--private"])]).private);
+    }
+
+    #[test]
+    fn page_size_limit_is_read_from_file_and_command_line() {
+        let default = merge(vec![]);
+        assert_eq!(default.max_page_size, 10 * 1024 * 1024);
+        assert!(default.truncate_large_pages);
+        let file = || parse_config("max_page_size 4096").unwrap();
+        assert_eq!(merge(vec![file()]).max_page_size, 4096);
+        let cli = args(&["--max-page-size", "100", "--fail-large-pages"]);
+        let config = merge(vec![file(), cli]);
+        assert_eq!(config.max_page_size, 100);
+        assert!(!config.truncate_large_pages);
+    }
+
+    #[test]
+    fn font_paths_add_up_with_the_later_ones_first() {
+        let file = parse_config("font_path file/a.uf2\nfont_path file/b.uf2").unwrap();
+        assert_eq!(
+            file.font_path,
+            [PathBuf::from("file/a.uf2"), PathBuf::from("file/b.uf2")]
+        );
+        let cli = args(&["-p", "cli/a.uf2", "--font-path", "cli/b.uf2"]);
+        let config = merge(vec![file, cli]);
+        let expected = ["cli/a.uf2", "cli/b.uf2", "file/a.uf2", "file/b.uf2"];
+        let expected: Vec<_> = expected.map(|path| Path::new(path).into()).into();
+        assert_eq!(config.font_path, expected);
+        // Without any named, the default font is used.
+        assert_eq!(merge(vec![]).font_path, Config::default().font_path);
+    }
+}
@@ -0,0 +1,82 @@
+//! Holding on to rapidly changing values until they settle.
+
+use std::time::{Duration, Instant};
+
+/// Coalesces a burst of values into the last one, which is let through once no new value has
+/// come in for the `interval`.
+pub struct Debounce<T> {
+    interval: Duration,
+    pending: Option<(T, Instant)>,
+}
+
+impl<T> Debounce<T> {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            pending: None,
+        }
+    }
+
+    /// Hold on to `value`, which replaces any value that is still pending, and start waiting anew
+    /// from `now`.
+    pub fn push(&mut self, value: T, now: Instant) {
+        self.pending = Some((value, now));
+    }
+
+    /// Returns when the pending value becomes ready, if there is one.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .as_ref()
+            .map(|(_, since)| *since + self.interval)
+    }
+
+    /// Returns the pending value if it was pushed at least the interval before `now`.
+    pub fn take_ready(&mut self, now: Instant) -> Option<T> {
+        match self.pending {
+            Some((_, since)) if now.saturating_duration_since(since) >= self.interval => {
+                self.pending.take().map(|(value, _)| value)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn burst_is_coalesced_into_its_last_value() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(INTERVAL);
+        assert_eq!(debounce.take_ready(start), None);
+        for (i, size) in [(0, 100), (30, 200), (60, 300)] {
+            debounce.push(size, start + Duration::from_millis(i));
+            assert_eq!(debounce.take_ready(start + Duration::from_millis(i)), None);
+        }
+        assert_eq!(
+            debounce.take_ready(start + Duration::from_millis(159)),
+            None
+        );
+        assert_eq!(
+            debounce.take_ready(start + Duration::from_millis(160)),
+            Some(300)
+        );
+        assert_eq!(debounce.take_ready(start + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn push_starts_the_wait_anew() {
+        let start = Instant::now();
+        let mut debounce = Debounce::new(INTERVAL);
+        debounce.push(1, start);
+        debounce.push(2, start + Duration::from_millis(90));
+        assert_eq!(debounce.take_ready(start + INTERVAL), None);
+        assert_eq!(
+            debounce.take_ready(start + Duration::from_millis(190)),
+            Some(2)
+        );
+    }
+}
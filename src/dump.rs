@@ -0,0 +1,97 @@
+//! Printing pages as text, to read them without opening a window.
+
+use gemininini::elements::WrappedText;
+use gemininini::gemtext::{self, Line};
+
+use crate::links::{link_text, LINK_PREFIX};
+
+/// How many columns a character takes up when dumped.
+const CHAR_WIDTH: u32 = 1;
+
+/// Returns the `text` of a page laid out as it is in the window, but wrapped to `width` columns.
+/// The text is only read as gemtext if it is `gemtext`.
+///
+/// Like in the window, every line is wrapped on its own, except for preformatted lines, which are
+/// left as they are. Links are shown by their label after the prefix of a link line, followed by
+/// the hint of where they lead, if there are to be `hints`, which are resolved against the `base`
+/// address of the page. The lines that they wrap onto hang under the label.
+pub fn render(text: &str, gemtext: bool, base: &str, hints: bool, width: u32) -> String {
+    let mut rendered = String::new();
+    for (line, parsed) in gemtext::parse_as(text, gemtext) {
+        let preformatted = matches!(parsed, Line::Preformatted(_));
+        let cleaned = gemtext::clean(line, preformatted);
+        if preformatted {
+            rendered.push_str(&cleaned);
+        } else {
+            let (shown, indent) = match parsed {
+                Line::Link { url, label } => {
+                    let (text, hint) = link_text(url, label, base, hints);
+                    let text = gemtext::clean(text, false);
+                    let shown = match hint {
+                        Some(hint) => format!("{LINK_PREFIX}{text} {hint}"),
+                        None => format!("{LINK_PREFIX}{text}"),
+                    };
+                    (shown, LINK_PREFIX.chars().count() as u32 * CHAR_WIDTH)
+                }
+                _ => (cleaned.into_owned(), 0),
+            };
+            let wrapped = WrappedText::with_glyph_widths(shown, width, indent, |_| CHAR_WIDTH);
+            for (idx, line) in wrapped.lines().enumerate() {
+                if idx > 0 {
+                    rendered.push('\n');
+                }
+                let columns = wrapped.indent_of(idx) / CHAR_WIDTH;
+                rendered.push_str(&" ".repeat(columns as usize));
+                rendered.push_str(line);
+            }
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "gemini://example.org/";
+    const PAGE: &str = "# Welcome\n\
+        Some text that goes on for a while.\n\
+        => gemini://example.org/about.gmi About this capsule\n\
+        => gemini://other.example/ Elsewhere\n\
+        ```\n\
+        a preformatted line that is not wrapped\n\
+        ```\n";
+
+    #[test]
+    fn page_is_dumped_as_in_the_window() {
+        let rendered = render(PAGE, true, BASE, true, 20);
+        let expected = "# Welcome\n\
+            Some text that goes\n\
+            on for a while.\n\
+            => About this\n\
+            \x20  capsule [gmi]\n\
+            \x20  [gemini://example\n\
+            \x20  .org]\n\
+            => Elsewhere\n\
+            \x20  [gemini://other.e\n\
+            \x20  xample]\n\
+            ```\n\
+            a preformatted line that is not wrapped\n\
+            ```\n";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn links_without_hints_are_their_label() {
+        let rendered = render(PAGE, true, BASE, false, 80);
+        assert!(rendered.contains("\n=> About this capsule [gmi]\n"));
+        assert!(rendered.contains("\n=> Elsewhere\n"));
+    }
+
+    #[test]
+    fn plain_text_is_not_read_as_gemtext() {
+        let rendered = render("=> gemini://example.org/ Not a link", false, BASE, true, 80);
+        assert_eq!(rendered, "=> gemini://example.org/ Not a link\n");
+    }
+}
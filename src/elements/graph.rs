@@ -3,10 +3,12 @@ use std::collections::VecDeque;
 use crate::{elements::Style, Pixel};
 
 // TODO: (easy) Isn't there a std lib type for this?! I'm pretty sure there is. Just moving on now.
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 struct Range {
     min: Option<f32>,
     max: Option<f32>,
+    /// The fraction of the span of the values that an automatic `min` or `max` is widened by.
+    headroom: f32,
 }
 
 impl Range {
@@ -45,6 +47,15 @@ impl Graph {
         self
     }
 
+    /// Widen the automatic `min` and `max` by a fraction of the span of the values, such as `0.1`
+    /// for 10%, so the extremes are not drawn right against the edges.
+    ///
+    /// A `min` or `max` that is set explicitly is left as it is.
+    pub fn with_headroom(mut self, headroom: f32) -> Self {
+        self.1.headroom = headroom;
+        self
+    }
+
     pub fn push(&mut self, value: f32) {
         let Self(inner, _) = self;
         let size = inner.len();
@@ -53,6 +64,14 @@ impl Graph {
         inner.push_front(value)
     }
 
+    /// Change the number of values to `new_size`, keeping the newest ones.
+    ///
+    /// Growing adds zeroes as the oldest values, and shrinking drops the oldest values.
+    pub fn resize(&mut self, new_size: usize) {
+        // The newest value is at the front, so the oldest ones are at the back.
+        self.0.resize(new_size, 0.0);
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
@@ -76,7 +95,7 @@ impl Graph {
         if let Some(min) = self.1.min {
             return min;
         }
-        self.iter().fold(f32::INFINITY, f32::min)
+        self.lowest() - self.headroom()
     }
 
     pub fn max(&self) -> f32 {
@@ -86,9 +105,61 @@ impl Graph {
         if let Some(max) = self.1.max {
             return max;
         }
+        self.highest() + self.headroom()
+    }
+
+    /// Returns the lowest of the values, as clamped to the range.
+    fn lowest(&self) -> f32 {
+        self.iter().fold(f32::INFINITY, f32::min)
+    }
+
+    /// Returns the highest of the values, as clamped to the range.
+    fn highest(&self) -> f32 {
         self.iter().fold(f32::NEG_INFINITY, f32::max)
     }
 
+    /// Returns how far an automatic `min` or `max` lies beyond the values.
+    fn headroom(&self) -> f32 {
+        self.1.headroom * (self.highest() - self.lowest())
+    }
+
+    /// Returns the mean of the values, as clamped to the range.
+    pub fn mean(&self) -> f32 {
+        if self.is_empty() {
+            return Default::default();
+        }
+        self.iter().sum::<f32>() / self.len() as f32
+    }
+
+    /// Returns the population standard deviation of the values, as clamped to the range.
+    pub fn stddev(&self) -> f32 {
+        if self.is_empty() {
+            return Default::default();
+        }
+        let mean = self.mean();
+        let variance = self.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / self.len() as f32;
+        variance.sqrt()
+    }
+
+    /// Returns a new [`Graph`] of the same length and range, where every value is the mean of
+    /// itself and the `window - 1` values that came before it.
+    ///
+    /// The oldest values have fewer values before them, so they are averaged over those alone. A
+    /// `window` larger than the length is clamped to the length.
+    pub fn moving_average(&self, window: usize) -> Self {
+        let window = window.clamp(1, self.len().max(1));
+        let values: Vec<f32> = self.iter().collect();
+        let smoothed = (0..values.len())
+            .map(|idx| {
+                // The values are ordered from new to old, so the ones before come after.
+                let end = (idx + window).min(values.len());
+                let span = &values[idx..end];
+                span.iter().sum::<f32>() / span.len() as f32
+            })
+            .collect();
+        Self(smoothed, self.1)
+    }
+
     /// Paint the graph onto a pixel buffer with a specified height.
     ///
     /// All values represented within the graph are mapped the pixels, such that `min` point of the
@@ -123,3 +194,75 @@ impl From<VecDeque<f32>> for Graph {
         Self(deque, Range::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a [`Graph`] of the `values`, from the newest to the oldest.
+    fn graph(values: &[f32]) -> Graph {
+        Graph::from(values.iter().copied().collect::<VecDeque<_>>())
+    }
+
+    #[test]
+    fn mean_and_stddev_of_a_known_sequence() {
+        let values = graph(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(values.mean(), 5.0);
+        assert_eq!(values.stddev(), 2.0);
+        assert_eq!(graph(&[]).mean(), 0.0);
+        assert_eq!(graph(&[]).stddev(), 0.0);
+        // The values are clamped to the range first.
+        let clamped = graph(&[1.0, 2.0, 3.0, 4.0, 5.0]).with_range(0.0, 4.0);
+        assert_eq!(clamped.mean(), 2.8);
+    }
+
+    #[test]
+    fn moving_average_over_three_values() {
+        let values = graph(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let smoothed: Vec<_> = values.moving_average(3).iter().collect();
+        assert_eq!(smoothed, [2.0, 3.0, 4.0, 4.5, 5.0]);
+        let whole: Vec<_> = values.moving_average(100).iter().collect();
+        assert_eq!(whole, [3.0, 3.5, 4.0, 4.5, 5.0]);
+        let same: Vec<_> = values.moving_average(0).iter().collect();
+        assert_eq!(same, [1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert!(graph(&[]).moving_average(3).is_empty());
+    }
+
+    #[test]
+    fn growing_pads_the_oldest_side() {
+        let mut values = graph(&[3.0, 2.0, 1.0]);
+        values.resize(5);
+        assert_eq!(values.iter().collect::<Vec<_>>(), [3.0, 2.0, 1.0, 0.0, 0.0]);
+        values.push(4.0);
+        assert_eq!(values.iter().collect::<Vec<_>>(), [4.0, 3.0, 2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn shrinking_drops_the_oldest_values() {
+        let mut values = graph(&[3.0, 2.0, 1.0]);
+        values.resize(2);
+        assert_eq!(values.iter().collect::<Vec<_>>(), [3.0, 2.0]);
+        values.push(4.0);
+        assert_eq!(values.iter().collect::<Vec<_>>(), [4.0, 3.0]);
+        values.resize(0);
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn headroom_widens_an_automatic_range() {
+        let values = [2.0, 12.0, 7.0];
+        assert_eq!((graph(&values).min(), graph(&values).max()), (2.0, 12.0));
+        let padded = graph(&values).with_headroom(0.1);
+        assert_eq!((padded.min(), padded.max()), (1.0, 13.0));
+        let half = graph(&values).with_min(0.0).with_headroom(0.1);
+        assert_eq!((half.min(), half.max()), (0.0, 13.0));
+    }
+
+    #[test]
+    fn headroom_is_ignored_for_an_explicit_range() {
+        let values = graph(&[2.0, 12.0, 7.0])
+            .with_range(0.0, 10.0)
+            .with_headroom(0.5);
+        assert_eq!((values.min(), values.max()), (0.0, 10.0));
+    }
+}
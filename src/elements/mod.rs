@@ -1,11 +1,14 @@
+use std::cell::Cell;
 use std::rc::Rc;
+use std::time::Duration;
 
 use fleck::Font;
 pub use graph::Graph;
 pub use wrapped_text::WrappedText;
 
 use crate::block::DrawBlock;
-use crate::{Block, Pixel};
+use crate::glyph_cache::GlyphCache;
+use crate::{Action, Block, Pixel};
 
 pub mod graph;
 pub mod wrapped_text;
@@ -59,6 +62,26 @@ impl Dimensions {
     }
 }
 
+/// The area an [`Element`] was last drawn at, relative to the top left corner of its parent.
+///
+/// The position can be negative, or fall outside of the parent, when the `Element` is scrolled
+/// out of view.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns whether the point (`x`, `y`) lies within this [`Rect`].
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        (self.x..self.x + self.width as i64).contains(&x)
+            && (self.y..self.y + self.height as i64).contains(&y)
+    }
+}
+
 type Pad = u32;
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -69,6 +92,13 @@ pub struct Padding {
     pub right: Pad,
 }
 
+/// A frame drawn just inside the bounds of an [`Element`], around its padding.
+#[derive(Debug, Clone, Copy)]
+pub struct Border {
+    pub color: Pixel,
+    pub thickness: u32,
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Flex {
     pub top: bool,
@@ -92,8 +122,20 @@ impl Flex {
 #[derive(Debug, Clone)]
 pub struct Style {
     pub foreground: Pixel,
+    /// The background color.
+    ///
+    /// Unless `own_background` is set, this is inherited from the parent [`Element`] when
+    /// [`Panel::update`](crate::Panel::update) is called.
     pub background: Pixel,
+    /// Whether `background` was chosen for this [`Element`] specifically.
+    pub own_background: bool,
     pub font: Rc<Font>,
+    /// Extra vertical space in pixels below every line of text.
+    pub line_spacing: u32,
+    /// The direction in which text is laid out.
+    pub direction: Direction,
+    /// The glyphs of `font` that have been drawn so far.
+    pub(crate) glyphs: GlyphCache,
 }
 
 impl Style {
@@ -102,7 +144,11 @@ impl Style {
         Self {
             foreground,
             background,
+            own_background: false,
             font,
+            line_spacing: 0,
+            direction: Direction::LeftToRight,
+            glyphs: GlyphCache::default(),
         }
     }
 
@@ -112,25 +158,65 @@ impl Style {
         Self {
             foreground: [0x00, 0x00, 0x00, 0xff],
             background: [0xff, 0xff, 0xff, 0xff],
+            own_background: false,
             font,
+            line_spacing: 0,
+            direction: Direction::LeftToRight,
+            glyphs: GlyphCache::default(),
         }
     }
+
+    /// Returns the vertical distance between the tops of two consecutive lines of text.
+    pub fn line_height(&self) -> u32 {
+        self.font.height() as u32 + self.line_spacing
+    }
 }
 
 pub struct Element<D> {
     pub size: Size,
     pub padding: Padding,
+    pub border: Option<Border>,
     pub flex: Flex,
     pub style: Style,
     update: Option<UpdateFn<D>>,
     pub content: Content<D>,
+    /// Where the content goes within the height of this `Element`, and where this `Element` goes
+    /// within the height of a [`Content::Row`].
+    pub valign: VAlignment,
     pub scroll: Option<u32>,
+    /// How many pixels the line of a [`Content::Text`] is shifted to the left, which cuts off its
+    /// start.
+    pub hscroll: u32,
+    /// The style that this `Element` is drawn with instead of its `style` while it is hovered.
+    pub hover_style: Option<Style>,
+    /// Whether the pointer was over this `Element` as of the last [`Element::hover`].
+    hovered: bool,
+    /// The name by which this `Element` is found with [`Element::find_by_tag`], if it has one.
+    pub tag: Option<String>,
+    /// Where this `Element` was drawn last, as set by its parent while drawing.
+    bounds: Cell<Rect>,
+    /// The [`Action`]s this `Element` and its children asked for since the last update, which
+    /// are handed up to the [`Panel`](crate::Panel).
+    pub(crate) actions: Vec<Action>,
+    /// The shortest interval that this `Element` or its children asked to be drawn again after
+    /// since the last update, which is handed up to the [`Panel`](crate::Panel).
+    pub(crate) redraw_interval: Option<Duration>,
 }
 
 pub enum Content<D> {
     Text(String, Alignment),
     Paragraph(WrappedText, Alignment),
-    Custom { buf: Vec<Pixel>, height: u32 },
+    Custom {
+        buf: Vec<Pixel>,
+        height: u32,
+    },
+    /// Empty space that takes up the room left in its [`Content::Row`] or [`Content::Stack`],
+    /// shared with other spacers in proportion to its weight.
+    Spacer(u32),
+    /// A horizontal line in the foreground color that spans the width of the [`Element`].
+    ///
+    /// The thickness of the line is the height of the `Element`, which is 1 unless set otherwise.
+    Rule,
     Row(Vec<Element<D>>),
     Stack(Vec<Element<D>>),
 }
@@ -140,6 +226,8 @@ pub mod builder {
 
     pub trait ElementBuilder<D> {
         fn with_update(self, update: UpdateFn<D>) -> Self;
+        /// Give the element a `tag`, by which it can be found with [`Element::find_by_tag`].
+        fn with_tag(self, tag: &str) -> Self;
         fn build(self) -> Element<D>;
     }
 
@@ -151,6 +239,7 @@ pub mod builder {
     pub struct ContainerBuilder<D> {
         font: Rc<Font>,
         update: Option<UpdateFn<D>>,
+        tag: Option<String>,
         kind: ContainerKind,
         children: Vec<Element<D>>,
     }
@@ -161,12 +250,20 @@ pub mod builder {
             self
         }
 
+        fn with_tag(mut self, tag: &str) -> Self {
+            self.tag = Some(tag.to_string());
+            self
+        }
+
         fn build(self) -> Element<D> {
             let content = match self.kind {
                 ContainerKind::Stack => Content::Stack(self.children),
                 ContainerKind::Row => Content::Row(self.children),
             };
-            Element::new(self.update, content, self.font)
+            Element {
+                tag: self.tag,
+                ..Element::new(self.update, content, self.font)
+            }
         }
     }
 
@@ -175,6 +272,7 @@ pub mod builder {
             Self {
                 font: Rc::clone(font),
                 update: None,
+                tag: None,
                 kind: ContainerKind::Row,
                 children: Vec::new(),
             }
@@ -184,6 +282,7 @@ pub mod builder {
             Self {
                 font: Rc::clone(font),
                 update: None,
+                tag: None,
                 kind: ContainerKind::Stack,
                 children: Vec::new(),
             }
@@ -203,6 +302,7 @@ pub mod builder {
     pub struct TextBuilder<D> {
         font: Rc<Font>,
         update: Option<UpdateFn<D>>,
+        tag: Option<String>,
         alignment: Alignment,
         string: String,
     }
@@ -213,9 +313,17 @@ pub mod builder {
             self
         }
 
+        fn with_tag(mut self, tag: &str) -> Self {
+            self.tag = Some(tag.to_string());
+            self
+        }
+
         fn build(self) -> Element<D> {
             let content = Content::Text(self.string, self.alignment);
-            Element::new(self.update, content, self.font)
+            Element {
+                tag: self.tag,
+                ..Element::new(self.update, content, self.font)
+            }
         }
     }
 
@@ -224,6 +332,7 @@ pub mod builder {
             Self {
                 font: Rc::clone(font),
                 update: None,
+                tag: None,
                 alignment: Default::default(),
                 string: Default::default(),
             }
@@ -247,8 +356,10 @@ pub mod builder {
     pub struct ParagraphBuilder<D> {
         font: Rc<Font>,
         update: Option<UpdateFn<D>>,
+        tag: Option<String>,
         alignment: Alignment,
         width: Option<u32>,
+        hanging_indent: u32,
         string: String,
     }
 
@@ -258,10 +369,23 @@ pub mod builder {
             self
         }
 
+        fn with_tag(mut self, tag: &str) -> Self {
+            self.tag = Some(tag.to_string());
+            self
+        }
+
         fn build(self) -> Element<D> {
-            let wrapped = WrappedText::new_without_width(self.string, self.width, &self.font);
+            let wrapped = WrappedText::new_without_width(
+                self.string,
+                self.width,
+                self.hanging_indent,
+                &self.font,
+            );
             let content = Content::Paragraph(wrapped, self.alignment);
-            Element::new(self.update, content, self.font)
+            Element {
+                tag: self.tag,
+                ..Element::new(self.update, content, self.font)
+            }
         }
     }
 
@@ -270,8 +394,10 @@ pub mod builder {
             Self {
                 font: Rc::clone(font),
                 update: None,
+                tag: None,
                 alignment: Default::default(),
                 width: None,
+                hanging_indent: 0,
                 string: Default::default(),
             }
         }
@@ -287,12 +413,97 @@ pub mod builder {
             self
         }
 
+        /// Indent the lines that continue a wrapped line by `indent` pixels, such as to hang them
+        /// under the text that follows a prefix on the first line.
+        pub fn with_hanging_indent(mut self, indent: u32) -> Self {
+            self.hanging_indent = indent;
+            self
+        }
+
         pub fn with_alignment(mut self, alignment: Alignment) -> Self {
             self.alignment = alignment;
             self
         }
     }
 
+    pub struct SpacerBuilder<D> {
+        font: Rc<Font>,
+        update: Option<UpdateFn<D>>,
+        tag: Option<String>,
+        weight: u32,
+    }
+
+    impl<D> ElementBuilder<D> for SpacerBuilder<D> {
+        fn with_update(mut self, update: UpdateFn<D>) -> Self {
+            self.update = Some(update);
+            self
+        }
+
+        fn with_tag(mut self, tag: &str) -> Self {
+            self.tag = Some(tag.to_string());
+            self
+        }
+
+        fn build(self) -> Element<D> {
+            Element {
+                tag: self.tag,
+                ..Element::new(self.update, Content::Spacer(self.weight), self.font)
+            }
+        }
+    }
+
+    impl<D> SpacerBuilder<D> {
+        fn spacer(font: &Rc<Font>) -> Self {
+            Self {
+                font: Rc::clone(font),
+                update: None,
+                tag: None,
+                weight: 1,
+            }
+        }
+
+        /// Set the weight with which the spacer takes up the room left by its siblings.
+        pub fn with_weight(mut self, weight: u32) -> Self {
+            self.weight = weight;
+            self
+        }
+    }
+
+    pub struct RuleBuilder<D> {
+        font: Rc<Font>,
+        update: Option<UpdateFn<D>>,
+        tag: Option<String>,
+    }
+
+    impl<D> ElementBuilder<D> for RuleBuilder<D> {
+        fn with_update(mut self, update: UpdateFn<D>) -> Self {
+            self.update = Some(update);
+            self
+        }
+
+        fn with_tag(mut self, tag: &str) -> Self {
+            self.tag = Some(tag.to_string());
+            self
+        }
+
+        fn build(self) -> Element<D> {
+            Element {
+                tag: self.tag,
+                ..Element::new(self.update, Content::Rule, self.font)
+            }
+        }
+    }
+
+    impl<D> RuleBuilder<D> {
+        fn rule(font: &Rc<Font>) -> Self {
+            Self {
+                font: Rc::clone(font),
+                update: None,
+                tag: None,
+            }
+        }
+    }
+
     impl<D> Element<D> {
         pub fn row_builder(font: &Rc<Font>) -> ContainerBuilder<D> {
             ContainerBuilder::row(font)
@@ -317,6 +528,14 @@ pub mod builder {
         pub fn empty_paragraph(font: &Rc<Font>) -> ParagraphBuilder<D> {
             ParagraphBuilder::paragraph(font)
         }
+
+        pub fn spacer(font: &Rc<Font>) -> SpacerBuilder<D> {
+            SpacerBuilder::spacer(font)
+        }
+
+        pub fn rule(font: &Rc<Font>) -> RuleBuilder<D> {
+            RuleBuilder::rule(font)
+        }
     }
 }
 
@@ -326,11 +545,20 @@ impl<D> Element<D> {
         Self {
             size: Default::default(),
             padding: Default::default(),
+            border: Default::default(),
             flex: Default::default(),
             style: Style::default_with_font(font),
             update,
             content,
+            valign: Default::default(),
             scroll: Default::default(),
+            hscroll: Default::default(),
+            hover_style: Default::default(),
+            hovered: Default::default(),
+            tag: Default::default(),
+            bounds: Default::default(),
+            actions: Default::default(),
+            redraw_interval: Default::default(),
         }
     }
 
@@ -394,6 +622,27 @@ impl<D> Element<D> {
     }
 
     /* padding */
+    /// Set the padding on each side, in the same order as CSS does: clockwise from the top.
+    pub fn with_padding(mut self, top: Pad, right: Pad, bottom: Pad, left: Pad) -> Self {
+        self.padding = Padding {
+            top,
+            bottom,
+            left,
+            right,
+        };
+        self
+    }
+
+    /// Set the same padding on all sides.
+    pub fn with_padding_all(self, padding: Pad) -> Self {
+        self.with_padding(padding, padding, padding, padding)
+    }
+
+    /// Set the padding on the left and right to `x`, and on the top and bottom to `y`.
+    pub fn with_padding_xy(self, x: Pad, y: Pad) -> Self {
+        self.with_padding(y, x, y, x)
+    }
+
     pub fn with_padding_top(mut self, padding: Pad) -> Self {
         self.padding.top = padding;
         self
@@ -446,8 +695,32 @@ impl<D> Element<D> {
         self
     }
 
+    /// Set a background for this [`Element`] and its children, instead of the one it would
+    /// otherwise inherit from its parent.
     pub fn with_background(mut self, background: Pixel) -> Self {
         self.style.background = background;
+        self.style.own_background = true;
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: u32) -> Self {
+        self.style.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.style.direction = direction;
+        self
+    }
+
+    /* border */
+    pub fn with_border(mut self, color: Pixel, thickness: u32) -> Self {
+        self.border = Some(Border { color, thickness });
+        self
+    }
+
+    pub fn with_valign(mut self, valign: VAlignment) -> Self {
+        self.valign = valign;
         self
     }
 
@@ -455,9 +728,34 @@ impl<D> Element<D> {
         self.scroll = Some(scroll);
         self
     }
+
+    pub fn with_hscroll(mut self, hscroll: u32) -> Self {
+        self.hscroll = hscroll;
+        self
+    }
+
+    /// Set a style to draw this [`Element`] with while the pointer is over it.
+    ///
+    /// Only the way it is drawn changes, so the `hover_style` should have the same font and line
+    /// spacing as its regular style.
+    pub fn with_hover_style(mut self, hover_style: Style) -> Self {
+        self.hover_style = Some(hover_style);
+        self
+    }
 }
 
 impl<D> Element<D> {
+    /// Lay out this [`Element`] and its children for the `data`, and return its overall size.
+    ///
+    /// This runs the `update` functions and bakes the sizes, just like
+    /// [`Panel::update`](crate::Panel::update) does, but nothing is drawn. Text is wrapped to the
+    /// `maxwidth_hint` where the `Element`s do not set a `maxwidth` of their own.
+    pub fn measure(&mut self, data: &D, maxwidth_hint: Option<u32>) -> Dimensions {
+        self.update(data);
+        self.bake_size(maxwidth_hint);
+        self.overall_size()
+    }
+
     /// Configure the internal size information based on the [`Element`]'s content and constraints.
     ///
     /// For container [`Content`]s, this method is called recursively.
@@ -495,22 +793,34 @@ impl<D> Element<D> {
         let height;
         match &mut self.content {
             Content::Text(text, _) => {
-                width = self.style.font.determine_width(text) as u32;
-                height = self.style.font.height() as u32;
+                width = self.style.glyphs.width(&self.style.font, text) as u32;
+                height = self.style.line_height();
             }
             Content::Paragraph(wrapped, _) => {
                 wrapped.rewrap(self.size.maxwidth.or(maxwidth_hint), &self.style.font);
                 width = wrapped
                     .lines()
-                    .map(|line| self.style.font.determine_width(line) as u32)
+                    .enumerate()
+                    .map(|(idx, line)| {
+                        let line_width = self.style.glyphs.width(&self.style.font, line) as u32;
+                        wrapped.indent_of(idx) + line_width
+                    })
                     .max()
                     .unwrap_or_default();
-                height = (self.style.font.height() * wrapped.lines_count()) as u32;
+                height = self.style.line_height() * wrapped.lines_count() as u32;
             }
             Content::Custom { buf, height: h } => {
                 width = buf.len() as u32 / *h;
                 height = *h;
             }
+            Content::Spacer(_) => {
+                width = 0;
+                height = 0;
+            }
+            Content::Rule => {
+                width = self.size.maxwidth.or(maxwidth_hint).unwrap_or_default();
+                height = 1;
+            }
             Content::Row(children) | Content::Stack(children) => {
                 // TODO: See whether this collect alloc can be eliminated. Perhaps unzip?
                 let sizes: Vec<_> = children
@@ -552,6 +862,68 @@ impl<D> Element<D> {
                 self.size.baked_height = self.size.minheight.unwrap_or(height).min(height);
             }
         }
+
+        self.grow_children();
+    }
+
+    /// Returns how much of the room left in a container this [`Element<D>`] wants to take up,
+    /// relative to its siblings.
+    ///
+    /// A [`Content::Spacer`] wants its weight, and an `Element` with the
+    /// [`SizingStrategy::Chonker`] strategy counts as a weight of 1. Other `Element`s do not grow.
+    fn grow_weight(&self) -> u32 {
+        match (&self.content, self.size.strategy) {
+            (Content::Spacer(weight), _) => *weight,
+            (_, SizingStrategy::Chonker) => 1,
+            _ => 0,
+        }
+    }
+
+    /// Hand out the room that is left in a [`Content::Row`] or [`Content::Stack`] after its
+    /// children have been sized, to the children that want to grow.
+    ///
+    /// The room is divided in proportion to the [weights](Element::grow_weight) of the children,
+    /// along the main axis of the container. That is horizontal for a `Row` and vertical for a
+    /// `Stack`.
+    fn grow_children(&mut self) {
+        let Dimensions { width, height } = self.fill_size();
+        let (children, room, horizontal) = match &mut self.content {
+            Content::Row(children) => (children, width, true),
+            Content::Stack(children) => (children, height, false),
+            _ => return,
+        };
+        let used: u32 = children
+            .iter()
+            .map(|child| match horizontal {
+                true => child.overall_size().width,
+                false => child.overall_size().height,
+            })
+            .sum();
+        let leftover = room.saturating_sub(used);
+        let total_weight: u32 = children.iter().map(|child| child.grow_weight()).sum();
+        if leftover == 0 || total_weight == 0 {
+            return;
+        }
+        let mut remainder = leftover;
+        for child in children.iter_mut().filter(|child| child.grow_weight() > 0) {
+            let share = (leftover as u64 * child.grow_weight() as u64 / total_weight as u64) as u32;
+            remainder -= share;
+            match horizontal {
+                true => child.size.baked_width += share,
+                false => child.size.baked_height += share,
+            }
+        }
+        // Hand out the pixels that did not divide evenly one by one.
+        for child in children.iter_mut().filter(|child| child.grow_weight() > 0) {
+            if remainder == 0 {
+                break;
+            }
+            remainder -= 1;
+            match horizontal {
+                true => child.size.baked_width += 1,
+                false => child.size.baked_height += 1,
+            }
+        }
     }
 
     pub(crate) fn update(&mut self, data: &D) {
@@ -564,11 +936,38 @@ impl<D> Element<D> {
         match &mut self.content {
             // Update the chirren.
             Content::Row(elements) | Content::Stack(elements) => {
-                elements.iter_mut().for_each(|element| element.update(data))
+                for element in elements {
+                    if !element.style.own_background {
+                        element.style.background = self.style.background;
+                    }
+                    element.update(data);
+                    self.actions.append(&mut element.actions);
+                    let intervals = self.redraw_interval.into_iter();
+                    self.redraw_interval = intervals.chain(element.redraw_interval.take()).min();
+                }
             }
             _ => {}
         };
     }
+
+    /// Ask the application to carry out the `action`.
+    ///
+    /// This is meant to be called from an `update` function. The `action` is handed to the
+    /// [`Panel`](crate::Panel) when it is updated, and can be taken from there with
+    /// [`Panel::take_actions`](crate::Panel::take_actions).
+    pub fn emit(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    /// Ask to be drawn again after `interval` at the latest, such as to animate a blinking cursor
+    /// or a [`Graph`](graph::Graph) that is live.
+    ///
+    /// This is meant to be called from an `update` function, which is run again for the redraw, so
+    /// asking again there keeps the redraws coming. Of the intervals that are asked for, the
+    /// shortest wins, and is handed to the [`Panel`](crate::Panel) when it is updated.
+    pub fn request_redraw_interval(&mut self, interval: Duration) {
+        self.redraw_interval = self.redraw_interval.into_iter().chain([interval]).min();
+    }
 }
 
 impl<D> Element<D> {
@@ -648,11 +1047,260 @@ impl<D> Element<D> {
         fillsize
     }
 
+    /// Returns the thickness of the [`Border`] of this [`Element<D>`], or 0 if it has none.
+    fn border_thickness(&self) -> u32 {
+        self.border.map_or(0, |border| border.thickness)
+    }
+
     /// Returns the overall size of this [`Element<D>`].
     ///
-    /// These [`Dimensions`] _include_ the padding.
+    /// These [`Dimensions`] _include_ the padding and the border.
     pub fn overall_size(&self) -> Dimensions {
-        Self::include_padding(self.fill_size(), self.padding)
+        let border = self.border_thickness();
+        let mut size = Self::include_padding(self.fill_size(), self.padding);
+        size.width += 2 * border;
+        size.height += 2 * border;
+        size
+    }
+
+    /// Returns how far this [`Element<D>`] can be scrolled down, in pixels, before it runs out of
+    /// content.
+    ///
+    /// Only an `Element` with [`Content::Stack`] can scroll. For any other content kind, this is 0.
+    pub fn max_scroll(&self) -> u32 {
+        let Content::Stack(children) = &self.content else {
+            return 0;
+        };
+        let children_height: u32 = children
+            .iter()
+            .map(|child| child.overall_size().height)
+            .sum();
+        children_height.saturating_sub(self.fill_size().height)
+    }
+
+    /// Returns the number of lines of text in this [`Element<D>`], as they were last wrapped.
+    ///
+    /// The lines of a [`Content::Stack`] are those of its children together, while a
+    /// [`Content::Row`] has as many lines as its child with the most of them.
+    pub fn total_lines(&self) -> usize {
+        match &self.content {
+            Content::Text(..) => 1,
+            Content::Paragraph(wrapped, _) => wrapped.lines_count(),
+            Content::Stack(children) => children.iter().map(Element::total_lines).sum(),
+            Content::Row(children) => children
+                .iter()
+                .map(Element::total_lines)
+                .max()
+                .unwrap_or_default(),
+            Content::Custom { .. } | Content::Spacer(_) | Content::Rule => 0,
+        }
+    }
+
+    /// Returns the index of the line at the top of this [`Element<D>`], given how far it is
+    /// scrolled. A line that is partly scrolled out of view still counts as the first.
+    pub fn first_visible_line(&self) -> usize {
+        (self.scroll.unwrap_or(0) / self.style.line_height()) as usize
+    }
+
+    /// Returns the number of whole lines that fit in this [`Element<D>`].
+    pub fn visible_line_count(&self) -> usize {
+        (self.fill_size().height / self.style.line_height()) as usize
+    }
+
+    /// Returns where this [`Element<D>`] was drawn last, relative to its parent.
+    ///
+    /// Before the first draw, this is an empty [`Rect`] at the origin.
+    pub fn bounds(&self) -> Rect {
+        self.bounds.get()
+    }
+
+    pub(crate) fn set_bounds(&self, bounds: Rect) {
+        self.bounds.set(bounds)
+    }
+
+    /// Returns the offset of the inner area where the content is drawn, relative to the top left
+    /// corner of this [`Element<D>`].
+    fn content_offset(&self) -> (u32, u32) {
+        let border = self.border_thickness();
+        (self.padding.left + border, self.padding.top + border)
+    }
+
+    /// Returns the index of the child that contains the point (`x`, `y`), relative to the top
+    /// left corner of this [`Element<D>`], as of the last time it was drawn.
+    ///
+    /// Points outside of the inner area of this `Element`, and points in between children, do not
+    /// belong to any child.
+    pub fn child_index_at(&self, x: u32, y: u32) -> Option<usize> {
+        let (Content::Row(children) | Content::Stack(children)) = &self.content else {
+            return None;
+        };
+        let (left, top) = self.content_offset();
+        let Dimensions { width, height } = self.fill_size();
+        if !(left..left + width).contains(&x) || !(top..top + height).contains(&y) {
+            return None;
+        }
+        children
+            .iter()
+            .position(|child| child.bounds().contains(x as i64, y as i64))
+    }
+
+    /// Returns the deepest [`Element<D>`] that contains the point (`x`, `y`), relative to the top
+    /// left corner of this `Element`, as of the last time it was drawn.
+    ///
+    /// If the point lies within this `Element` but not within any of its children, this
+    /// `Element` itself is returned.
+    pub fn hit_test(&self, x: u32, y: u32) -> Option<&Element<D>> {
+        if !self.contains(x, y) {
+            return None;
+        }
+        match (self.child_index_at(x, y), &self.content) {
+            (Some(idx), Content::Row(children) | Content::Stack(children)) => {
+                let child = &children[idx];
+                let (x, y) = child.relative_point(x, y);
+                child.hit_test(x, y)
+            }
+            _ => Some(self),
+        }
+    }
+
+    /// Returns a mutable reference to the deepest [`Element<D>`] that contains the point (`x`,
+    /// `y`).
+    ///
+    /// See [`Element::hit_test`].
+    pub fn hit_test_mut(&mut self, x: u32, y: u32) -> Option<&mut Element<D>> {
+        if !self.contains(x, y) {
+            return None;
+        }
+        let Some(idx) = self.child_index_at(x, y) else {
+            return Some(self);
+        };
+        // A child was found, so this must be a container.
+        let (Content::Row(children) | Content::Stack(children)) = &mut self.content else {
+            unreachable!()
+        };
+        let child = &mut children[idx];
+        let (x, y) = child.relative_point(x, y);
+        child.hit_test_mut(x, y)
+    }
+
+    /// Returns the first [`Element<D>`] with the `tag`, going through this `Element` and its
+    /// children depth first, in order.
+    pub fn find_by_tag(&self, tag: &str) -> Option<&Element<D>> {
+        if self.tag.as_deref() == Some(tag) {
+            return Some(self);
+        }
+        self.children()?
+            .iter()
+            .find_map(|child| child.find_by_tag(tag))
+    }
+
+    /// Returns a mutable reference to the first [`Element<D>`] with the `tag`.
+    ///
+    /// See [`Element::find_by_tag`].
+    pub fn find_by_tag_mut(&mut self, tag: &str) -> Option<&mut Element<D>> {
+        if self.tag.as_deref() == Some(tag) {
+            return Some(self);
+        }
+        self.children_mut()?
+            .iter_mut()
+            .find_map(|child| child.find_by_tag_mut(tag))
+    }
+
+    /// Returns the line of this [`Element<D>`], if its content is [`Content::Text`].
+    pub fn as_text(&self) -> Option<&str> {
+        match &self.content {
+            Content::Text(text, _) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the line of this [`Element<D>`], if its content is
+    /// [`Content::Text`].
+    pub fn as_text_mut(&mut self) -> Option<&mut String> {
+        match &mut self.content {
+            Content::Text(text, _) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the wrapped text of this [`Element<D>`], if its content is [`Content::Paragraph`].
+    pub fn as_paragraph(&self) -> Option<&WrappedText> {
+        match &self.content {
+            Content::Paragraph(wrapped, _) => Some(wrapped),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the wrapped text of this [`Element<D>`], if its content is
+    /// [`Content::Paragraph`].
+    pub fn as_paragraph_mut(&mut self) -> Option<&mut WrappedText> {
+        match &mut self.content {
+            Content::Paragraph(wrapped, _) => Some(wrapped),
+            _ => None,
+        }
+    }
+
+    /// Returns the children of this [`Element<D>`], if it is a [`Content::Row`] or
+    /// [`Content::Stack`].
+    pub fn children(&self) -> Option<&[Element<D>]> {
+        match &self.content {
+            Content::Row(children) | Content::Stack(children) => Some(children),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the children of this [`Element<D>`], if it is a
+    /// [`Content::Row`] or [`Content::Stack`].
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Element<D>>> {
+        match &mut self.content {
+            Content::Row(children) | Content::Stack(children) => Some(children),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this [`Element<D>`] was hovered as of the last [`Element::hover`].
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+
+    /// Mark this [`Element<D>`] and its children that contain the `pointer` as hovered, and all
+    /// others as not hovered, as of the last time they were drawn. Returns whether any of them
+    /// changed.
+    ///
+    /// Like with [`Element::hit_test`], the `pointer` is relative to the top left corner of this
+    /// `Element`. The hovered `Element`s are those on the way down to the one that a hit test
+    /// would find. Without a `pointer`, nothing is hovered.
+    pub fn hover(&mut self, pointer: Option<(u32, u32)>) -> bool {
+        let pointer = pointer.filter(|&(x, y)| self.contains(x, y));
+        let mut changed = self.hovered != pointer.is_some();
+        self.hovered = pointer.is_some();
+        let hovered_child = pointer.and_then(|(x, y)| Some((self.child_index_at(x, y)?, x, y)));
+        if let Content::Row(children) | Content::Stack(children) = &mut self.content {
+            for (idx, child) in children.iter_mut().enumerate() {
+                let pointer = match hovered_child {
+                    Some((hovered, x, y)) if hovered == idx => Some(child.relative_point(x, y)),
+                    // Nothing below an Element that was not hovered can have been hovered.
+                    _ if !child.hovered => continue,
+                    _ => None,
+                };
+                changed |= child.hover(pointer);
+            }
+        }
+        changed
+    }
+
+    /// Returns whether the point (`x`, `y`), relative to the top left corner of this
+    /// [`Element<D>`], lies within it.
+    fn contains(&self, x: u32, y: u32) -> bool {
+        let Dimensions { width, height } = self.overall_size();
+        x < width && y < height
+    }
+
+    /// Returns the point (`x`, `y`) of the parent relative to the top left corner of this
+    /// [`Element<D>`], as of the last time it was drawn.
+    fn relative_point(&self, x: u32, y: u32) -> (u32, u32) {
+        let Rect { x: cx, y: cy, .. } = self.bounds();
+        ((x as i64 - cx) as u32, (y as i64 - cy) as u32)
     }
 
     /// Returns a tuple with the horizontal then vertical room per flex for `children`.
@@ -683,8 +1331,8 @@ impl<D> Element<D> {
                 "this function can only produce meaningful results for Row and Stack Content"
             ),
         };
-        let flex_room_hor = self.overall_size().width.saturating_sub(children_width);
-        let flex_room_ver = self.overall_size().height.saturating_sub(children_height);
+        let flex_room_hor = self.fill_size().width.saturating_sub(children_width);
+        let flex_room_ver = self.fill_size().height.saturating_sub(children_height);
         let flexes = children.iter().map(|child| child.flex);
         let flexes_hor: u32 = flexes
             .clone()
@@ -700,35 +1348,29 @@ impl<D> Element<D> {
 
 impl<D> DrawBlock for Element<D> {
     fn block(&self) -> Block {
+        let style = match (&self.hover_style, self.hovered) {
+            (Some(hover_style), true) => hover_style,
+            _ => &self.style,
+        };
         let Dimensions { width, height } = self.fill_size();
-        let mut inner_block = Block::new(width, height, self.style.background);
+        let mut inner_block = Block::new(width, height, style.background);
         match &self.content {
-            Content::Text(text, alignment) => draw_text(
-                &mut inner_block,
-                text,
-                *alignment,
-                &self.style.font,
-                self.style.foreground,
-                self.style.background,
-            ),
+            Content::Text(text, alignment) => {
+                let font_height = style.font.height() as u32;
+                let mut line_block = Block::new(width, font_height, style.background);
+                draw_text(&mut line_block, text, *alignment, self.hscroll, style);
+                inner_block.paint(&line_block, 0, self.valign.offset(height, font_height));
+            }
             Content::Paragraph(wrapped, alignment) => {
-                let mut y = 0;
-                for line in wrapped.lines() {
-                    let mut line_block = Block::new(
-                        width,
-                        self.style.font.height() as u32,
-                        self.style.background,
-                    );
-                    draw_text(
-                        &mut line_block,
-                        line,
-                        *alignment,
-                        &self.style.font,
-                        self.style.foreground,
-                        self.style.background,
-                    );
-                    inner_block.paint(&line_block, 0, y);
-                    y += line_block.height;
+                let lines_height = wrapped.lines().count() as u32 * style.line_height();
+                let mut y = self.valign.offset(height, lines_height);
+                for (idx, line) in wrapped.lines().enumerate() {
+                    let indent = wrapped.indent_of(idx).min(width);
+                    let mut line_block =
+                        Block::new(width - indent, style.font.height() as u32, style.background);
+                    draw_text(&mut line_block, line, *alignment, 0, style);
+                    inner_block.paint(&line_block, indent, y);
+                    y += style.line_height();
 
                     if let Some(maxheight) = self.size.maxheight {
                         if y > maxheight {
@@ -742,14 +1384,31 @@ impl<D> DrawBlock for Element<D> {
                 assert_eq!(*h, height);
                 inner_block.buf.copy_from_slice(buf);
             }
+            Content::Spacer(_) => {} // Spacers are all background.
+            Content::Rule => inner_block.buf.fill(style.foreground),
             Content::Row(children) => {
                 let (room_per_flex_hor, room_per_flex_ver) = self.room_per_flex(children);
+                let (left, top) = self.content_offset();
                 let mut x = 0;
                 for child in children {
                     if child.flex.left {
                         x += room_per_flex_hor
                     }
-                    inner_block.paint(&child.block(), x, child.flex.top as u32 * room_per_flex_ver);
+                    let child_block = child.block();
+                    // Flexes decide the placement of a child that has them.
+                    let y = if child.flex.vertical_flexes() > 0 {
+                        child.flex.top as u32 * room_per_flex_ver
+                    } else {
+                        let child_height = child.overall_size().height;
+                        child.valign.offset(inner_block.height, child_height)
+                    };
+                    child.set_bounds(Rect {
+                        x: (left + x) as i64,
+                        y: (top + y) as i64,
+                        width: child_block.width,
+                        height: child_block.height,
+                    });
+                    inner_block.paint(&child_block, x, y);
                     if child.flex.right {
                         x += room_per_flex_hor
                     }
@@ -764,42 +1423,45 @@ impl<D> DrawBlock for Element<D> {
                     .map(|child| child.overall_size().height)
                     .sum::<u32>();
                 let scroll_block_height = u32::max(children_height, inner_block.height);
-                let mut scroll_block = Block::new(
-                    self.overall_size().width,
-                    scroll_block_height,
-                    self.style.background,
-                );
+                let mut scroll_block =
+                    Block::new(inner_block.width, scroll_block_height, style.background);
 
+                let (left, top) = self.content_offset();
+                let scroll = self.scroll.unwrap_or(0);
                 let mut y = 0;
                 for child in children {
                     if child.flex.top {
                         y += room_per_flex_ver
                     }
 
-                    scroll_block.paint(
-                        &child.block(),
-                        child.flex.left as u32 * room_per_flex_hor,
-                        y,
-                    );
+                    let child_block = child.block();
+                    let x = child.flex.left as u32 * room_per_flex_hor;
+                    child.set_bounds(Rect {
+                        x: (left + x) as i64,
+                        y: top as i64 + y as i64 - scroll as i64,
+                        width: child_block.width,
+                        height: child_block.height,
+                    });
+                    scroll_block.paint(&child_block, x, y);
                     if child.flex.bottom {
                         y += room_per_flex_ver
                     }
                     y += child.overall_size().height;
                 }
 
-                let scroll_index: usize = self.scroll.unwrap_or(0) as usize;
-                let start_index: usize = scroll_index * inner_block.width as usize;
-
-                if let Some(scrolled_block) = scroll_block.buf.get(start_index..) {
-                    // TODO: This may not be a very efficient way to set the inner_block buf.
-                    inner_block.buf = scrolled_block.to_vec();
-                }
+                // The scroll position is in pixels, so the top line may be cut off partway.
+                let scrolled_block = scroll_block.crop_rows(scroll, inner_block.height);
+                inner_block.paint(&scrolled_block, 0, 0);
             }
         }
 
         let Dimensions { width, height } = self.overall_size();
-        let mut padded_block = Block::new(width, height, self.style.background);
-        padded_block.paint(&inner_block, self.padding.left, self.padding.top);
+        let mut padded_block = Block::new(width, height, style.background);
+        let (left, top) = self.content_offset();
+        padded_block.paint(&inner_block, left, top);
+        if let Some(Border { color, thickness }) = self.border {
+            padded_block.frame(color, thickness);
+        }
         padded_block
     }
 }
@@ -812,15 +1474,70 @@ pub enum Alignment {
     Right,
 }
 
+impl Alignment {
+    /// Returns where a line of text `width` pixels wide goes within `available` pixels of room, as
+    /// the offset into the room at which it starts and the number of its leading pixels that are
+    /// cut off.
+    ///
+    /// The room that is left over goes to the right of a left-aligned line, to the left of a
+    /// right-aligned line, and is split evenly for a centered line. A line that does not fit is
+    /// cut off at the end, except when right-aligned, in which case its start is cut off instead.
+    pub fn offsets(self, available: u32, width: u32) -> (usize, usize) {
+        let leftover = available.saturating_sub(width) as usize;
+        let overflow = width.saturating_sub(available) as usize;
+        match self {
+            Alignment::Left => (0, 0),
+            Alignment::Center => (leftover / 2, 0),
+            Alignment::Right => (leftover, overflow),
+        }
+    }
+}
+
+/// Vertical placement of something within a taller space, the counterpart to [`Alignment`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VAlignment {
+    #[default]
+    Top,
+    Center,
+    Bottom,
+}
+
+impl VAlignment {
+    /// Returns how far down from the top something `height` pixels tall goes within `available`
+    /// pixels of room. Something that does not fit is placed at the top.
+    pub fn offset(self, available: u32, height: u32) -> u32 {
+        let leftover = available.saturating_sub(height);
+        match self {
+            VAlignment::Top => 0,
+            VAlignment::Center => leftover / 2,
+            VAlignment::Bottom => leftover,
+        }
+    }
+}
+
+/// The direction in which the glyphs of a line of text follow each other.
+///
+/// This is only a base direction for the whole line. Runs of text in the other direction within
+/// the line are not reordered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    LeftToRight,
+    /// The first glyph is placed at the right end of the line, and each next one to its left.
+    RightToLeft,
+}
+
+/// Draw a line of `text` onto the `block`, with the first `hscroll` pixels of it cut off.
 #[inline(always)]
-fn draw_text(
-    block: &mut Block,
-    text: &str,
-    alignment: Alignment,
-    font: &Font,
-    foreground: Pixel,
-    background: Pixel,
-) {
+fn draw_text(block: &mut Block, text: &str, alignment: Alignment, hscroll: u32, style: &Style) {
+    let Style {
+        foreground,
+        background,
+        direction,
+        ref font,
+        ref glyphs,
+        ..
+    } = *style;
     // TODO: This implementation is not ideal. Since a Block is created to fit the entire
     // line of text to then be cut off in a manner that depends on the alignment, we do
     // more allocations than is necessary, and we may render more characters into that
@@ -830,7 +1547,7 @@ fn draw_text(
     // As a note for such an implementation in the future, see the state at or around
     // commit e945006.
 
-    let scrap_width = font.determine_width(text);
+    let scrap_width = glyphs.width(font, text);
     // TODO: Perhaps this case can be handled a little more gracefully, but that will
     // require a more holistic view of the whole layout "engine" in a later stage.
     // Postponing ;)
@@ -838,12 +1555,18 @@ fn draw_text(
         return; // Nothing to even draw, here. Why expend the energy?
     }
     let mut scrap = Block::new(scrap_width as u32, font.height() as u32, background);
-    let glyphs = text.chars().flat_map(|ch| font.glyph(ch));
-    let mut x0 = 0;
+    let glyphs = text.chars().flat_map(|ch| glyphs.glyph(font, ch));
+    let mut x0 = match direction {
+        Direction::LeftToRight => 0,
+        Direction::RightToLeft => scrap_width,
+    };
     for glyph in glyphs {
-        let glyph_width = glyph.width as usize;
-        for (y, row) in glyph.enumerate() {
-            for (xg, cell) in row.enumerate() {
+        let glyph_width = glyph.width;
+        if direction == Direction::RightToLeft {
+            x0 -= glyph_width;
+        }
+        for (y, row) in glyph.rows().enumerate() {
+            for (xg, &cell) in row.iter().enumerate() {
                 let x = x0 + xg;
                 // TODO: This may be more efficient than what I did in Graph. May be
                 // worth investigating which is better.
@@ -851,50 +1574,730 @@ fn draw_text(
                     if cell { foreground } else { background };
             }
         }
-        x0 += glyph_width;
-    }
-
-    match alignment {
-        Alignment::Left => {
-            let end = block.width.min(scrap.width) as usize;
-            block
-                .rows_mut()
-                .zip(scrap.rows())
-                .for_each(|(row, scrap_row)| {
-                    row[..end].copy_from_slice(&scrap_row[..end]);
-                })
-        }
-        // TODO: The first two branches do the exact same thing. Figure out how to do this
-        // nicely.
-        // TODO: Decide on the actually correct behavior for Alignment::Center in this case.
-        Alignment::Center if scrap.width >= block.width => {
-            let end = block.width.min(scrap.width) as usize;
-            block
-                .rows_mut()
-                .zip(scrap.rows())
-                .for_each(|(row, scrap_row)| {
-                    row[..end].copy_from_slice(&scrap_row[..end]);
-                })
-        }
-        Alignment::Center => {
-            let remainder = block.width - scrap.width;
-            let start = remainder as usize / 2;
-            block
-                .rows_mut()
-                .zip(scrap.rows())
-                .for_each(|(row, scrap_row)| {
-                    row[start..start + scrap.width as usize].copy_from_slice(scrap_row);
-                })
-        }
-        Alignment::Right => {
-            let rstart = block.width.saturating_sub(scrap.width) as usize;
-            let sstart = scrap.width.saturating_sub(block.width) as usize;
-            block
-                .rows_mut()
-                .zip(scrap.rows())
-                .for_each(|(row, scrap_row)| {
-                    row[rstart..].copy_from_slice(&scrap_row[sstart..]);
-                })
+        if direction == Direction::LeftToRight {
+            x0 += glyph_width;
+        }
+    }
+
+    let hscroll = hscroll.min(scrap.width);
+    let (start, skip) = alignment.offsets(block.width, scrap.width - hscroll);
+    let skip = skip + hscroll as usize;
+    let len = (block.width as usize - start).min(scrap.width as usize - skip);
+    block
+        .rows_mut()
+        .zip(scrap.rows())
+        .for_each(|(row, scrap_row)| {
+            row[start..start + len].copy_from_slice(&scrap_row[skip..skip + len]);
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builder::ElementBuilder;
+    use super::*;
+    use crate::{Panel, PIXEL_SIZE};
+
+    const BLACK: Pixel = [0x00, 0x00, 0x00, 0xff];
+    const WHITE: Pixel = [0xff, 0xff, 0xff, 0xff];
+
+    /// How many of the top rows of a glyph of the [`font`] are filled.
+    const INKED_HEIGHT: usize = 12;
+
+    /// Returns a font in which every glyph is a block eight pixels wide, filled but for its bottom
+    /// rows.
+    fn font() -> Rc<Font> {
+        let mut bytes = [0xff; fleck::FILE_SIZE];
+        let (widths, glyphs) = bytes.split_at_mut(0x100);
+        widths.fill(8);
+        // The bytes of a glyph are the left halves of its rows, followed by the right halves.
+        for glyph in glyphs.chunks_exact_mut(32) {
+            glyph[INKED_HEIGHT..16].fill(0);
+            glyph[16 + INKED_HEIGHT..].fill(0);
         }
+        Rc::new(Font::new(&bytes))
+    }
+
+    /// Returns a [`Panel`] of `width` by `height` pixels with the `elements`, laid out and drawn,
+    /// along with its pixels.
+    fn draw(elements: Element<()>, width: u32, height: u32) -> (Panel<()>, Vec<Pixel>) {
+        let mut panel = Panel::new(elements, BLACK, WHITE, ());
+        panel.resize(width, height);
+        panel.update();
+        let mut bytes = vec![0; width as usize * height as usize * PIXEL_SIZE];
+        panel.draw(&mut bytes);
+        let pixels = bytes
+            .chunks_exact(PIXEL_SIZE)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect();
+        (panel, pixels)
+    }
+
+    /// Returns the rows of the `pixels` of a panel `width` pixels wide that have any foreground
+    /// in them.
+    fn inked_rows(pixels: &[Pixel], width: u32) -> Vec<usize> {
+        let rows = pixels.chunks_exact(width as usize).enumerate();
+        rows.filter(|(_, row)| row.contains(&BLACK))
+            .map(|(y, _)| y)
+            .collect()
+    }
+
+    /// Returns the rows that `lines` lines of text in the `font` cover with a `spacing` between
+    /// them.
+    fn line_rows(font: &Font, lines: usize, spacing: usize) -> Vec<usize> {
+        let height = font.height();
+        (0..lines)
+            .flat_map(|k| {
+                let top = k * (height + spacing);
+                top..top + INKED_HEIGHT
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines_are_spaced_by_the_line_spacing() {
+        let font = font();
+        for spacing in [0, 3] {
+            let lines =
+                (0..4).map(|_| Element::text("H", &font).build().with_line_spacing(spacing));
+            let stack = Element::stack_builder(&font).add_children(lines).build();
+            let (_, pixels) = draw(stack, 100, 100);
+            assert_eq!(
+                inked_rows(&pixels, 100),
+                line_rows(&font, 4, spacing as usize)
+            );
+        }
+    }
+
+    #[test]
+    fn wrapped_lines_are_spaced_by_the_line_spacing() {
+        let font = font();
+        let spacing = 5;
+        // Only one word fits on a line.
+        let width = font.determine_width("H ") as u32;
+        let paragraph = Element::paragraph("H H H", &font)
+            .build()
+            .with_line_spacing(spacing as u32);
+        let (_, pixels) = draw(paragraph, width, 100);
+        assert_eq!(inked_rows(&pixels, width), line_rows(&font, 3, spacing));
+    }
+
+    #[test]
+    fn scrolling_by_pixels_drops_the_top_rows() {
+        let font = font();
+        let stack = |scroll| {
+            let lines = ["Hg", "Hg", "Hg"].map(|line| Element::text(line, &font).build());
+            Element::stack_builder(&font)
+                .add_children(lines.into_iter())
+                .build()
+                .with_fixedheight(20)
+                .with_scroll(scroll)
+        };
+        let width = 30;
+        let (_, unscrolled) = draw(stack(0), width, 20);
+        let (_, scrolled) = draw(stack(3), width, 20);
+        let row = width as usize;
+        assert_eq!(scrolled[..17 * row], unscrolled[3 * row..]);
+    }
+
+    /// Returns a stack of `lines` lines of text, `height` pixels tall, laid out.
+    fn page(font: &Rc<Font>, lines: usize, height: u32) -> Element<()> {
+        let lines = (0..lines).map(|_| Element::text("Hg", font).build());
+        let mut stack = Element::stack_builder(font)
+            .add_children(lines)
+            .build()
+            .with_fixedheight(height);
+        stack.bake_size(Some(100));
+        stack
+    }
+
+    #[test]
+    fn short_page_does_not_scroll() {
+        let font = font();
+        let line_height = font.height() as u32;
+        assert_eq!(page(&font, 0, 10 * line_height).max_scroll(), 0);
+        assert_eq!(page(&font, 3, 10 * line_height).max_scroll(), 0);
+        assert_eq!(page(&font, 10, 10 * line_height).max_scroll(), 0);
+    }
+
+    #[test]
+    fn long_page_scrolls_until_its_last_line_is_at_the_bottom() {
+        let font = font();
+        let line_height = font.height() as u32;
+        let page = page(&font, 25, 10 * line_height + 4);
+        assert_eq!(page.max_scroll(), 15 * line_height - 4);
+        assert_eq!(Element::<()>::text("Hg", &font).build().max_scroll(), 0);
+    }
+
+    #[test]
+    fn padding_adds_to_the_overall_size() {
+        let font = font();
+        let measure = |pad: fn(Element<()>) -> Element<()>| {
+            let mut element = pad(Element::text("Hg", &font).build());
+            element.bake_size(None);
+            element.overall_size()
+        };
+        let Dimensions { width, height } = measure(|text| text);
+        assert_eq!(
+            measure(|text| text.with_padding(1, 2, 3, 4)),
+            Dimensions::new(width + 2 + 4, height + 1 + 3)
+        );
+        assert_eq!(
+            measure(|text| text.with_padding_all(5)),
+            Dimensions::new(width + 10, height + 10)
+        );
+        assert_eq!(
+            measure(|text| text.with_padding_xy(6, 7)),
+            Dimensions::new(width + 12, height + 14)
+        );
+    }
+
+    /// Returns the overall widths of the children of the `container`.
+    fn child_widths(container: &Element<()>) -> Vec<u32> {
+        let (Content::Row(children) | Content::Stack(children)) = &container.content else {
+            panic!("only rows and stacks have children")
+        };
+        children
+            .iter()
+            .map(|child| child.overall_size().width)
+            .collect()
+    }
+
+    #[test]
+    fn chonker_takes_the_room_left_by_a_fixed_child_in_a_row() {
+        let font = font();
+        let fixed = Element::text("H", &font).build().with_fixedwidth(30);
+        let chonker = Element::text("H", &font)
+            .build()
+            .with_strategy(SizingStrategy::Chonker);
+        let mut row = Element::row_builder(&font)
+            .add_child(fixed)
+            .add_child(chonker)
+            .build()
+            .with_fixedwidth(100);
+        row.bake_size(Some(100));
+        assert_eq!(child_widths(&row), [30, 70]);
+    }
+
+    #[test]
+    fn chonkers_share_the_room_in_a_row_evenly() {
+        let font = font();
+        let chonker = || {
+            Element::text("", &font)
+                .build()
+                .with_strategy(SizingStrategy::Chonker)
+        };
+        let mut row = Element::row_builder(&font)
+            .add_child(chonker())
+            .add_child(chonker())
+            .add_child(chonker())
+            .build()
+            .with_fixedwidth(100);
+        row.bake_size(None);
+        // The pixels that do not divide evenly go to the first children.
+        assert_eq!(child_widths(&row), [34, 33, 33]);
+    }
+
+    #[test]
+    fn spacers_split_the_room_by_their_weights() {
+        let font = font();
+        let fixed = Element::text("H", &font).build().with_fixedwidth(10);
+        let mut row = Element::row_builder(&font)
+            .add_child(Element::spacer(&font).build())
+            .add_child(fixed)
+            .add_child(Element::spacer(&font).with_weight(2).build())
+            .build()
+            .with_fixedwidth(100);
+        row.bake_size(None);
+        assert_eq!(child_widths(&row), [30, 10, 60]);
+
+        let mut stack: Element<()> = Element::stack_builder(&font)
+            .add_child(Element::spacer(&font).build())
+            .add_child(Element::spacer(&font).with_weight(2).build())
+            .build()
+            .with_fixedheight(90);
+        stack.bake_size(None);
+        let Content::Stack(children) = &stack.content else {
+            unreachable!()
+        };
+        let heights: Vec<_> = children
+            .iter()
+            .map(|child| child.overall_size().height)
+            .collect();
+        assert_eq!(heights, [30, 60]);
+    }
+
+    #[test]
+    fn border_is_drawn_on_all_edges_and_insets_the_content() {
+        const RED: Pixel = [0xff, 0x00, 0x00, 0xff];
+        let font = font();
+        let (width, height, thickness) = (20, 16, 2);
+        let stack = Element::stack_builder(&font)
+            .add_child(Element::text("H", &font).build())
+            .build()
+            .with_fixedwidth(width - 2 * thickness)
+            .with_fixedheight(height - 2 * thickness)
+            .with_border(RED, thickness);
+        let (_, pixels) = draw(stack, width, height);
+        for (idx, pixel) in pixels.iter().enumerate() {
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+            let on_edge =
+                x < thickness || y < thickness || x >= width - thickness || y >= height - thickness;
+            assert_eq!(*pixel == RED, on_edge, "({x}, {y})");
+        }
+        // The text starts right inside the border.
+        assert_eq!(pixels[(thickness * width + thickness) as usize], BLACK);
+    }
+
+    #[test]
+    fn rule_spans_the_width_in_the_foreground() {
+        let font = font();
+        let stack = Element::stack_builder(&font)
+            .add_child(Element::rule(&font).build())
+            .build()
+            .with_fixedwidth(10);
+        let (_, pixels) = draw(stack, 10, 3);
+        assert_eq!(pixels[..10], [BLACK; 10]);
+        assert_eq!(pixels[10..], [WHITE; 20]);
+    }
+
+    #[test]
+    fn backgrounds_are_inherited_unless_overridden() {
+        const BLUE: Pixel = [0x00, 0x00, 0xff, 0xff];
+        const GREEN: Pixel = [0x00, 0xff, 0x00, 0xff];
+        let font = font();
+        let inner = Element::stack_builder(&font)
+            .add_child(Element::text("", &font).build().with_fixedwidth(4))
+            .add_child(
+                Element::text("", &font)
+                    .build()
+                    .with_fixedwidth(4)
+                    .with_background(GREEN),
+            )
+            .build();
+        let outer = Element::stack_builder(&font)
+            .add_child(inner)
+            .build()
+            .with_background(BLUE);
+        let (panel, pixels) = draw(outer, 4, 2 * font.height() as u32);
+        let Content::Stack(outer) = &panel.elements.content else {
+            unreachable!()
+        };
+        let Content::Stack(children) = &outer[0].content else {
+            unreachable!()
+        };
+        assert_eq!(children[0].style.background, BLUE);
+        assert_eq!(children[1].style.background, GREEN);
+        let half = pixels.len() / 2;
+        assert!(pixels[..half].iter().all(|&pixel| pixel == BLUE));
+        assert!(pixels[half..].iter().all(|&pixel| pixel == GREEN));
+    }
+
+    /// Returns a stack with some padding around two rows of two lines each, which are 10 pixels
+    /// wide and read `a` through `d`.
+    fn lettered_tree(font: &Rc<Font>) -> Element<()> {
+        let line = |letter: &str| Element::text(letter, font).build().with_fixedwidth(10);
+        let row = |left: &str, right: &str| {
+            Element::row_builder(font)
+                .add_child(line(left))
+                .add_child(line(right))
+                .build()
+        };
+        Element::stack_builder(font)
+            .add_child(row("a", "b"))
+            .add_child(row("c", "d"))
+            .build()
+            .with_padding_all(5)
+    }
+
+    /// Returns the text of a text `element`, or what kind of container it is.
+    fn name(element: &Element<()>) -> &str {
+        match &element.content {
+            Content::Text(text, _) => text,
+            Content::Row(_) => "row",
+            Content::Stack(_) => "stack",
+            _ => "other",
+        }
+    }
+
+    #[test]
+    fn hit_test_finds_the_deepest_element() {
+        let font = font();
+        let height = font.height() as u32;
+        let (panel, _) = draw(lettered_tree(&font), 40, 40);
+        let name_at = |x, y| panel.hit_test(x, y).map(name);
+        assert_eq!(name_at(5, 5), Some("a"));
+        assert_eq!(name_at(15, 5), Some("b"));
+        assert_eq!(name_at(14, 5 + height), Some("c"));
+        assert_eq!(name_at(24, 4 + 2 * height), Some("d"));
+        // The padding belongs to the element that it pads.
+        assert_eq!(name_at(2, 2), Some("stack"));
+        assert_eq!(name_at(40, 5), None);
+    }
+
+    #[test]
+    fn hit_test_mut_finds_the_same_element() {
+        let font = font();
+        let (mut panel, _) = draw(lettered_tree(&font), 40, 40);
+        let found = panel.elements.hit_test_mut(15, 5).unwrap();
+        assert_eq!(name(found), "b");
+        found.content = Content::Text("changed".to_string(), Alignment::Left);
+        assert_eq!(panel.hit_test(15, 5).map(name), Some("changed"));
+    }
+
+    /// Returns the pixels of the `text` drawn right-aligned in the `direction`, `width` pixels
+    /// wide.
+    fn right_aligned(text: &str, direction: Direction, width: u32) -> Vec<Pixel> {
+        let font = font();
+        let line = Element::text(text, &font)
+            .with_alignment(Alignment::Right)
+            .build()
+            .with_fixedwidth(width)
+            .with_direction(direction);
+        draw(line, width, font.height() as u32).1
+    }
+
+    #[test]
+    fn right_to_left_line_starts_at_the_right_edge() {
+        let width = 40;
+        let glyph_width = font().determine_width("H");
+        let alone = right_aligned("H", Direction::RightToLeft, width);
+        let first = right_aligned("Hi", Direction::RightToLeft, width);
+        let last = right_aligned("iH", Direction::LeftToRight, width);
+        let right_edge = |pixels: &[Pixel]| -> Vec<Pixel> {
+            pixels
+                .chunks_exact(width as usize)
+                .flat_map(|row| row[row.len() - glyph_width..].to_vec())
+                .collect()
+        };
+        assert_eq!(right_edge(&first), right_edge(&alone));
+        assert_eq!(first, last);
+    }
+
+    #[test]
+    fn alignment_places_lines_by_their_leftover_room() {
+        assert_eq!(Alignment::Left.offsets(100, 30), (0, 0));
+        assert_eq!(Alignment::Center.offsets(100, 30), (35, 0));
+        assert_eq!(Alignment::Right.offsets(100, 30), (70, 0));
+        // A line that does not fit loses its end, unless it is aligned to the right.
+        assert_eq!(Alignment::Center.offsets(20, 30), (0, 0));
+        assert_eq!(Alignment::Right.offsets(20, 30), (0, 10));
+    }
+
+    #[test]
+    fn short_lines_are_drawn_centered_and_right_aligned() {
+        let font = font();
+        let width = 40;
+        let glyph_width = font.determine_width("H");
+        // Returns the columns that have foreground in them.
+        let inked_columns = |alignment| {
+            let line = Element::text("H", &font)
+                .with_alignment(alignment)
+                .build()
+                .with_fixedwidth(width);
+            let (_, pixels) = draw(line, width, font.height() as u32);
+            let columns = (0..width as usize).filter(|&x| {
+                pixels
+                    .chunks_exact(width as usize)
+                    .any(|row| row[x] == BLACK)
+            });
+            columns.collect::<Vec<_>>()
+        };
+        let left = inked_columns(Alignment::Left);
+        let shift = |by: usize| left.iter().map(|x| x + by).collect::<Vec<_>>();
+        let leftover = width as usize - glyph_width;
+        assert_eq!(inked_columns(Alignment::Center), shift(leftover / 2));
+        assert_eq!(inked_columns(Alignment::Right), shift(leftover));
+    }
+
+    #[test]
+    fn vertical_alignment_places_by_the_leftover_room() {
+        assert_eq!(VAlignment::Top.offset(30, 10), 0);
+        assert_eq!(VAlignment::Center.offset(30, 10), 10);
+        assert_eq!(VAlignment::Bottom.offset(30, 10), 20);
+        assert_eq!(VAlignment::Bottom.offset(10, 30), 0);
+    }
+
+    #[test]
+    fn single_line_is_centered_in_a_taller_slot() {
+        let font = font();
+        let height = font.height();
+        let slot = 3 * height as u32;
+        let line = |valign| {
+            Element::text("H", &font)
+                .build()
+                .with_fixedheight(slot)
+                .with_valign(valign)
+        };
+        let (_, top) = draw(line(VAlignment::Top), 20, slot);
+        let (_, center) = draw(line(VAlignment::Center), 20, slot);
+        let top_rows = inked_rows(&top, 20);
+        let center_rows = inked_rows(&center, 20);
+        let shifted: Vec<_> = top_rows.iter().map(|y| y + height).collect();
+        assert_eq!(center_rows, shifted);
+
+        // A child of a row is placed within the height of the row the same way.
+        let short = Element::text("H", &font)
+            .build()
+            .with_valign(VAlignment::Bottom);
+        let tall = Element::text("", &font).build().with_fixedheight(slot);
+        let row = Element::row_builder(&font)
+            .add_child(short)
+            .add_child(tall)
+            .build();
+        let (panel, _) = draw(row, 20, slot);
+        let Content::Row(children) = &panel.elements.content else {
+            unreachable!()
+        };
+        assert_eq!(children[0].bounds().y, 2 * height as i64);
+    }
+
+    #[test]
+    fn stack_measures_as_tall_as_its_children() {
+        let font = font();
+        let children = || {
+            let long = "a few words that wrap onto several lines";
+            [
+                Element::text("H", &font).build(),
+                Element::paragraph(long, &font).build(),
+                Element::text("H", &font).build().with_padding_all(3),
+            ]
+        };
+        let heights: u32 = children()
+            .into_iter()
+            .map(|mut child| child.measure(&(), Some(50)).height)
+            .sum();
+        let mut stack = Element::stack_builder(&font)
+            .add_children(children().into_iter())
+            .build()
+            .with_maxwidth(50);
+        assert_eq!(stack.measure(&(), None).height, heights);
+        // Nothing was drawn, so nothing was placed yet.
+        let Content::Stack(children) = &stack.content else {
+            unreachable!()
+        };
+        assert_eq!(children[1].bounds(), Rect::default());
+    }
+
+    #[test]
+    fn content_height_of_a_panel_is_that_of_its_elements() {
+        let font = font();
+        let lines = (0..30).map(|_| Element::text("H", &font).build());
+        let stack = Element::stack_builder(&font).add_children(lines).build();
+        let mut panel = Panel::new(stack, BLACK, WHITE, ());
+        panel.resize(20, 20);
+        assert_eq!(panel.content_height(), 30 * font.height() as u32);
+    }
+
+    #[test]
+    fn sideways_scroll_cuts_off_the_start_of_a_line() {
+        let font = Rc::new(crate::font::fallback());
+        let text = "Hello, world";
+        let (width, height) = (80, font.height() as u32);
+        let line = |hscroll| Element::text(text, &font).build().with_hscroll(hscroll);
+        let (_, flush) = draw(line(0), width, height);
+        let (_, scrolled) = draw(line(7), width, height);
+        let rows = flush
+            .chunks(width as usize)
+            .zip(scrolled.chunks(width as usize));
+        for (flush_row, scrolled_row) in rows {
+            assert_eq!(scrolled_row[..73], flush_row[7..]);
+            assert!(!scrolled_row[73..].contains(&BLACK));
+        }
+        // Scrolling past the end of the line leaves nothing of it.
+        let (_, past) = draw(line(1000), width, height);
+        assert!(!past.contains(&BLACK));
+    }
+
+    #[test]
+    fn lines_are_counted_as_they_are_wrapped() {
+        let font = font();
+        let word_width = font.determine_width("word ") as u32;
+        let text = ["word"; 12].join(" ");
+        // A paragraph of its own is wrapped to the width of the panel.
+        let paragraph = Element::paragraph(&text, &font).build();
+        let (mut panel, _) = draw(paragraph, 6 * word_width, 100);
+        assert_eq!(panel.elements.total_lines(), 2);
+        panel.resize(3 * word_width, 100);
+        panel.update();
+        assert_eq!(panel.elements.total_lines(), 4);
+        let stack = Element::stack_builder(&font)
+            .add_child(Element::text("Hg", &font).build())
+            .add_child(Element::paragraph(&text, &font).build())
+            .build()
+            .with_maxwidth(4 * word_width);
+        let (panel, _) = draw(stack, 6 * word_width, 100);
+        assert_eq!(panel.elements.total_lines(), 1 + 3);
+    }
+
+    #[test]
+    fn visible_lines_follow_the_scroll_and_the_height() {
+        let font = font();
+        let line_height = font.height() as u32;
+        let mut stack = page(&font, 20, 5 * line_height + line_height / 2);
+        assert_eq!(stack.total_lines(), 20);
+        assert_eq!(stack.first_visible_line(), 0);
+        assert_eq!(stack.visible_line_count(), 5);
+        stack.scroll = Some(2 * line_height + line_height / 2);
+        assert_eq!(stack.first_visible_line(), 2);
+        assert_eq!(stack.visible_line_count(), 5);
+    }
+
+    /// Returns the names of the hovered elements in the `tree`, from the top down.
+    fn hovered_names(tree: &Element<()>) -> Vec<&str> {
+        let mut names = Vec::new();
+        if tree.is_hovered() {
+            names.push(name(tree));
+        }
+        if let Content::Row(children) | Content::Stack(children) = &tree.content {
+            for child in children {
+                names.extend(hovered_names(child));
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn hovered_elements_are_those_a_hit_test_goes_through() {
+        let font = font();
+        let height = font.height() as u32;
+        let (mut panel, _) = draw(lettered_tree(&font), 40, 40);
+        assert!(panel.hover(Some((15, 5))));
+        assert_eq!(hovered_names(&panel.elements), ["stack", "row", "b"]);
+        assert_eq!(panel.hit_test(15, 5).map(name), Some("b"));
+        // Nothing changes while the pointer stays on the same element.
+        assert!(!panel.hover(Some((16, 6))));
+        assert!(panel.hover(Some((14, 5 + height))));
+        assert_eq!(hovered_names(&panel.elements), ["stack", "row", "c"]);
+        assert!(panel.hover(Some((2, 2))));
+        assert_eq!(hovered_names(&panel.elements), ["stack"]);
+        assert!(panel.hover(None));
+        assert!(hovered_names(&panel.elements).is_empty());
+    }
+
+    #[test]
+    fn hovered_element_is_drawn_in_its_hover_style() {
+        let font = font();
+        let mut hover_style = Style::new(WHITE, BLACK, Rc::clone(&font));
+        hover_style.own_background = true;
+        let line = Element::text("H", &font)
+            .build()
+            .with_fixedwidth(20)
+            .with_hover_style(hover_style);
+        let height = font.height() as u32;
+        let (mut panel, before) = draw(line, 20, height);
+        assert!(before.contains(&BLACK) && before.contains(&WHITE));
+        panel.hover(Some((1, 1)));
+        panel.update();
+        let mut bytes = vec![0; 20 * height as usize * PIXEL_SIZE];
+        panel.draw(&mut bytes);
+        let after: Vec<Pixel> = bytes
+            .chunks_exact(PIXEL_SIZE)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect();
+        // Black on white turned white on black.
+        let inverted: Vec<Pixel> = before
+            .iter()
+            .map(|&pixel| if pixel == BLACK { WHITE } else { BLACK })
+            .collect();
+        assert_eq!(after, inverted);
+    }
+
+    #[test]
+    fn elements_are_found_by_their_tag() {
+        fn text_of(element: &Element<()>) -> Option<&str> {
+            match &element.content {
+                Content::Text(text, _) => Some(text),
+                _ => None,
+            }
+        }
+
+        let font = font();
+        let mut tree: Element<()> = Element::stack_builder(&font)
+            .with_tag("root")
+            .add_child(Element::text("address", &font).with_tag("address").build())
+            .add_child(
+                Element::row_builder(&font)
+                    .add_child(Element::text("first", &font).with_tag("mode").build())
+                    .add_child(Element::text("second", &font).with_tag("mode").build())
+                    .build(),
+            )
+            .build();
+        let root = tree.find_by_tag("root").unwrap();
+        assert!(matches!(root.content, Content::Stack(_)));
+        assert_eq!(
+            text_of(tree.find_by_tag("address").unwrap()),
+            Some("address")
+        );
+        // The first of the elements with the same tag is found.
+        assert_eq!(text_of(tree.find_by_tag("mode").unwrap()), Some("first"));
+        assert!(tree.find_by_tag("missing").is_none());
+
+        let address = tree.find_by_tag_mut("address").unwrap();
+        if let Content::Text(text, _) = &mut address.content {
+            text.push_str(" bar");
+        }
+        assert_eq!(
+            text_of(tree.find_by_tag("address").unwrap()),
+            Some("address bar")
+        );
+    }
+
+    #[test]
+    fn paragraphs_break_where_their_wrapped_text_does() {
+        let font = font();
+        let texts = [
+            "a few short words that wrap",
+            "averyveryverylongwordthatdoesnotfitonalineatall and then some",
+            "  leading spaces,   runs of spaces, and trailing ones  ",
+            "=> gemini://example.org/ A link whose label wraps onto more lines",
+            "",
+        ];
+        for text in texts {
+            for width in [30, 57, 120] {
+                let paragraph = Element::paragraph(text, &font).build();
+                let (panel, _) = draw(paragraph, width, 200);
+                let Content::Paragraph(drawn, _) = &panel.elements.content else {
+                    unreachable!()
+                };
+                let drawn: Vec<_> = drawn.lines().collect();
+                let wrapped = WrappedText::new(text.to_string(), width, &font);
+                assert_eq!(
+                    drawn,
+                    wrapped.lines().collect::<Vec<_>>(),
+                    "{text:?} at {width}"
+                );
+                // Nothing is lost to the wrapping, even of words that are split for being long.
+                let ink = |text: &str| text.split_whitespace().collect::<String>();
+                assert_eq!(ink(&drawn.concat()), ink(text), "{text:?} at {width}");
+            }
+        }
+    }
+
+    #[test]
+    fn content_is_reached_only_through_the_accessor_of_its_kind() {
+        let font = font();
+        let mut text: Element<()> = Element::text("line", &font).build();
+        let mut paragraph: Element<()> = Element::paragraph("some words", &font).build();
+        let mut stack: Element<()> = Element::stack_builder(&font).build();
+
+        text.as_text_mut().unwrap().push('s');
+        assert_eq!(text.as_text(), Some("lines"));
+        assert!(text.as_paragraph().is_none() && text.as_paragraph_mut().is_none());
+        assert!(text.children().is_none() && text.children_mut().is_none());
+
+        assert!(paragraph.as_paragraph_mut().is_some());
+        assert_eq!(
+            paragraph
+                .as_paragraph()
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>(),
+            ["some words"]
+        );
+        assert!(paragraph.as_text().is_none() && paragraph.as_text_mut().is_none());
+
+        stack.children_mut().unwrap().push(text);
+        assert_eq!(stack.children().unwrap().len(), 1);
+        assert!(stack.as_text().is_none() && stack.as_paragraph().is_none());
     }
 }
@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use fleck::Font;
 
 /// A wrapper for a [`String`] where its contents are guaranteed to be wrapped at time of use.
@@ -10,14 +12,42 @@ use fleck::Font;
 /// No actual wrapping of the internal string takes place at time of [creation](WrappedText::new)
 /// or when [rewrapped](WrappedText::rewrap). In fact, the internal string is not mutate over the
 /// lifetime of [`WrappedText`].
+///
+/// Lines that continue a line broken by the wrapping, rather than at a newline, can be set to hang
+/// by an indent. They are wrapped that much narrower, so that they still fit when they are shown
+/// indented.
 #[derive(Debug, Default, Clone)]
-pub struct WrappedText(String, Vec<usize>);
+pub struct WrappedText(String, Vec<usize>, Option<WrapKey>, u32);
+
+/// The width and [`Font`] that a [`WrappedText`] was last wrapped for.
+///
+/// The font is held on to, so that it cannot be dropped and another one take its place at the same
+/// address, which would pass for the same font.
+type WrapKey = (Option<u32>, Rc<Font>);
 
 impl WrappedText {
     /// Creates a new [`WrappedText`] that will be wrapped to the specified `width` and according
     /// to the glyphs in the provided [`Font`].
-    pub fn new(text: String, width: u32, font: &Font) -> Self {
-        Self::new_without_width(text, Some(width), font)
+    pub fn new(text: String, width: u32, font: &Rc<Font>) -> Self {
+        Self::new_without_width(text, Some(width), 0, font)
+    }
+
+    /// Creates a new [`WrappedText`] that is wrapped to the specified `width`, measuring every
+    /// character with `glyph_width` rather than with a [`Font`].
+    ///
+    /// This lays out text where there is no font to speak of, such as in a terminal, where every
+    /// character is as wide as the next. Like any [`WrappedText`], it is wrapped anew when
+    /// [rewrapped](WrappedText::rewrap) for a [`Font`].
+    ///
+    /// Continuation lines are wrapped to leave room for a `hanging_indent` before them.
+    pub fn with_glyph_widths(
+        text: String,
+        width: u32,
+        hanging_indent: u32,
+        glyph_width: impl Fn(char) -> u32,
+    ) -> Self {
+        let breaklist = breaks(&text, Some(width), hanging_indent, glyph_width);
+        Self(text, breaklist, None, hanging_indent)
     }
 
     // TODO: Consider whether it is worth it to expose this function as `pub`. Will a user ever
@@ -28,8 +58,13 @@ impl WrappedText {
     ///
     /// In order to wrap the text to the desired width at a later stage, call
     /// [`WrappedText::rewrap`].
-    pub(crate) fn new_without_width(text: String, width: Option<u32>, font: &Font) -> Self {
-        let mut ret = Self(text, Vec::new());
+    pub(crate) fn new_without_width(
+        text: String,
+        width: Option<u32>,
+        hanging_indent: u32,
+        font: &Rc<Font>,
+    ) -> Self {
+        let mut ret = Self(text, Vec::new(), None, hanging_indent);
         ret.rewrap(width, font);
         ret
     }
@@ -37,56 +72,26 @@ impl WrappedText {
     /// Rewrap the [`WrappedText`] to the desired width.
     ///
     /// If `None` is passed as the `maxwidth`, the lines are not wrapped.
-    pub fn rewrap(&mut self, maxwidth: Option<u32>, font: &Font) {
+    ///
+    /// The text cannot change, so when it was last wrapped to the same `maxwidth` with the same
+    /// `font`, the lines are still as they should be and nothing is done.
+    pub fn rewrap(&mut self, maxwidth: Option<u32>, font: &Rc<Font>) {
         // TODO: Do this optimization that I had this note for:
         // > TODO: I don't know whether this makes any sense. Never measured it. I like it because
         // > it may prevent two allocations but also, who cares.
 
         // TODO: Equal starts optimization.
 
-        let Self(text, breaklist) = self;
-        breaklist.clear();
-        let mut scrapwidth = 0u32;
-        let mut wordwidth = 0u32;
-        // FIXME: There may be a bug with a very long unbroken first line because we set it to 0
-        // here. Maybe consider a None here.
-        let mut last_whitespace = None;
-        for (idx, ch) in text.char_indices() {
-            match ch {
-                '\n' => {
-                    scrapwidth = 0;
-                    wordwidth = 0;
-                    last_whitespace = None; // FIXME: Or None?
-                    breaklist.push(idx)
-                }
-                ch if maxwidth.is_some() => {
-                    if ch.is_whitespace() {
-                        last_whitespace = Some(idx);
-                        wordwidth = 0;
-                    }
-                    let glyphwidth = font.glyph(ch).map_or(0, |ch| ch.width) as u32;
-                    // TODO: Think about this unwrap().
-                    if scrapwidth + glyphwidth > maxwidth.unwrap() {
-                        let br = match last_whitespace {
-                            Some(br) => br,
-                            None => {
-                                wordwidth = 0;
-                                idx
-                            }
-                        };
-                        breaklist.push(br);
-                        wordwidth += glyphwidth;
-                        scrapwidth = wordwidth;
-                    } else {
-                        wordwidth += glyphwidth;
-                        scrapwidth += glyphwidth;
-                    }
-                }
-                _ => {}
+        if let Some((width, wrapped_font)) = &self.2 {
+            if *width == maxwidth && Rc::ptr_eq(wrapped_font, font) {
+                return;
             }
         }
-
-        breaklist.push(text.len());
+        let Self(text, breaklist, wrapped_for, hanging_indent) = self;
+        *wrapped_for = Some((maxwidth, Rc::clone(font)));
+        *breaklist = breaks(text, maxwidth, *hanging_indent, |ch| {
+            font.glyph(ch).map_or(0, |glyph| glyph.width) as u32
+        });
     }
 
     /// Returns an iterator over the lines of this [`WrappedText`].
@@ -94,15 +99,48 @@ impl WrappedText {
         let mut runner = 0;
         self.1.iter().map(move |&breakpoint| {
             let a = &self.0[runner..breakpoint];
+            let at_break = runner > 0;
             runner = breakpoint;
-            if a.chars().next().is_some_and(|ch| ch.is_whitespace()) {
-                &a[1..]
-            } else {
-                a
+            // A line that follows a break starts with the newline or whitespace it was broken at.
+            match a.chars().next() {
+                Some(ch) if at_break && ch.is_whitespace() => &a[ch.len_utf8()..],
+                _ => a,
             }
         })
     }
 
+    /// Returns the byte offset into the text at which the wrapped `line` starts.
+    ///
+    /// A line past the last one starts at the end of the text.
+    pub fn line_start(&self, line: usize) -> usize {
+        match line {
+            0 => 0,
+            line => self.1.get(line - 1).copied().unwrap_or(self.0.len()),
+        }
+    }
+
+    /// Returns the index of the wrapped line that the byte `offset` into the text is on.
+    ///
+    /// An offset past the end of the text is on the last line.
+    pub fn line_of(&self, offset: usize) -> usize {
+        let line = self.1.partition_point(|&breakpoint| breakpoint <= offset);
+        line.min(self.1.len().saturating_sub(1))
+    }
+
+    /// Returns how far the lines that continue a wrapped line are indented.
+    pub fn hanging_indent(&self) -> u32 {
+        self.3
+    }
+
+    /// Returns the indent before the wrapped `line`, which is the hanging indent if it continues
+    /// the line before it, and nothing if it starts the text or follows a newline.
+    pub fn indent_of(&self, line: usize) -> u32 {
+        match line.checked_sub(1).and_then(|prev| self.1.get(prev)) {
+            Some(&breakpoint) if !self.0[breakpoint..].starts_with('\n') => self.3,
+            _ => 0,
+        }
+    }
+
     /// Returns the number of wrapped lines in this [`WrappedText`].
     pub fn lines_count(&self) -> usize {
         self.1.len()
@@ -115,3 +153,173 @@ impl WrappedText {
         self.lines().intersperse("\n").collect()
     }
 }
+
+/// Returns where the lines of `text` end when it is wrapped to `maxwidth`, with every character as
+/// wide as `glyph_width` measures it. The last line ends at the end of the text.
+///
+/// Lines that continue a line that was broken at the `maxwidth` are wrapped to `hanging_indent`
+/// less than that, so that they fit once indented.
+///
+/// If `maxwidth` is `None`, the lines are only broken at newlines.
+fn breaks(
+    text: &str,
+    maxwidth: Option<u32>,
+    hanging_indent: u32,
+    glyph_width: impl Fn(char) -> u32,
+) -> Vec<usize> {
+    let mut breaklist = Vec::new();
+    // The room on the current line, which is less on a line that continues a broken one.
+    let mut linewidth = maxwidth;
+    let mut continues = false;
+    let mut scrapwidth = 0u32;
+    let mut wordwidth = 0u32;
+    // Whitespace is only a place to break a line once a word came before it on that line.
+    // Otherwise, breaking there would leave nothing but whitespace on the line before it.
+    let mut line_has_word = false;
+    let mut last_whitespace = None;
+    for (idx, ch) in text.char_indices() {
+        match ch {
+            '\n' => {
+                scrapwidth = 0;
+                wordwidth = 0;
+                line_has_word = false;
+                last_whitespace = None;
+                linewidth = maxwidth;
+                continues = false;
+                breaklist.push(idx)
+            }
+            ch if maxwidth.is_some() => {
+                let is_whitespace = ch.is_whitespace();
+                // The start of a line that is as wide as the hanging indent is what the lines that
+                // continue it hang under, so breaking within it would leave them under nothing.
+                let in_prefix = !continues && scrapwidth < hanging_indent;
+                if is_whitespace && line_has_word && !in_prefix {
+                    last_whitespace = Some(idx);
+                }
+                let glyphwidth = glyph_width(ch);
+                // TODO: Think about this unwrap().
+                // A glyph that does not even fit on an empty line is left to stick out, since
+                // breaking before it would only add an empty line.
+                if scrapwidth > 0 && scrapwidth + glyphwidth > linewidth.unwrap() {
+                    // The line is broken here, one way or another.
+                    linewidth = maxwidth.map(|width| width.saturating_sub(hanging_indent));
+                    continues = true;
+                    match last_whitespace.take() {
+                        // The whitespace at the break is dropped, so it takes up no room.
+                        Some(br) if br == idx => {
+                            breaklist.push(br);
+                            scrapwidth = 0;
+                            line_has_word = false;
+                        }
+                        // The word since the whitespace moves to the next line.
+                        Some(br) => {
+                            breaklist.push(br);
+                            scrapwidth = wordwidth + glyphwidth;
+                            line_has_word = true;
+                        }
+                        // A word that does not fit on a line of its own is broken where it
+                        // overflows.
+                        None => {
+                            breaklist.push(idx);
+                            scrapwidth = glyphwidth;
+                            line_has_word = !is_whitespace;
+                        }
+                    }
+                } else {
+                    scrapwidth += glyphwidth;
+                    line_has_word |= !is_whitespace;
+                }
+                // The width of the word so far, which starts after the last whitespace.
+                wordwidth = if is_whitespace {
+                    0
+                } else {
+                    wordwidth + glyphwidth
+                };
+            }
+            _ => {}
+        }
+    }
+
+    breaklist.push(text.len());
+    breaklist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "aaaa bbbb";
+
+    /// Returns the bytes of a font in which every glyph is `width` pixels wide.
+    fn font_bytes(width: u8) -> [u8; fleck::FILE_SIZE] {
+        let mut bytes = [0; fleck::FILE_SIZE];
+        bytes[..0x100].fill(width);
+        bytes
+    }
+
+    #[test]
+    fn continuation_lines_hang_by_the_indent() {
+        let text = "=> aaaa bbbb cccc dddd";
+        let wrapped = WrappedText::with_glyph_widths(text.to_string(), 9, 3, |_| 1);
+        let lines: Vec<_> = wrapped.lines().collect();
+        assert_eq!(lines, ["=> aaaa", "bbbb", "cccc", "dddd"]);
+        let indents: Vec<_> = (0..lines.len()).map(|idx| wrapped.indent_of(idx)).collect();
+        assert_eq!(indents, [0, 3, 3, 3]);
+    }
+
+    #[test]
+    fn whitespace_in_the_indent_of_the_first_line_is_no_break() {
+        let wrapped = WrappedText::with_glyph_widths("=> aaaaaaaa".to_string(), 6, 3, |_| 1);
+        assert_eq!(wrapped.lines().next(), Some("=> aaa"));
+    }
+
+    #[test]
+    fn rewrapping_for_the_same_width_and_font_keeps_the_lines() {
+        let font = Rc::new(Font::new(&font_bytes(8)));
+        let mut wrapped = WrappedText::new(TEXT.to_string(), 40, &font);
+        let lines = wrapped.lines().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(lines, ["aaaa", "bbbb"]);
+        wrapped.rewrap(Some(40), &font);
+        assert_eq!(wrapped.lines().collect::<Vec<_>>(), lines);
+        // The font is held on to, for as long as the lines are wrapped for it.
+        assert_eq!(Rc::strong_count(&font), 2);
+    }
+
+    #[test]
+    fn rewrapping_for_another_font_wraps_anew() {
+        let font = Rc::new(Font::new(&font_bytes(8)));
+        let mut wrapped = WrappedText::new(TEXT.to_string(), 40, &font);
+        assert_eq!(wrapped.lines_count(), 2);
+        drop(font);
+        wrapped.rewrap(Some(40), &Rc::new(Font::new(&font_bytes(1))));
+        assert_eq!(wrapped.lines().collect::<Vec<_>>(), [TEXT]);
+    }
+
+    /// Returns the `text` wrapped to `width` with every character one wide, after checking that
+    /// every word is in it exactly once.
+    fn wrap_every_word(text: &str, width: u32) -> Vec<String> {
+        let font = Rc::new(Font::new(&font_bytes(1)));
+        let wrapped = WrappedText::new(text.to_string(), width, &font);
+        let lines: Vec<_> = wrapped.lines().map(str::to_string).collect();
+        let joined: String = lines.concat();
+        let without_whitespace = |text: &str| text.split_whitespace().collect::<String>();
+        assert_eq!(without_whitespace(&joined), without_whitespace(text));
+        lines
+    }
+
+    #[test]
+    fn word_too_long_for_a_line_is_broken_where_it_overflows() {
+        assert_eq!(wrap_every_word("aaaaaaaaaa", 4), ["aaaa", "aaaa", "aa"]);
+        let lines = wrap_every_word("bb aaaaaaaaaa cc", 4);
+        assert_eq!(lines, ["bb", "aaaa", "aaaa", "aa", "cc"]);
+        assert_eq!(wrap_every_word("aaaaa", 1).len(), 5);
+    }
+
+    #[test]
+    fn leading_spaces_are_no_place_to_break() {
+        let lines = wrap_every_word("   aaaa bbbb", 6);
+        assert_eq!(lines, ["   aaa", "a bbbb"]);
+        assert_eq!(wrap_every_word("  aa bb", 5), ["  aa", "bb"]);
+        assert!(wrap_every_word("", 4).iter().all(String::is_empty));
+    }
+}
@@ -0,0 +1,140 @@
+//! Shortening text that does not fit, by leaving part of it out.
+
+use gemininini::Font;
+
+/// What is shown in place of the part of the text that was left out.
+const ELLIPSIS: &str = "...";
+
+/// Returns the longest end of `text` that is at most `max_width` wide in the `font`.
+fn fitting_tail<'t>(text: &'t str, max_width: usize, font: &Font) -> &'t str {
+    let mut width = 0;
+    for (idx, ch) in text.char_indices().rev() {
+        width += font.determine_width(ch.encode_utf8(&mut [0; 4]));
+        if width > max_width {
+            return &text[idx + ch.len_utf8()..];
+        }
+    }
+    text
+}
+
+/// Returns the longest start of `text` that is at most `max_width` wide in the `font`.
+fn fitting_head<'t>(text: &'t str, max_width: usize, font: &Font) -> &'t str {
+    let mut width = 0;
+    for (idx, ch) in text.char_indices() {
+        width += font.determine_width(ch.encode_utf8(&mut [0; 4]));
+        if width > max_width {
+            return &text[..idx];
+        }
+    }
+    text
+}
+
+/// Returns `text` with as much of its start left out as is needed to fit in `max_width`.
+///
+/// This keeps the end of the text in view, which is where the caret is when typing.
+pub fn elide_start(text: &str, max_width: usize, font: &Font) -> String {
+    if font.determine_width(text) <= max_width {
+        return text.to_string();
+    }
+    match max_width.checked_sub(font.determine_width(ELLIPSIS)) {
+        Some(room) => format!("{ELLIPSIS}{}", fitting_tail(text, room, font)),
+        None => fitting_tail(text, max_width, font).to_string(),
+    }
+}
+
+/// Returns `text` with as much of its end left out as is needed to fit in `max_width`.
+pub fn elide_end(text: &str, max_width: usize, font: &Font) -> String {
+    if font.determine_width(text) <= max_width {
+        return text.to_string();
+    }
+    match max_width.checked_sub(font.determine_width(ELLIPSIS)) {
+        Some(room) => format!("{}{ELLIPSIS}", fitting_head(text, room, font)),
+        None => fitting_head(text, max_width, font).to_string(),
+    }
+}
+
+/// Returns `text` with as much of its middle left out as is needed to fit in `max_width`.
+///
+/// The first `keep` bytes of the text are kept, along with as much of the end as fits. If even
+/// those first bytes do not fit, the start is left out instead, as with [`elide_start`].
+pub fn elide_middle(text: &str, keep: usize, max_width: usize, font: &Font) -> String {
+    if font.determine_width(text) <= max_width {
+        return text.to_string();
+    }
+    let (head, rest) = text.split_at(keep);
+    let head_width = font.determine_width(head) + font.determine_width(ELLIPSIS);
+    if head_width > max_width {
+        return elide_start(text, max_width, font);
+    }
+    let tail = fitting_tail(rest, max_width - head_width, font);
+    format!("{head}{ELLIPSIS}{tail}")
+}
+
+/// Returns the length of the part of `address` up to and including its host, such as
+/// `gemini://example.org` in `gemini://example.org/some/page.gmi`.
+pub fn host_end(address: &str) -> usize {
+    let Some(authority) = address.find("://").map(|idx| idx + "://".len()) else {
+        return 0;
+    };
+    address[authority..]
+        .find(['/', '?', '#'])
+        .map_or(address.len(), |idx| authority + idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use gemininini::font::fallback;
+
+    use super::*;
+
+    const ADDRESS: &str = "gemini://example.org/a/very/long/path/to/some/page.gmi";
+
+    #[test]
+    fn text_that_fits_is_kept_whole() {
+        let font = fallback();
+        let width = font.determine_width(ADDRESS);
+        assert_eq!(elide_start(ADDRESS, width, &font), ADDRESS);
+        assert_eq!(elide_middle(ADDRESS, 20, width, &font), ADDRESS);
+    }
+
+    #[test]
+    fn elided_text_fits() {
+        let font = fallback();
+        let full = font.determine_width(ADDRESS);
+        for max_width in [0, 1, full / 4, full / 2, full - 1] {
+            let start = elide_start(ADDRESS, max_width, &font);
+            assert!(font.determine_width(&start) <= max_width, "{start:?}");
+            assert!(ADDRESS.ends_with(start.trim_start_matches(ELLIPSIS)));
+            let middle = elide_middle(ADDRESS, host_end(ADDRESS), max_width, &font);
+            assert!(font.determine_width(&middle) <= max_width, "{middle:?}");
+        }
+    }
+
+    #[test]
+    fn middle_is_left_out_between_the_host_and_the_end() {
+        let font = fallback();
+        let host = "gemini://example.org";
+        let max_width = font.determine_width(ADDRESS) * 3 / 4;
+        let elided = elide_middle(ADDRESS, host_end(ADDRESS), max_width, &font);
+        let tail = elided.strip_prefix(&format!("{host}{ELLIPSIS}")).unwrap();
+        assert!(ADDRESS.ends_with(tail) && tail.ends_with("page.gmi"));
+        // Without room for the host, the end is kept.
+        let narrow = font.determine_width(host) / 2;
+        let elided = elide_middle(ADDRESS, host_end(ADDRESS), narrow, &font);
+        assert_eq!(elided, elide_start(ADDRESS, narrow, &font));
+    }
+
+    #[test]
+    fn host_ends_before_the_path_query_or_fragment() {
+        assert_eq!(host_end(ADDRESS), "gemini://example.org".len());
+        assert_eq!(
+            host_end("gemini://example.org?q"),
+            "gemini://example.org".len()
+        );
+        assert_eq!(
+            host_end("gemini://example.org"),
+            "gemini://example.org".len()
+        );
+        assert_eq!(host_end("about:blank"), 0);
+    }
+}
@@ -0,0 +1,55 @@
+//! Explaining why a page could not be loaded, by the status of the response that came instead.
+
+/// Returns whether a response with `status` failed, in which case its meta says why instead of
+/// there being a page.
+pub fn is_failure(status: u8) -> bool {
+    matches!(status / 10, 4..=6)
+}
+
+/// Returns what it means that a response has the failure `status`, in a sentence.
+///
+/// The few statuses that call for something particular to be done get their own explanation. The
+/// others are explained by their class, which is what the first of their two digits stands for.
+pub fn explain_status(status: u8) -> &'static str {
+    match status {
+        44 => "The server asks to slow down. Wait a moment before trying again.",
+        51 => "There is no page at this address. It may have moved, or never existed.",
+        52 => "The page that was at this address is gone for good.",
+        59 => "The server could not make sense of the request, so the address may be malformed.",
+        61 => "The client certificate that was presented is not allowed to see this page.",
+        62 => "The client certificate that was presented is not valid.",
+        _ => match status / 10 {
+            4 => "Something went wrong for now. Trying again later may well work.",
+            5 => "The page cannot be loaded, and trying again will not change that.",
+            6 => "The page is only shown to those who present a client certificate.",
+            _ => "The page could not be loaded.",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_temporary_permanent_and_certificate_statuses_fail() {
+        assert!(!is_failure(20));
+        assert!(!is_failure(31));
+        assert!([40, 44, 51, 59, 60, 62].into_iter().all(is_failure));
+        assert!(!is_failure(10));
+    }
+
+    #[test]
+    fn statuses_are_explained_by_themselves_or_their_class() {
+        assert_eq!(
+            explain_status(51),
+            "There is no page at this address. It may have moved, or never existed."
+        );
+        assert_eq!(explain_status(42), explain_status(40));
+        assert_eq!(explain_status(53), explain_status(50));
+        assert_eq!(explain_status(63), explain_status(60));
+        assert_ne!(explain_status(44), explain_status(40));
+        assert_ne!(explain_status(40), explain_status(50));
+        assert_eq!(explain_status(99), "The page could not be loaded.");
+    }
+}
@@ -0,0 +1,174 @@
+//! The `favicon.txt` convention, by which a capsule names a single emoji to stand for it.
+
+use url::Url;
+
+/// Where a capsule keeps its favicon, relative to its root.
+const FAVICON_PATH: &str = "/favicon.txt";
+/// The scheme of the capsules that are asked for a favicon.
+const GEMINI_SCHEME: &str = "gemini";
+
+/// Joins emoji into a single one, such as the members of a family.
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+/// Turns a digit, `#` or `*` into a keycap emoji.
+const COMBINING_KEYCAP: char = '\u{20e3}';
+/// The blocks of characters that are emoji, or mostly are.
+const EMOJI_RANGES: &[(char, char)] = &[
+    ('\u{2190}', '\u{21ff}'),   // Arrows.
+    ('\u{2300}', '\u{23ff}'),   // Miscellaneous Technical.
+    ('\u{25a0}', '\u{25ff}'),   // Geometric Shapes.
+    ('\u{2600}', '\u{27bf}'),   // Miscellaneous Symbols and Dingbats.
+    ('\u{2900}', '\u{297f}'),   // Supplemental Arrows-B.
+    ('\u{2b00}', '\u{2bff}'),   // Miscellaneous Symbols and Arrows.
+    ('\u{1f000}', '\u{1faff}'), // From Mahjong Tiles to Symbols and Pictographs Extended-A.
+];
+/// The characters outside of [`EMOJI_RANGES`] that are emoji.
+const EMOJI_CHARS: &[char] = &[
+    '\u{a9}', '\u{ae}', '\u{203c}', '\u{2049}', '\u{2122}', '\u{2139}', '\u{3030}', '\u{303d}',
+    '\u{3297}', '\u{3299}',
+];
+/// The regional indicator symbols, two of which make a flag.
+const REGIONAL_INDICATORS: (char, char) = ('\u{1f1e6}', '\u{1f1ff}');
+/// The characters that can follow an emoji without making it a different one, such as variation
+/// selectors, skin tones, and the tags of subdivision flags.
+const EMOJI_MODIFIERS: &[(char, char)] = &[
+    ('\u{fe0e}', '\u{fe0f}'),
+    ('\u{1f3fb}', '\u{1f3ff}'),
+    ('\u{e0020}', '\u{e007f}'),
+];
+
+/// Returns whether `ch` is in one of the `ranges`.
+fn in_ranges(ch: char, ranges: &[(char, char)]) -> bool {
+    ranges
+        .iter()
+        .any(|&(first, last)| (first..=last).contains(&ch))
+}
+
+/// Returns whether `ch` can stand on its own as an emoji.
+fn is_emoji(ch: char) -> bool {
+    in_ranges(ch, EMOJI_RANGES) || EMOJI_CHARS.contains(&ch)
+}
+
+/// Returns whether `ch` is a regional indicator symbol.
+fn is_regional_indicator(ch: char) -> bool {
+    in_ranges(ch, &[REGIONAL_INDICATORS])
+}
+
+/// Returns whether `text` is exactly one emoji, which may be made up of several characters, such
+/// as a flag, a keycap, or emoji joined together.
+pub fn is_single_emoji(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    // A keycap is an ordinary character that is made into an emoji by what follows it.
+    if first.is_ascii_digit() || first == '#' || first == '*' {
+        let rest: String = chars.filter(|&ch| ch != '\u{fe0f}').collect();
+        return rest == COMBINING_KEYCAP.to_string();
+    }
+    // A flag is a pair of regional indicators, and no more than that.
+    if is_regional_indicator(first) {
+        return chars.next().is_some_and(is_regional_indicator) && chars.next().is_none();
+    }
+    if !is_emoji(first) {
+        return false;
+    }
+    while let Some(ch) = chars.next() {
+        if ch == ZERO_WIDTH_JOINER {
+            if !chars.next().is_some_and(is_emoji) {
+                return false;
+            }
+        } else if !in_ranges(ch, EMOJI_MODIFIERS) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Returns the favicon in the `body` of a `favicon.txt`, if it is a single emoji.
+pub fn parse_favicon(body: &str) -> Option<&str> {
+    let favicon = body.trim();
+    is_single_emoji(favicon).then_some(favicon)
+}
+
+/// Returns the address of the favicon of the capsule that `address` is on, if it can have one.
+pub fn favicon_url(address: &str) -> Option<String> {
+    let mut url = Url::parse(address).ok()?;
+    if url.scheme() != GEMINI_SCHEME || url.host_str().is_none() {
+        return None;
+    }
+    url.set_path(FAVICON_PATH);
+    url.set_query(None);
+    url.set_fragment(None);
+    Some(url.to_string())
+}
+
+/// Returns the host of the capsule that `address` is on, which its favicon is kept under.
+///
+/// Hosts are told apart ignoring case, so the host is lowercased, which the [`Url`] parser only
+/// does for the schemes it knows, and not for `gemini:`.
+pub fn capsule_host(address: &str) -> Option<String> {
+    Some(Url::parse(address).ok()?.host_str()?.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_emoji_of_any_make_is_a_favicon() {
+        let emoji = [
+            "\u{1f680}",                                   // Rocket.
+            "\u{2615}",                                    // Hot beverage.
+            "\u{2764}\u{fe0f}",                            // Red heart, as an emoji.
+            "\u{1f44d}\u{1f3fd}",                          // Thumbs up, with a skin tone.
+            "\u{1f1f3}\u{1f1f1}",                          // Flag of the Netherlands.
+            "1\u{fe0f}\u{20e3}",                           // Keycap one.
+            "\u{1f469}\u{200d}\u{1f4bb}",                  // Woman technologist.
+            "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}", // Family.
+        ];
+        for favicon in emoji {
+            assert!(is_single_emoji(favicon), "{favicon:?}");
+        }
+    }
+
+    #[test]
+    fn text_and_several_emoji_are_no_favicon() {
+        let not_emoji = [
+            "",
+            "a",
+            "1",
+            "\u{1f680}\u{1f680}",
+            "\u{1f680} ",
+            "\u{1f1f3}",
+            "\u{1f1f3}\u{1f1f1}\u{1f1f3}",
+            "\u{1f469}\u{200d}",
+            "\u{1f469}\u{200d}a",
+            "12\u{20e3}",
+        ];
+        for text in not_emoji {
+            assert!(!is_single_emoji(text), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn favicon_file_is_trimmed() {
+        assert_eq!(parse_favicon(" \u{1f680}\n"), Some("\u{1f680}"));
+        assert_eq!(parse_favicon("<html>"), None);
+        assert_eq!(parse_favicon(""), None);
+    }
+
+    #[test]
+    fn favicon_is_at_the_root_of_gemini_capsules() {
+        let favicon = favicon_url("gemini://example.org:1966/dir/page.gmi?q#top");
+        assert_eq!(
+            favicon.as_deref(),
+            Some("gemini://example.org:1966/favicon.txt")
+        );
+        assert_eq!(favicon_url("https://example.org/"), None);
+        assert_eq!(favicon_url("file:///home/page.gmi"), None);
+        assert_eq!(favicon_url("not an address"), None);
+        let host = capsule_host("gemini://Example.ORG/dir/page.gmi");
+        assert_eq!(host.as_deref(), Some("example.org"));
+        assert_eq!(capsule_host("data:text/plain,Hi"), None);
+    }
+}
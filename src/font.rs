@@ -0,0 +1,281 @@
+//! Questions about [`Font`]s that [`fleck`] does not answer by itself.
+
+use std::fmt;
+use std::path::Path;
+
+use fleck::{Font, FILE_SIZE};
+
+/// The uf2 font that is compiled into the binary, to fall back on when no other font can be loaded.
+pub const FALLBACK_FONT: &[u8; FILE_SIZE] = include_bytes!("../fonts/cream12.uf2");
+
+/// The number of bytes that hold the widths of the glyphs, at the start of a uf2 file.
+const WIDTHS_SIZE: usize = 0x100;
+
+/// What went wrong when loading a uf2 [`Font`].
+///
+/// A uf2 file starts right away with the widths of its glyphs, without any magic number to check,
+/// so a file that is not a font at all can only be told apart by its size. That makes such a file
+/// either [`FontError::UnexpectedEof`] or [`FontError::TrailingBytes`], and there is no error for a
+/// bad magic number. Likewise, [`FontError::BadGlyph`] only names the `index` of the glyph, since
+/// the offset of its width in the file is that same number.
+#[derive(Debug)]
+pub enum FontError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The data ends before the end of the last glyph, after `len` bytes.
+    UnexpectedEof { len: usize },
+    /// The data goes on after the last glyph, for a total of `len` bytes.
+    TrailingBytes { len: usize },
+    /// The glyph for the character with code `index` is wider than a glyph can be. Its width is
+    /// at the byte with the same offset as the code, since the widths come first.
+    BadGlyph { index: u8 },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Io(err) => write!(f, "{err}"),
+            FontError::UnexpectedEof { len } => {
+                write!(f, "font ends after {len} of {FILE_SIZE} bytes")
+            }
+            FontError::TrailingBytes { len } => {
+                write!(f, "not a uf2 font, {len} bytes is over {FILE_SIZE}")
+            }
+            FontError::BadGlyph { index } => write!(
+                f,
+                "bad glyph {index:#04x}: width at offset {index:#06x} is over {}",
+                Font::GLYPH_WIDTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FontError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FontError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FontError {
+    fn from(err: std::io::Error) -> Self {
+        FontError::Io(err)
+    }
+}
+
+/// Returns the [`FALLBACK_FONT`].
+pub fn fallback() -> Font {
+    Font::new(FALLBACK_FONT)
+}
+
+/// Parse a uf2 [`Font`] from its bytes.
+///
+/// Unlike [`Font::new`], this takes a slice of any length, and reports an error if it is not the
+/// size of a uf2 file or describes glyphs that cannot be drawn.
+pub fn load_from_bytes(bytes: &[u8]) -> Result<Font, FontError> {
+    let len = bytes.len();
+    let bytes: &[u8; FILE_SIZE] = match bytes.try_into() {
+        Ok(bytes) => bytes,
+        Err(_) if len < FILE_SIZE => return Err(FontError::UnexpectedEof { len }),
+        Err(_) => return Err(FontError::TrailingBytes { len }),
+    };
+    let widths = &bytes[..WIDTHS_SIZE];
+    if let Some(offset) = widths
+        .iter()
+        .position(|&width| width as usize > Font::GLYPH_WIDTH)
+    {
+        // The widths come first, so the offset of a width is the code of its character.
+        let index = offset as u8;
+        return Err(FontError::BadGlyph { index });
+    }
+    Ok(Font::new(bytes))
+}
+
+/// Load a uf2 [`Font`] from the file at `path`.
+///
+/// Unlike [`Font::load_from_file`], this does not panic when the file is not a uf2 font.
+pub fn load_from_file(path: impl AsRef<Path>) -> Result<Font, FontError> {
+    load_from_bytes(&std::fs::read(path)?)
+}
+
+/// Load a uf2 [`Font`] from the file at `path`, or the [`fallback`] font if that fails.
+///
+/// When the fallback is used, the error that prevented loading the file is returned with it.
+pub fn load_or_fallback(path: impl AsRef<Path>) -> (Font, Option<FontError>) {
+    match load_from_file(path) {
+        Ok(font) => (font, None),
+        Err(err) => (fallback(), Some(err)),
+    }
+}
+
+/// Load the first uf2 [`Font`] of the files at `paths` that loads, or the [`fallback`] font if none
+/// of them does.
+///
+/// The errors that prevented loading the files before it are returned with it, by their index in
+/// `paths`.
+pub fn load_first_or_fallback(paths: &[impl AsRef<Path>]) -> (Font, Vec<(usize, FontError)>) {
+    let mut errors = Vec::new();
+    for (idx, path) in paths.iter().enumerate() {
+        match load_from_file(path) {
+            Ok(font) => return (font, errors),
+            Err(err) => errors.push((idx, err)),
+        }
+    }
+    (fallback(), errors)
+}
+
+/// Returns whether `font` has a glyph for `ch`.
+///
+/// A uf2 font describes every character up to `U+00FF`, but the ones it has no artwork for are
+/// given a width of zero. Those take up no room when drawn, so they are not counted as covered.
+pub fn covers(font: &Font, ch: char) -> bool {
+    font.glyph(ch).is_some_and(|glyph| glyph.width > 0)
+}
+
+/// Returns an iterator over the characters that `font` has glyphs for, in order.
+pub fn covered_chars(font: &Font) -> impl Iterator<Item = char> + '_ {
+    (0..=u8::MAX).map(char::from).filter(|&ch| covers(font, ch))
+}
+
+/// Returns every character of `text` along with whether `font` has a glyph for it.
+pub fn coverage_for(font: &Font, text: &str) -> Vec<(char, bool)> {
+    text.chars().map(|ch| (ch, covers(font, ch))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+
+    /// Returns the bytes of a font with glyphs for the ASCII letters only.
+    fn letters() -> [u8; fleck::FILE_SIZE] {
+        let mut bytes = [0; fleck::FILE_SIZE];
+        for ch in ('a'..='z').chain('A'..='Z') {
+            bytes[ch as usize] = 8;
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii_letters_are_covered_by_a_font_of_letters() {
+        let font = Font::new(&letters());
+        assert!(('a'..='z').chain('A'..='Z').all(|ch| covers(&font, ch)));
+        assert!(!covers(&font, '1'));
+        let covered: Vec<_> = covered_chars(&font).collect();
+        assert!(covered.contains(&'Q'));
+        assert!(covered.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(!covers(&font, '\u{2603}'));
+        assert_eq!(
+            coverage_for(&font, "a\u{2603}"),
+            [('a', true), ('\u{2603}', false)]
+        );
+    }
+
+    #[test]
+    fn glyphs_without_width_are_not_covered() {
+        let mut bytes = letters();
+        bytes[0x41] = 0;
+        let font = Font::new(&bytes);
+        assert!(!covers(&font, 'A'));
+        assert!(covered_chars(&font).all(|ch| ch != 'A'));
+        assert!(covers(&font, 'B'));
+    }
+
+    #[test]
+    fn font_from_a_file_is_the_font_from_its_bytes() {
+        let name = format!("gemininini-font-from-file-{}.uf2", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, letters()).unwrap();
+        let from_file = load_from_file(&path).unwrap();
+        let from_bytes = load_from_bytes(&letters()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for ch in (0..=u8::MAX).map(char::from) {
+            let (a, b) = (from_file.glyph(ch).unwrap(), from_bytes.glyph(ch).unwrap());
+            assert_eq!(a.width, b.width, "{ch:?}");
+            assert!(a.flatten().eq(b.flatten()), "{ch:?}");
+        }
+    }
+
+    #[test]
+    fn font_that_fails_to_load_is_replaced_by_the_fallback() {
+        let dir = std::env::temp_dir().join(format!("gemininini-fallback-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (bad, good) = (dir.join("bad.uf2"), dir.join("good.uf2"));
+        std::fs::write(&bad, &FALLBACK_FONT[..10]).unwrap();
+        std::fs::write(&good, letters()).unwrap();
+
+        let (font, err) = load_or_fallback(&good);
+        assert!(err.is_none());
+        assert!(!covers(&font, '1'));
+
+        let (font, err) = load_or_fallback(&bad);
+        assert!(matches!(err, Some(FontError::UnexpectedEof { len: 10 })));
+        assert!(covers(&font, '1'));
+
+        let (_, err) = load_or_fallback(dir.join("missing.uf2"));
+        assert!(matches!(err, Some(FontError::Io(err)) if err.kind() == ErrorKind::NotFound));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn first_font_that_loads_is_taken() {
+        let name = format!("gemininini-first-font-{}", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (bad, good) = (dir.join("bad.uf2"), dir.join("good.uf2"));
+        std::fs::write(&bad, &FALLBACK_FONT[..10]).unwrap();
+        std::fs::write(&good, letters()).unwrap();
+        let missing = dir.join("missing.uf2");
+
+        let (font, errors) = load_first_or_fallback(&[&missing, &bad, &good]);
+        assert!(!covers(&font, '1'));
+        assert!(matches!(
+            errors[..],
+            [(0, FontError::Io(_)), (1, FontError::UnexpectedEof { .. })]
+        ));
+
+        let (font, errors) = load_first_or_fallback(&[&missing, &bad]);
+        assert!(covers(&font, '1'));
+        assert_eq!(errors.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_font_ends_unexpectedly() {
+        let err = load_from_bytes(&FALLBACK_FONT[..100]).unwrap_err();
+        assert!(matches!(err, FontError::UnexpectedEof { len: 100 }));
+    }
+
+    #[test]
+    fn other_files_are_told_apart_by_their_size() {
+        // The start of a PNG file, which has a magic number, unlike a uf2 font.
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let err = load_from_bytes(png).unwrap_err();
+        assert!(matches!(err, FontError::UnexpectedEof { .. }));
+        let mut long = FALLBACK_FONT.to_vec();
+        long.extend_from_slice(png);
+        let err = load_from_bytes(&long).unwrap_err();
+        assert!(matches!(err, FontError::TrailingBytes { len } if len == long.len()));
+    }
+
+    #[test]
+    fn glyph_that_is_too_wide_is_bad() {
+        let mut bytes = *FALLBACK_FONT;
+        bytes[0x41] = Font::GLYPH_WIDTH as u8 + 1;
+        let err = load_from_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, FontError::BadGlyph { index: 0x41 }));
+        assert!(err.to_string().contains("0x0041"), "{err}");
+    }
+
+    #[test]
+    fn fallback_font_loads() {
+        assert!(load_from_bytes(FALLBACK_FONT).is_ok());
+        let (_, err) = load_or_fallback("/nonexistent/font.uf2");
+        assert!(matches!(err, Some(FontError::Io(_))));
+    }
+}
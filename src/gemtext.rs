@@ -0,0 +1,415 @@
+//! Just enough of a parser for the line-oriented gemtext format to tell its lines apart.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+const LINK_PREFIX: &str = "=>";
+const PREFORMAT_TOGGLE: &str = "```";
+const HEADING_PREFIX: char = '#';
+/// The deepest level of heading, `###`.
+const MAX_HEADING_LEVEL: usize = 3;
+/// The schemes that a bare URL in a line of text is recognized by.
+const BARE_URL_SCHEMES: &[&str] = &["gemini://", "https://", "http://"];
+/// The characters that end a bare URL, besides whitespace, since they are not allowed in URLs and
+/// tend to be written around them instead.
+const URL_DELIMITERS: &[char] = &['<', '>', '"', '`'];
+/// The punctuation that a sentence may put right after a bare URL, which is taken to belong to the
+/// sentence when it is the last thing in the URL.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ':', ';', '!', '?', '\'', '*'];
+/// The brackets that a bare URL may be written in, as pairs of the opening and closing one.
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+/// How many characters of its first line the label of a section has at most.
+const SECTION_LABEL_CHARS: usize = 60;
+/// What a label that is cut short ends with.
+const ELLIPSIS: char = '…';
+
+/// A single line of gemtext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line<'t> {
+    /// A line of regular text.
+    Text(&'t str),
+    /// A link line, such as `=> gemini://example.org/ An example`.
+    Link {
+        url: &'t str,
+        label: Option<&'t str>,
+    },
+    /// A heading line, such as `## Section`, with its level from 1 to 3.
+    Heading { level: usize, text: &'t str },
+    /// A line that starts or ends a preformatted block, with its optional alt text.
+    PreformatToggle(&'t str),
+    /// A line inside a preformatted block, which is to be shown exactly as it is.
+    Preformatted(&'t str),
+}
+
+impl<'t> Line<'t> {
+    /// Determine what kind of [`Line`] the `line` is.
+    pub fn parse(line: &'t str) -> Self {
+        if let Some(rest) = line.strip_prefix(LINK_PREFIX) {
+            let rest = rest.trim();
+            let (url, label) = match rest.split_once(char::is_whitespace) {
+                Some((url, label)) => (url, Some(label.trim_start())),
+                None => (rest, None),
+            };
+            // A link without a URL is not much of a link.
+            if !url.is_empty() {
+                return Self::Link { url, label };
+            }
+        }
+        let level = line.len() - line.trim_start_matches(HEADING_PREFIX).len();
+        if (1..=MAX_HEADING_LEVEL).contains(&level) {
+            let text = line[level..].trim();
+            return Self::Heading { level, text };
+        }
+        Self::Text(line)
+    }
+
+    /// Returns the bare URLs in this line, in order. Only a [`Line::Text`] has any, since the URL
+    /// of a link line is not bare, and all other lines are not looked into.
+    pub fn bare_urls(&self) -> Vec<&'t str> {
+        match *self {
+            Self::Text(text) => url_spans(text)
+                .into_iter()
+                .map(|span| &text[span])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns where the bare URLs in `text` are, as the byte ranges that they span, in order.
+///
+/// A bare URL starts with one of the recognized schemes, such as `gemini://`, unless that is in
+/// the middle of a word, and goes on until whitespace. Punctuation at its end is left to the
+/// sentence it is in, as is a closing bracket that has no opening one in the URL, such that the
+/// URL in `(see gemini://example.org/).` ends at the slash.
+pub fn url_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut prev = None;
+    let mut rest_start = 0;
+    for (idx, ch) in text.char_indices() {
+        let in_word = prev.is_some_and(char::is_alphanumeric);
+        prev = Some(ch);
+        if idx < rest_start || in_word {
+            continue;
+        }
+        let rest = &text[idx..];
+        let Some(scheme) = BARE_URL_SCHEMES.iter().find(|scheme| {
+            rest.get(..scheme.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+        }) else {
+            continue;
+        };
+        let len = rest
+            .find(|ch: char| ch.is_whitespace() || URL_DELIMITERS.contains(&ch))
+            .unwrap_or(rest.len());
+        let url = trim_url_end(&rest[..len]);
+        // A scheme with nothing after it is only talked about, not linked to.
+        if url.len() > scheme.len() {
+            spans.push(idx..idx + url.len());
+        }
+        rest_start = idx + len;
+    }
+    spans
+}
+
+/// Returns the `url` without the punctuation and unpaired closing brackets at its end, which
+/// belong to the text around it.
+fn trim_url_end(mut url: &str) -> &str {
+    while let Some(last) = url.chars().last() {
+        let unpaired = BRACKETS.iter().any(|&(open, close)| {
+            last == close && url.matches(close).count() > url.matches(open).count()
+        });
+        if !TRAILING_PUNCTUATION.contains(&last) && !unpaired {
+            break;
+        }
+        url = &url[..url.len() - last.len_utf8()];
+    }
+    url
+}
+
+/// Returns the `=>` that a link `line` starts with, along with the whitespace after it, which is
+/// where its URL starts. Any other line has no such prefix.
+pub fn link_prefix(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(LINK_PREFIX)?;
+    let prefix_len = line.len() - rest.trim_start().len();
+    Some(&line[..prefix_len])
+}
+
+/// Returns the `text` with every line ending made a newline, be it a newline already, a carriage
+/// return and a newline, or a carriage return alone, such that no carriage return is left.
+pub fn normalize_line_endings(text: String) -> String {
+    if !text.contains('\r') {
+        return text;
+    }
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Returns the `line` cleaned up for display.
+///
+/// Trailing whitespace is trimmed, and unless the line is `preformatted`, ASCII control characters
+/// are removed. Fonts have no glyphs for them, so they would only throw off the width of the line.
+pub fn clean(line: &str, preformatted: bool) -> Cow<'_, str> {
+    let trimmed = line.trim_end();
+    if !preformatted && trimmed.contains(|ch: char| ch.is_ascii_control()) {
+        Cow::Owned(
+            trimmed
+                .chars()
+                .filter(|ch| !ch.is_ascii_control())
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(trimmed)
+    }
+}
+
+/// Returns an iterator over the lines of a gemtext document, each paired with its parsed
+/// [`Line`].
+pub fn parse(text: &str) -> impl Iterator<Item = (&str, Line<'_>)> {
+    parse_as(text, true)
+}
+
+/// Returns an iterator over the lines of a document, each paired with its parsed [`Line`].
+///
+/// Unless the document is `gemtext`, such as one in `text/plain`, every line is [`Line::Text`],
+/// however it starts. There are no links or headings in plain text, only lines that look like them.
+pub fn parse_as(text: &str, gemtext: bool) -> impl Iterator<Item = (&str, Line<'_>)> {
+    let mut preformatted = false;
+    text.lines().map(move |line| {
+        let parsed = if !gemtext {
+            Line::Text(line)
+        } else if let Some(alt) = line.strip_prefix(PREFORMAT_TOGGLE) {
+            preformatted = !preformatted;
+            Line::PreformatToggle(alt.trim())
+        } else if preformatted {
+            Line::Preformatted(line)
+        } else {
+            Line::parse(line)
+        };
+        (line, parsed)
+    })
+}
+
+/// Returns whether a gemtext document has nothing to show, because it is empty or only has blank
+/// lines.
+pub fn is_blank(text: &str) -> bool {
+    parse(text).all(|(_, line)| matches!(line, Line::Text(text) if text.trim().is_empty()))
+}
+
+/// Returns an iterator over the links of a gemtext document, as `(label, url)` pairs. A link
+/// without a label is labelled with its URL.
+pub fn links(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    links_as(text, true, false)
+}
+
+/// Returns an iterator over the links of a document, as [`links`] does, unless it is not `gemtext`
+/// and has none.
+///
+/// With `bare_urls`, the bare URLs in the lines of text of a gemtext document are links as well,
+/// each labelled with itself, in the order they come in along with the link lines.
+pub fn links_as(text: &str, gemtext: bool, bare_urls: bool) -> impl Iterator<Item = (&str, &str)> {
+    parse_as(text, gemtext).flat_map(move |(_, line)| match line {
+        Line::Link { url, label } => vec![(label.unwrap_or(url), url)],
+        line if gemtext && bare_urls => {
+            line.bare_urls().into_iter().map(|url| (url, url)).collect()
+        }
+        _ => Vec::new(),
+    })
+}
+
+/// Returns the outline of a page made up of the `lines`, which is its headings, each as its level,
+/// its text, and the index of its line.
+pub fn outline(lines: &[Line]) -> Vec<(u8, String, usize)> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, line)| match *line {
+            Line::Heading { level, text } => Some((level as u8, text.to_string(), idx)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the sections of a page made up of the `lines`, each as its label and the index of the
+/// line it starts at.
+///
+/// Every heading starts a section, labelled with its text, and so does every other line that comes
+/// right after a blank one, labelled with the start of what it shows. Blank lines inside a
+/// preformatted block are part of the block, and split nothing.
+pub fn sections(lines: &[Line]) -> Vec<(String, usize)> {
+    let mut sections = Vec::new();
+    let mut after_blank = false;
+    for (idx, line) in lines.iter().enumerate() {
+        let label = match *line {
+            Line::Heading { text, .. } => Some(text),
+            Line::Text(text) if text.trim().is_empty() => {
+                after_blank = true;
+                continue;
+            }
+            _ if !after_blank => None,
+            Line::Text(text) => Some(text.trim()),
+            Line::Link { url, label } => Some(label.unwrap_or(url)),
+            Line::PreformatToggle("") => Some(PREFORMAT_TOGGLE),
+            Line::PreformatToggle(alt) => Some(alt),
+            Line::Preformatted(text) => Some(text.trim()),
+        };
+        after_blank = false;
+        if let Some(label) = label {
+            sections.push((section_label(label), idx));
+        }
+    }
+    sections
+}
+
+/// Returns the `text` as the label of a section, cut short if it is too long to be one.
+fn section_label(text: &str) -> String {
+    match text.char_indices().nth(SECTION_LABEL_CHARS) {
+        Some((end, _)) => format!("{}{ELLIPSIS}", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// Returns the preformatted blocks of a gemtext document, each as the index of its first line
+/// and the lines inside it.
+pub fn preformatted_blocks(text: &str) -> Vec<(usize, Vec<&str>)> {
+    let mut blocks: Vec<(usize, Vec<&str>)> = Vec::new();
+    let mut inside = false;
+    for (idx, (_, line)) in parse(text).enumerate() {
+        match line {
+            Line::PreformatToggle(_) => {
+                inside = !inside;
+                if inside {
+                    blocks.push((idx + 1, Vec::new()));
+                }
+            }
+            Line::Preformatted(line) => blocks.last_mut().unwrap().1.push(line),
+            _ => {}
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_characters_and_trailing_whitespace_are_cleaned() {
+        assert_eq!(clean("ring\u{7} the bell  \t", false), "ring the bell");
+        assert!(matches!(clean("as it is", false), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn preformatted_lines_keep_their_control_characters() {
+        assert_eq!(clean("\u{1b}[1mbold  ", true), "\u{1b}[1mbold");
+    }
+
+    #[test]
+    fn preformatted_blocks_are_not_parsed() {
+        let text = "```alt\n=> not a link\n```\n=> gemini://example.org/ A link";
+        let lines: Vec<_> = parse(text).map(|(_, line)| line).collect();
+        assert_eq!(
+            lines,
+            [
+                Line::PreformatToggle("alt"),
+                Line::Preformatted("=> not a link"),
+                Line::PreformatToggle(""),
+                Line::Link {
+                    url: "gemini://example.org/",
+                    label: Some("A link")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn outline_lists_the_headings_with_their_lines() {
+        let text = "# Top\nintro\n## Middle\n```\n# not a heading\n```\n### Bottom\n#### Too deep";
+        let lines: Vec<_> = parse(text).map(|(_, line)| line).collect();
+        assert_eq!(
+            outline(&lines),
+            [
+                (1, "Top".to_string(), 0),
+                (2, "Middle".to_string(), 2),
+                (3, "Bottom".to_string(), 6),
+            ]
+        );
+        assert_eq!(outline(&[Line::Text("no headings")]), []);
+    }
+
+    #[test]
+    fn bare_urls_leave_the_punctuation_around_them() {
+        let urls = |text| Line::Text(text).bare_urls();
+        assert_eq!(
+            urls("See gemini://example.org/a. And https://example.com/b, too!"),
+            ["gemini://example.org/a", "https://example.com/b"]
+        );
+        assert_eq!(
+            urls("(see gemini://example.org/)."),
+            ["gemini://example.org/"]
+        );
+        // A closing bracket that is opened in the URL is part of it.
+        assert_eq!(
+            urls("http://example.org/Foo_(bar)?"),
+            ["http://example.org/Foo_(bar)"]
+        );
+        assert_eq!(urls("<gemini://example.org/x>"), ["gemini://example.org/x"]);
+        // A scheme in the middle of a word, or with nothing after it, is no URL.
+        assert_eq!(
+            urls("xgemini://example.org/ or https://"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(url_spans("a GEMINI://Example.org b"), vec![2..22]);
+        assert_eq!(
+            Line::Link {
+                url: "gemini://example.org/",
+                label: None
+            }
+            .bare_urls(),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn bare_urls_are_links_only_when_asked_for() {
+        let text =
+            "=> /first First\nsee gemini://example.org/second\n```\nhttps://example.org/\n```";
+        let links: Vec<_> = links_as(text, true, true).map(|(_, url)| url).collect();
+        assert_eq!(links, ["/first", "gemini://example.org/second"]);
+        assert_eq!(links_as(text, true, false).count(), 1);
+        assert_eq!(links_as(text, false, true).count(), 0);
+    }
+
+    #[test]
+    fn every_kind_of_line_ending_becomes_a_newline() {
+        let text = "one\r\ntwo\rthree\nfour\r\r\nsix".to_string();
+        let normal = normalize_line_endings(text);
+        assert!(!normal.contains('\r'));
+        let lines: Vec<_> = normal.lines().collect();
+        assert_eq!(lines, ["one", "two", "three", "four", "", "six"]);
+        assert_eq!(normalize_line_endings("a\nb".to_string()), "a\nb");
+    }
+
+    #[test]
+    fn sections_start_at_headings_and_after_blank_lines() {
+        let long = "x".repeat(SECTION_LABEL_CHARS + 10);
+        let text = format!(
+            "intro\n## Part\nmore\n\n  second\n\n=> /next Next\n\n```\n\ncode\n```\n\n{long}"
+        );
+        let lines: Vec<_> = parse(&text).map(|(_, line)| line).collect();
+        let found = sections(&lines);
+        let labels: Vec<_> = found.iter().map(|(label, _)| label.as_str()).collect();
+        let cut = format!("{}{ELLIPSIS}", "x".repeat(SECTION_LABEL_CHARS));
+        assert_eq!(labels, ["Part", "second", "Next", PREFORMAT_TOGGLE, &cut]);
+        let starts: Vec<_> = found.iter().map(|&(_, idx)| idx).collect();
+        assert_eq!(starts, [1, 4, 6, 8, 13]);
+        assert_eq!(sections(&[Line::Text(""), Line::Text("  ")]), []);
+    }
+
+    #[test]
+    fn empty_and_blank_pages_are_blank() {
+        assert!(is_blank(""));
+        assert!(is_blank("\n  \n\t\n"));
+        assert!(!is_blank("x"));
+        assert!(!is_blank("\n=> gemini://example.org/\n"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Rasterized glyphs, kept around so that repeated characters are not decoded over and over.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use fleck::{Font, Glyph};
+
+/// A glyph decoded into whether each of its pixels is filled, row by row.
+#[derive(Debug)]
+pub(crate) struct CachedGlyph {
+    pub width: usize,
+    pub mask: Vec<bool>,
+}
+
+impl CachedGlyph {
+    fn rasterize(glyph: Glyph) -> Self {
+        let width = glyph.width as usize;
+        let mask = glyph.flatten().collect();
+        Self { width, mask }
+    }
+
+    /// Returns the rows of this [`CachedGlyph`].
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        // A glyph without any width has no pixels at all, so the chunk size does not matter.
+        self.mask.chunks(self.width.max(1))
+    }
+}
+
+/// A cache of the [`CachedGlyph`]s of a single [`Font`], shared between the clones of a
+/// [`Style`](crate::elements::Style).
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GlyphCache(Rc<RefCell<Glyphs>>);
+
+#[derive(Debug, Default)]
+struct Glyphs {
+    font: Option<Rc<Font>>,
+    glyphs: HashMap<char, Option<Rc<CachedGlyph>>>,
+}
+
+impl GlyphCache {
+    /// Returns the glyph for `ch` in the `font`, or `None` if the font does not describe it.
+    ///
+    /// The cache holds the glyphs of one font at a time, so asking for a glyph from another font
+    /// than the last one empties it first.
+    pub fn glyph(&self, font: &Rc<Font>, ch: char) -> Option<Rc<CachedGlyph>> {
+        let mut cache = self.0.borrow_mut();
+        if !cache
+            .font
+            .as_ref()
+            .is_some_and(|cached| Rc::ptr_eq(cached, font))
+        {
+            cache.font = Some(Rc::clone(font));
+            cache.glyphs.clear();
+        }
+        cache
+            .glyphs
+            .entry(ch)
+            .or_insert_with(|| {
+                font.glyph(ch)
+                    .map(|glyph| Rc::new(CachedGlyph::rasterize(glyph)))
+            })
+            .clone()
+    }
+
+    /// Returns the width of `text` in the `font`, just like [`Font::determine_width`].
+    pub fn width(&self, font: &Rc<Font>, text: &str) -> usize {
+        text.chars()
+            .flat_map(|ch| self.glyph(font, ch))
+            .map(|glyph| glyph.width)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a font in which the glyphs differ in their width and shape.
+    fn font() -> Rc<Font> {
+        let bytes: [u8; fleck::FILE_SIZE] = std::array::from_fn(|i| (i % 13) as u8);
+        Rc::new(Font::new(&bytes))
+    }
+
+    #[test]
+    fn repeated_characters_come_from_the_cache() {
+        let font = font();
+        let cache = GlyphCache::default();
+        let first = cache.glyph(&font, 'g').unwrap();
+        let again = cache.clone().glyph(&font, 'g').unwrap();
+        assert!(Rc::ptr_eq(&first, &again));
+        let glyph = font.glyph('g').unwrap();
+        assert_eq!(first.width, glyph.width as usize);
+        assert_eq!(first.mask, glyph.flatten().collect::<Vec<_>>());
+        assert!(first.rows().all(|row| row.len() == first.width));
+    }
+
+    #[test]
+    fn width_is_that_of_the_font() {
+        let font = font();
+        let cache = GlyphCache::default();
+        for text in ["", "Hello, world!", "aaaa", "\u{10ffff}"] {
+            assert_eq!(cache.width(&font, text), font.determine_width(text));
+        }
+    }
+
+    #[test]
+    fn another_font_empties_the_cache() {
+        let (one, other) = (font(), font());
+        let cache = GlyphCache::default();
+        let first = cache.glyph(&one, 'x').unwrap();
+        assert!(!Rc::ptr_eq(&first, &cache.glyph(&other, 'x').unwrap()));
+        assert!(!Rc::ptr_eq(&first, &cache.glyph(&one, 'x').unwrap()));
+    }
+}
+
+#[cfg(test)]
+mod benches {
+    extern crate test;
+
+    use test::Bencher;
+
+    use super::*;
+
+    /// Returns the text of a long page, with the characters repeated as in prose.
+    fn page() -> String {
+        let line = "The quick brown fox jumps over the lazy dog, again and again.\n";
+        line.repeat(500)
+    }
+
+    fn font() -> Rc<Font> {
+        let bytes: [u8; fleck::FILE_SIZE] = std::array::from_fn(|i| (i % 13) as u8);
+        Rc::new(Font::new(&bytes))
+    }
+
+    /// Rasterizes every glyph of the page anew, as drawing did before there was a cache.
+    #[bench]
+    fn rasterize_long_page_uncached(b: &mut Bencher) {
+        let (font, page) = (font(), page());
+        b.iter(|| {
+            for line in page.lines() {
+                test::black_box(font.determine_width(line));
+                for glyph in line.chars().flat_map(|ch| font.glyph(ch)) {
+                    test::black_box(CachedGlyph::rasterize(glyph));
+                }
+            }
+        });
+    }
+
+    /// Takes every glyph of the page from a cache that earlier frames filled.
+    #[bench]
+    fn rasterize_long_page_cached(b: &mut Bencher) {
+        let (font, page) = (font(), page());
+        let cache = GlyphCache::default();
+        b.iter(|| {
+            for line in page.lines() {
+                test::black_box(cache.width(&font, line));
+                for glyph in line.chars().flat_map(|ch| cache.glyph(&font, ch)) {
+                    test::black_box(glyph);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,75 @@
+//! A grip in the bottom-right corner for resizing a window that has no decorations.
+
+use gemininini::{Pixel, PIXEL_SIZE};
+
+/// The length in pixels of the sides of the grip triangle.
+const GRIP_SIZE: u32 = 12;
+
+/// Returns whether the point (`x`, `y`) lies in the grip of a `width` by `height` window.
+///
+/// The grip is a right triangle in the bottom-right corner, with the right angle in the corner.
+pub fn in_resize_grip(x: u32, y: u32, width: u32, height: u32) -> bool {
+    if x >= width || y >= height {
+        return false;
+    }
+    let dx = width - 1 - x;
+    let dy = height - 1 - y;
+    dx + dy < GRIP_SIZE
+}
+
+/// Draw the grip in the `color` onto a `width` by `height` frame of raw pixel bytes.
+pub fn draw_resize_grip(frame: &mut [u8], width: u32, height: u32, color: Pixel) {
+    let top = height.saturating_sub(GRIP_SIZE);
+    let left = width.saturating_sub(GRIP_SIZE);
+    for y in top..height {
+        for x in left..width {
+            if in_resize_grip(x, y, width, height) {
+                let idx = (y * width + x) as usize * PIXEL_SIZE;
+                if let Some(px) = frame.get_mut(idx..idx + PIXEL_SIZE) {
+                    px.copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grip_is_a_triangle_in_the_bottom_right_corner() {
+        let (width, height) = (100, 50);
+        assert!(in_resize_grip(99, 49, width, height));
+        assert!(in_resize_grip(99, 49 - (GRIP_SIZE - 1), width, height));
+        assert!(in_resize_grip(99 - (GRIP_SIZE - 1), 49, width, height));
+        // Just past the hypotenuse.
+        assert!(!in_resize_grip(
+            99 - GRIP_SIZE / 2,
+            49 - GRIP_SIZE / 2,
+            width,
+            height
+        ));
+        assert!(!in_resize_grip(0, 0, width, height));
+        assert!(!in_resize_grip(100, 49, width, height));
+    }
+
+    #[test]
+    fn grip_is_drawn_where_it_is_hit() {
+        let (width, height) = (20, 16);
+        let color = [0x12, 0x34, 0x56, 0xff];
+        let mut frame = vec![0; (width * height) as usize * PIXEL_SIZE];
+        draw_resize_grip(&mut frame, width, height, color);
+        for (idx, pixel) in frame.chunks_exact(PIXEL_SIZE).enumerate() {
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+            assert_eq!(pixel == color, in_resize_grip(x, y, width, height));
+        }
+    }
+
+    #[test]
+    fn grip_fits_in_a_tiny_window() {
+        let mut frame = vec![0; 3 * 2 * PIXEL_SIZE];
+        draw_resize_grip(&mut frame, 3, 2, [0xff; PIXEL_SIZE]);
+        assert!(frame.iter().all(|&byte| byte == 0xff));
+    }
+}
@@ -0,0 +1,46 @@
+//! The gutter beside the page that holds the line numbers.
+
+use gemininini::Font;
+
+/// The empty space in pixels between the line numbers and the lines.
+pub const GUTTER_GAP: u32 = 8;
+
+/// Returns the number of decimal digits in `n`.
+fn digits(n: usize) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Returns the width of a gutter in which the numbers up to `last` fit, not counting the gap.
+///
+/// Each digit is given the room of the widest digit in the `font`, so that the width does not
+/// depend on which digits happen to be in `last`.
+pub fn gutter_width(last: usize, font: &Font) -> u32 {
+    let advance = ('0'..='9')
+        .filter_map(|ch| font.glyph(ch))
+        .map(|glyph| glyph.width as u32)
+        .max()
+        .unwrap_or_default();
+    digits(last) * advance
+}
+
+#[cfg(test)]
+mod tests {
+    use gemininini::font::fallback;
+
+    use super::*;
+
+    #[test]
+    fn gutter_is_as_wide_as_the_digits_of_the_last_number() {
+        let font = fallback();
+        let advance = ('0'..='9')
+            .map(|ch| font.glyph(ch).unwrap().width as u32)
+            .max()
+            .unwrap();
+        assert!(advance > 0);
+        assert_eq!(gutter_width(0, &font), advance);
+        assert_eq!(gutter_width(9, &font), advance);
+        assert_eq!(gutter_width(10, &font), 2 * advance);
+        assert_eq!(gutter_width(999, &font), 3 * advance);
+        assert_eq!(gutter_width(1000, &font), 4 * advance);
+    }
+}
@@ -0,0 +1,427 @@
+//! Client certificates that identify the user to a capsule, one per host.
+//!
+//! Capsules that ask for a client certificate, with a `6x` response, recognize a user by it on
+//! later requests. Such an identity is simply a self-signed certificate that is kept around and
+//! presented to the same host every time.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+
+/// The extension of the file in which the certificate of an identity is kept.
+const CERT_EXTENSION: &str = "crt";
+/// The extension of the file in which the private key of an identity is kept.
+const KEY_EXTENSION: &str = "key";
+/// The label of a certificate in a PEM file.
+const PEM_CERT_LABEL: &str = "CERTIFICATE";
+/// The label of a PKCS #8 private key in a PEM file.
+const PEM_KEY_LABEL: &str = "PRIVATE KEY";
+/// How many base64 characters go on a line of a PEM file.
+const PEM_LINE_LENGTH: usize = 64;
+
+/// A client certificate along with its private key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// The DER encoded X.509 certificate.
+    pub cert: Vec<u8>,
+    /// The DER encoded PKCS #8 private key that goes with the certificate.
+    pub key: Vec<u8>,
+}
+
+/// Something that makes new identities.
+pub trait CertGenerator {
+    /// Returns a new [`Identity`] for the `host`.
+    fn generate(&self, host: &str) -> Result<Identity>;
+}
+
+/// Makes self-signed Ed25519 certificates that never expire, with the host as their common name.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelfSigned;
+
+impl CertGenerator for SelfSigned {
+    fn generate(&self, host: &str) -> Result<Identity> {
+        let rng = SystemRandom::new();
+        let key =
+            Ed25519KeyPair::generate_pkcs8(&rng).map_err(|_| anyhow!("could not make a key"))?;
+        let pair = Ed25519KeyPair::from_pkcs8(key.as_ref())
+            .map_err(|err| anyhow!("could not use the key: {}", err))?;
+        let mut serial = [0; 16];
+        rng.fill(&mut serial)
+            .map_err(|_| anyhow!("could not make a serial number"))?;
+        // The serial number must be positive, and its first byte must not be zero in DER.
+        serial[0] = serial[0] & 0x7f | 0x40;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let tbs = der::tbs_certificate(&serial, host, now, pair.public_key().as_ref());
+        let signature = pair.sign(&tbs);
+        Ok(Identity {
+            cert: der::certificate(&tbs, signature.as_ref()),
+            key: key.as_ref().to_vec(),
+        })
+    }
+}
+
+/// The identities that are kept in a directory, as a certificate and key file for every host.
+#[derive(Debug, Clone)]
+pub struct Identities<G = SelfSigned> {
+    dir: PathBuf,
+    generator: G,
+}
+
+impl<G> Identities<G> {
+    /// Creates new [`Identities`] that are kept in `dir`, and made by the `generator`.
+    pub fn new(dir: PathBuf, generator: G) -> Self {
+        Self { dir, generator }
+    }
+
+    /// Returns the path of the file with the `extension` for the identity of `host`.
+    fn path(&self, host: &str, extension: &str) -> Result<PathBuf> {
+        // The host becomes a file name, so it must not lead anywhere else.
+        if host.is_empty() || host.starts_with('.') || host.contains(['/', '\\']) {
+            bail!("cannot keep an identity for host {:?}", host)
+        }
+        Ok(self.dir.join(format!("{host}.{extension}")))
+    }
+
+    /// Returns the identity for `host`, if one was made for it.
+    pub fn for_host(&self, host: &str) -> Result<Option<Identity>> {
+        let cert = match std::fs::read_to_string(self.path(host, CERT_EXTENSION)?) {
+            Ok(cert) => cert,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let key = std::fs::read_to_string(self.path(host, KEY_EXTENSION)?)?;
+        Ok(Some(Identity {
+            cert: pem_decode(&cert, PEM_CERT_LABEL)?,
+            key: pem_decode(&key, PEM_KEY_LABEL)?,
+        }))
+    }
+}
+
+impl<G: CertGenerator> Identities<G> {
+    /// Make a new identity for `host` and keep it, replacing the one it had, if any.
+    pub fn create(&self, host: &str) -> Result<Identity> {
+        let identity = self.generator.generate(host)?;
+        std::fs::create_dir_all(&self.dir)?;
+        write_private(
+            &self.path(host, KEY_EXTENSION)?,
+            &pem_encode(&identity.key, PEM_KEY_LABEL),
+        )?;
+        std::fs::write(
+            self.path(host, CERT_EXTENSION)?,
+            pem_encode(&identity.cert, PEM_CERT_LABEL),
+        )?;
+        Ok(identity)
+    }
+}
+
+/// Write the `contents` to the file at `path`, such that only its owner can read it.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Returns the `der` bytes as a PEM block with the `label`.
+fn pem_encode(der: &[u8], label: &str) -> String {
+    let encoded = base64::encode(der);
+    let mut pem = format!("-----BEGIN {label}-----\n");
+    for line in encoded.as_bytes().chunks(PEM_LINE_LENGTH) {
+        pem.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {label}-----\n"));
+    pem
+}
+
+/// Returns the bytes in the PEM block with the `label` in `pem`.
+fn pem_decode(pem: &str, label: &str) -> Result<Vec<u8>> {
+    let begin = format!("-----BEGIN {label}-----");
+    let end = format!("-----END {label}-----");
+    let Some((_, rest)) = pem.split_once(&begin) else {
+        bail!("missing {}", begin)
+    };
+    let Some((encoded, _)) = rest.split_once(&end) else {
+        bail!("missing {}", end)
+    };
+    let encoded: String = encoded.split_whitespace().collect();
+    Ok(base64::decode(encoded)?)
+}
+
+/// Just enough of DER to write a self-signed Ed25519 certificate.
+mod der {
+    use std::net::IpAddr;
+
+    const SEQUENCE: u8 = 0x30;
+    const SET: u8 = 0x31;
+    const INTEGER: u8 = 0x02;
+    const BIT_STRING: u8 = 0x03;
+    const OCTET_STRING: u8 = 0x04;
+    const OBJECT_IDENTIFIER: u8 = 0x06;
+    const UTF8_STRING: u8 = 0x0c;
+    const UTC_TIME: u8 = 0x17;
+    const GENERALIZED_TIME: u8 = 0x18;
+    /// The explicit tag of the version of a certificate.
+    const VERSION_TAG: u8 = 0xa0;
+    /// The explicit tag of the extensions of a certificate.
+    const EXTENSIONS_TAG: u8 = 0xa3;
+    /// The implicit tag of a DNS name in a subject alternative name.
+    const DNS_NAME_TAG: u8 = 0x82;
+    /// The implicit tag of an IP address in a subject alternative name.
+    const IP_ADDRESS_TAG: u8 = 0x87;
+    /// Version 3, which counts from 0.
+    const VERSION_3: u8 = 2;
+    /// The object identifier of Ed25519, 1.3.101.112.
+    const ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+    /// The object identifier of the common name attribute, 2.5.4.3.
+    const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+    /// The object identifier of the subject alternative name extension, 2.5.29.17.
+    const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+    /// The end of the validity of certificates that do not expire, as RFC 5280 has it.
+    const NO_EXPIRY: &str = "99991231235959Z";
+
+    /// Returns the `contents` with the `tag` and their length in front.
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let len = contents.len();
+        let mut out = vec![tag];
+        match len {
+            0..=0x7f => out.push(len as u8),
+            0x80..=0xff => out.extend([0x81, len as u8]),
+            _ => out.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(contents);
+        out
+    }
+
+    /// Returns the `parts` one after the other, in a sequence.
+    fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+        tlv(SEQUENCE, &parts.concat())
+    }
+
+    /// Returns the `bits` as a bit string without unused bits.
+    fn bit_string(bits: &[u8]) -> Vec<u8> {
+        tlv(BIT_STRING, &[&[0], bits].concat())
+    }
+
+    /// Returns the identifier of the Ed25519 algorithm.
+    fn ed25519() -> Vec<u8> {
+        sequence(&[&tlv(OBJECT_IDENTIFIER, ED25519)])
+    }
+
+    /// Returns the time `secs` after the Unix epoch, as a UTC time until 2049 and as a generalized
+    /// time from then on, as RFC 5280 has it.
+    fn time(secs: u64) -> Vec<u8> {
+        let days = (secs / 86_400) as i64;
+        let secs = secs % 86_400;
+        let (year, month, day) = civil_from_days(days);
+        let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+        let time = format!("{month:02}{day:02}{hour:02}{minute:02}{second:02}Z");
+        match year {
+            ..=2049 => tlv(UTC_TIME, format!("{:02}{time}", year % 100).as_bytes()),
+            _ => tlv(GENERALIZED_TIME, format!("{year:04}{time}").as_bytes()),
+        }
+    }
+
+    /// Returns the year, month and day of the date `days` after the Unix epoch.
+    ///
+    /// This is Howard Hinnant's `civil_from_days`.
+    fn civil_from_days(days: i64) -> (i64, u32, u32) {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        (year, month, day)
+    }
+
+    /// Returns the extension that names the `host` as the subject of a certificate.
+    ///
+    /// Some verifiers insist on it, even though it says nothing that the common name does not.
+    fn subject_alt_name(host: &str) -> Vec<u8> {
+        let name = match host.trim_matches(['[', ']']).parse() {
+            Ok(IpAddr::V4(ip)) => tlv(IP_ADDRESS_TAG, &ip.octets()),
+            Ok(IpAddr::V6(ip)) => tlv(IP_ADDRESS_TAG, &ip.octets()),
+            Err(_) => tlv(DNS_NAME_TAG, host.as_bytes()),
+        };
+        sequence(&[
+            &tlv(OBJECT_IDENTIFIER, SUBJECT_ALT_NAME),
+            &tlv(OCTET_STRING, &sequence(&[&name])),
+        ])
+    }
+
+    /// Returns the part of a certificate that is signed, for the `public_key` of `host`, valid
+    /// from `now` in seconds since the Unix epoch.
+    pub fn tbs_certificate(serial: &[u8], host: &str, now: u64, public_key: &[u8]) -> Vec<u8> {
+        let name = sequence(&[&tlv(
+            SET,
+            &sequence(&[
+                &tlv(OBJECT_IDENTIFIER, COMMON_NAME),
+                &tlv(UTF8_STRING, host.as_bytes()),
+            ]),
+        )]);
+        let validity = sequence(&[&time(now), &tlv(GENERALIZED_TIME, NO_EXPIRY.as_bytes())]);
+        let public_key_info = sequence(&[&ed25519(), &bit_string(public_key)]);
+        sequence(&[
+            &tlv(VERSION_TAG, &tlv(INTEGER, &[VERSION_3])),
+            &tlv(INTEGER, serial),
+            &ed25519(),
+            &name,
+            &validity,
+            &name,
+            &public_key_info,
+            &tlv(EXTENSIONS_TAG, &sequence(&[&subject_alt_name(host)])),
+        ])
+    }
+
+    /// Returns the certificate with the signed part `tbs` and its `signature`.
+    pub fn certificate(tbs: &[u8], signature: &[u8]) -> Vec<u8> {
+        sequence(&[tbs, &ed25519(), &bit_string(signature)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use webpki::{DNSNameRef, EndEntityCert, TLSClientTrustAnchors, Time};
+
+    use super::*;
+
+    /// Makes identities that are told apart by how many came before them.
+    #[derive(Default)]
+    struct Counting(Cell<u8>);
+
+    impl CertGenerator for Counting {
+        fn generate(&self, host: &str) -> Result<Identity> {
+            self.0.set(self.0.get() + 1);
+            Ok(Identity {
+                cert: [host.as_bytes(), &[self.0.get()]].concat(),
+                key: vec![self.0.get(); 48],
+            })
+        }
+    }
+
+    /// Returns identities in a directory of their own, made by a [`Counting`] generator.
+    fn identities(name: &str) -> Identities<Counting> {
+        let name = format!("gemininini-identities-{}-{name}", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        Identities::new(dir, Counting::default())
+    }
+
+    fn now() -> Time {
+        Time::try_from(SystemTime::now()).unwrap()
+    }
+
+    #[test]
+    fn certificate_is_valid_for_a_client_of_its_host() {
+        let identity = SelfSigned.generate("example.org").unwrap();
+        let cert = EndEntityCert::from(&identity.cert).unwrap();
+        let anchor = webpki::trust_anchor_util::cert_der_as_trust_anchor(&identity.cert).unwrap();
+        cert.verify_is_valid_tls_client_cert(
+            &[&webpki::ED25519],
+            &TLSClientTrustAnchors(&[anchor]),
+            &[],
+            now(),
+        )
+        .unwrap();
+        let host = DNSNameRef::try_from_ascii_str("example.org").unwrap();
+        cert.verify_is_valid_for_dns_name(host).unwrap();
+        let other = DNSNameRef::try_from_ascii_str("example.com").unwrap();
+        assert!(cert.verify_is_valid_for_dns_name(other).is_err());
+    }
+
+    #[test]
+    fn certificate_holds_the_public_half_of_the_key() {
+        let identity = SelfSigned.generate("example.org").unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(&identity.key).unwrap();
+        let signature = pair.sign(b"hello");
+        let cert = EndEntityCert::from(&identity.cert).unwrap();
+        cert.verify_signature(&webpki::ED25519, b"hello", signature.as_ref())
+            .unwrap();
+    }
+
+    #[test]
+    fn certificate_for_an_address_parses() {
+        for host in ["127.0.0.1", "[::1]"] {
+            let identity = SelfSigned.generate(host).unwrap();
+            EndEntityCert::from(&identity.cert).unwrap();
+        }
+    }
+
+    #[test]
+    fn validity_switches_to_generalized_time_in_2050() {
+        let utc = der::tbs_certificate(&[0x40], "a", 0, &[0; 32]);
+        assert!(contains(&utc, b"\x17\x0d700101000000Z"));
+        // 2050-01-01 at 00:00:00.
+        let generalized = der::tbs_certificate(&[0x40], "a", 2_524_608_000, &[0; 32]);
+        assert!(contains(&generalized, b"\x18\x0f20500101000000Z"));
+    }
+
+    #[test]
+    fn long_names_have_long_form_lengths() {
+        let host = "a".repeat(200);
+        let identity = SelfSigned.generate(&host).unwrap();
+        EndEntityCert::from(&identity.cert).unwrap();
+        assert!(contains(
+            &identity.cert,
+            &[&[0x0c, 0x81, 200][..], host.as_bytes()].concat()
+        ));
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn identity_is_kept_and_found_again() {
+        let identities = identities("kept");
+        assert_eq!(identities.for_host("example.org").unwrap(), None);
+        let identity = identities.create("example.org").unwrap();
+        assert_eq!(identities.for_host("example.org").unwrap(), Some(identity));
+        assert_eq!(identities.for_host("example.com").unwrap(), None);
+        assert_eq!(identities.generator.0.get(), 1);
+        std::fs::remove_dir_all(&identities.dir).unwrap();
+    }
+
+    #[test]
+    fn creating_again_replaces_the_identity() {
+        let identities = identities("replaced");
+        let first = identities.create("example.org").unwrap();
+        let second = identities.create("example.org").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(identities.for_host("example.org").unwrap(), Some(second));
+        std::fs::remove_dir_all(&identities.dir).unwrap();
+    }
+
+    #[test]
+    fn hosts_that_are_not_file_names_are_refused() {
+        let identities = identities("refused");
+        for host in ["", "..", ".hidden", "a/b", "a\\b"] {
+            assert!(identities.create(host).is_err(), "{host:?}");
+        }
+        // Nothing was created, so there may be no directory to remove.
+        let _ = std::fs::remove_dir_all(&identities.dir);
+    }
+
+    #[test]
+    fn pem_round_trips() {
+        let der: Vec<u8> = (0..=255).collect();
+        let pem = pem_encode(&der, PEM_CERT_LABEL);
+        assert!(pem.lines().all(|line| line.len() <= PEM_LINE_LENGTH));
+        assert_eq!(pem_decode(&pem, PEM_CERT_LABEL).unwrap(), der);
+        assert!(pem_decode(&pem, PEM_KEY_LABEL).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+//! Resolving pressed keys to the actions they are bound to.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use winit::event::VirtualKeyCode;
+use winit_input_helper::WinitInputHelper;
+
+const SHIFT_PREFIX: &str = "shift+";
+
+/// The keys that can be named in a binding. They are named the way [`VirtualKeyCode`] names them.
+#[rustfmt::skip]
+const KEYS: &[VirtualKeyCode] = {
+    use VirtualKeyCode::*;
+    &[
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Escape, Insert, Home, Delete, End, PageDown, PageUp, Left, Up, Right, Down,
+        Back, Return, Space, Tab, Minus, Equals, Plus, Slash, Backslash, Comma, Period, Semicolon,
+        Apostrophe, LBracket, RBracket, Grave,
+    ]
+};
+
+/// Something that can be done by pressing a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    NormalMode,
+    InsertMode,
+    LinkMode,
+    TitanMode,
+    TitanUpload,
+    Reload,
+    OpenExternal,
+    Save,
+    Home,
+    Columns,
+    LineNumbers,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PrevTab,
+    Private,
+    Outline,
+    Sections,
+    CopyAddress,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    SourceView,
+}
+
+impl Action {
+    const ALL: [Action; 31] = [
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::ScrollLeft,
+        Action::ScrollRight,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::NormalMode,
+        Action::InsertMode,
+        Action::LinkMode,
+        Action::TitanMode,
+        Action::TitanUpload,
+        Action::Reload,
+        Action::OpenExternal,
+        Action::Save,
+        Action::Home,
+        Action::Columns,
+        Action::LineNumbers,
+        Action::NewTab,
+        Action::CloseTab,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::Private,
+        Action::Outline,
+        Action::Sections,
+        Action::CopyAddress,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ZoomReset,
+        Action::SourceView,
+    ];
+
+    /// Returns the name by which this [`Action`] is referred to in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::NormalMode => "normal_mode",
+            Action::InsertMode => "insert_mode",
+            Action::LinkMode => "link_mode",
+            Action::TitanMode => "titan_mode",
+            Action::TitanUpload => "titan_upload",
+            Action::Reload => "reload",
+            Action::OpenExternal => "open_external",
+            Action::Save => "save",
+            Action::Home => "home",
+            Action::Columns => "columns",
+            Action::LineNumbers => "line_numbers",
+            Action::NewTab => "new_tab",
+            Action::CloseTab => "close_tab",
+            Action::NextTab => "next_tab",
+            Action::PrevTab => "prev_tab",
+            Action::Private => "private",
+            Action::Outline => "outline",
+            Action::Sections => "sections",
+            Action::CopyAddress => "copy_address",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::ZoomReset => "zoom_reset",
+            Action::SourceView => "source_view",
+        }
+    }
+
+    /// Returns the keys this [`Action`] is bound to unless configured otherwise.
+    fn default_bindings(self) -> Vec<Binding> {
+        use VirtualKeyCode::*;
+        let keys: &[_] = match self {
+            Action::ScrollUp => &[Up, K],
+            Action::ScrollDown => &[Down, J],
+            Action::ScrollLeft => &[Left, H],
+            Action::ScrollRight => &[Right, L],
+            Action::PageUp => &[PageUp],
+            Action::PageDown => &[PageDown],
+            Action::Top => &[Home, G],
+            Action::Bottom => return vec![Binding::new(End), Binding::shifted(G)],
+            Action::NormalMode => &[Escape],
+            Action::InsertMode => &[I],
+            Action::LinkMode => &[F],
+            Action::TitanMode => &[U],
+            Action::TitanUpload => &[W],
+            Action::Reload => &[R],
+            Action::OpenExternal => &[O],
+            Action::Save => &[S],
+            Action::Home => return vec![Binding::shifted(H)],
+            Action::Columns => &[C],
+            Action::LineNumbers => &[N],
+            Action::NewTab => &[T],
+            Action::CloseTab => &[X],
+            Action::NextTab => &[Tab],
+            Action::PrevTab => return vec![Binding::shifted(Tab)],
+            Action::Private => &[P],
+            Action::Outline => return vec![Binding::shifted(T)],
+            Action::Sections => return vec![Binding::shifted(S)],
+            Action::CopyAddress => &[Y],
+            // Plus is shift and equals on many keyboards, and a key of its own on others.
+            Action::ZoomIn => {
+                return vec![
+                    Binding::new(Equals),
+                    Binding::shifted(Equals),
+                    Binding::new(Plus),
+                ]
+            }
+            Action::ZoomOut => &[Minus],
+            Action::ZoomReset => &[Key0],
+            Action::SourceView => &[Grave],
+        };
+        keys.iter().copied().map(Binding::new).collect()
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or(format!("unknown action '{s}'"))
+    }
+}
+
+/// A key, optionally combined with shift, such as `J` or `Shift+G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub key: VirtualKeyCode,
+    pub shift: bool,
+}
+
+impl Binding {
+    fn new(key: VirtualKeyCode) -> Self {
+        Self { key, shift: false }
+    }
+
+    fn shifted(key: VirtualKeyCode) -> Self {
+        Self { key, shift: true }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, shift) = match s.get(..SHIFT_PREFIX.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(SHIFT_PREFIX) => {
+                (&s[SHIFT_PREFIX.len()..], true)
+            }
+            _ => (s, false),
+        };
+        let key = KEYS
+            .iter()
+            .find(|key| format!("{key:?}").eq_ignore_ascii_case(name))
+            .ok_or(format!("unknown key '{name}'"))?;
+        Ok(Self { key: *key, shift })
+    }
+}
+
+/// The mapping of [`Action`]s to the keys that trigger them.
+pub struct KeyMap(HashMap<Action, Vec<Binding>>);
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+        )
+    }
+}
+
+impl KeyMap {
+    /// Bind an [`Action`] to the `bindings`, replacing the keys it was bound to before.
+    pub fn bind(&mut self, action: Action, bindings: Vec<Binding>) {
+        self.0.insert(action, bindings);
+    }
+
+    /// Returns whether any of the keys bound to `action` was pressed.
+    ///
+    /// A binding without shift only matches when shift is not held.
+    pub fn pressed(&self, input: &WinitInputHelper, action: Action) -> bool {
+        self.0.get(&action).is_some_and(|bindings| {
+            bindings.iter().any(|binding| {
+                input.key_pressed(binding.key) && binding.shift == input.held_shift()
+            })
+        })
+    }
+
+    /// Returns whether any of the keys bound to `action` was pressed, or is held down long enough
+    /// for the system to repeat it.
+    pub fn repeated(&self, input: &WinitInputHelper, action: Action) -> bool {
+        self.0.get(&action).is_some_and(|bindings| {
+            bindings.iter().any(|binding| {
+                input.key_pressed_os(binding.key) && binding.shift == input.held_shift()
+            })
+        })
+    }
+}
+
+/// A vi-style count typed before a motion, such as the `10` in `10j`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Count(Option<usize>);
+
+impl Count {
+    /// Feed a typed character into the count. Returns whether it was taken as a digit.
+    ///
+    /// A leading `0` is not a count, just like in vi.
+    pub fn push(&mut self, ch: char) -> bool {
+        match (ch.to_digit(10), self.0) {
+            (Some(0), None) | (None, _) => false,
+            (Some(digit), count) => {
+                let count = count.unwrap_or(0);
+                self.0 = Some(count.saturating_mul(10).saturating_add(digit as usize));
+                true
+            }
+        }
+    }
+
+    /// Returns the count, or 1 if none was typed, and starts over.
+    pub fn take(&mut self) -> usize {
+        self.0.take().unwrap_or(1)
+    }
+
+    /// Returns whether a count is being typed, which the next digit would go on.
+    pub fn is_typed(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Forget the count typed so far.
+    pub fn reset(&mut self) {
+        self.0 = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_are_parsed_ignoring_case() {
+        assert_eq!("j".parse(), Ok(Binding::new(VirtualKeyCode::J)));
+        assert_eq!("PageUp".parse(), Ok(Binding::new(VirtualKeyCode::PageUp)));
+        assert_eq!("Shift+g".parse(), Ok(Binding::shifted(VirtualKeyCode::G)));
+        assert_eq!(
+            "SHIFT+tab".parse(),
+            Ok(Binding::shifted(VirtualKeyCode::Tab))
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        assert!("hyper".parse::<Binding>().is_err());
+        assert!("shift+".parse::<Binding>().is_err());
+        assert!("ctrl+j".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn actions_are_parsed_by_name() {
+        for action in Action::ALL {
+            assert_eq!(action.name().parse(), Ok(action));
+        }
+        assert!("scroll_sideways".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn unbound_actions_keep_their_default_bindings() {
+        let mut keymap = KeyMap::default();
+        assert!(Action::ALL
+            .into_iter()
+            .all(|action| !keymap.0[&action].is_empty()));
+        keymap.bind(Action::ScrollDown, vec![Binding::new(VirtualKeyCode::N)]);
+        assert_eq!(
+            keymap.0[&Action::ScrollDown],
+            [Binding::new(VirtualKeyCode::N)]
+        );
+        assert_eq!(
+            keymap.0[&Action::ScrollUp],
+            Action::ScrollUp.default_bindings()
+        );
+    }
+
+    #[test]
+    fn count_takes_digits_after_the_first_nonzero_one() {
+        let mut count = Count::default();
+        assert!(!count.push('0'));
+        assert_eq!(count, Count::default());
+        assert!(count.push('1'));
+        assert!(count.push('0'));
+        assert!(count.push('5'));
+        assert!(!count.push('j'));
+        assert_eq!(count.take(), 105);
+        // Taking the count starts over.
+        assert_eq!(count.take(), 1);
+    }
+
+    #[test]
+    fn count_is_forgotten_on_reset() {
+        let mut count = Count::default();
+        count.push('4');
+        count.push('2');
+        count.reset();
+        assert_eq!(count, Count::default());
+        assert_eq!(count.take(), 1);
+    }
+}
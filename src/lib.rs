@@ -1,22 +1,70 @@
 #![feature(iter_intersperse)]
+#![cfg_attr(test, feature(test))]
+
+use std::time::{Duration, Instant};
 
 use block::{Block, DrawBlock};
 use elements::{Dimensions, Element};
 pub use fleck::Font;
+use gemtext::Line;
+use source::FetchOutcome;
 
 mod block;
 pub mod elements;
+pub mod font;
+pub mod gemtext;
+mod glyph_cache;
+pub mod source;
 
 /// The number of bytes per [`Pixel`].
 pub const PIXEL_SIZE: usize = 4;
 /// A pixel in the form `[r, g, b, a]`.
 pub type Pixel = [u8; PIXEL_SIZE];
 
+/// Returns the [`Pixel`] with its color channels multiplied by its alpha channel.
+///
+/// A fully opaque pixel is returned unchanged, while a pixel with an alpha of `0x80` has its color
+/// channels roughly halved. This is the representation a compositor expects when blending a
+/// translucent window over the desktop.
+pub fn premultiply(pixel: Pixel) -> Pixel {
+    let [r, g, b, a] = pixel;
+    let mul = |c: u8| ((c as u16 * a as u16 + u8::MAX as u16 / 2) / u8::MAX as u16) as u8;
+    [mul(r), mul(g), mul(b), a]
+}
+
 /// An iterator over rows of [`Pixel`]s.
 type Rows<'b> = std::slice::ChunksExact<'b, Pixel>;
 /// An iterator over mutable rows of [`Pixel`]s.
 type RowsMut<'b> = std::slice::ChunksExactMut<'b, Pixel>;
 
+/// Something that an [`Element`] asks the application to do, rather than doing it itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Go to the page at this address, which may be relative to the current one.
+    Navigate(String),
+    /// Load the current page again.
+    Reload,
+    /// Switch to the mode with this name.
+    SetMode(String),
+    /// Close the application.
+    Quit,
+}
+
+/// A callback that is called with the parsed lines of every page that finishes loading.
+pub type PageLoaded = Box<dyn Fn(&[Line])>;
+
+/// Data that shows a page, such that a [`Panel`] can take it from one page to the next.
+pub trait Navigable {
+    /// Returns the text of the page that is shown.
+    fn text(&self) -> &str;
+
+    /// Returns whether the text of the page that is shown is gemtext.
+    fn is_gemtext(&self) -> bool;
+
+    /// Show the `page`, which finished loading, in place of the page that is shown.
+    fn show_page(&mut self, page: FetchOutcome);
+}
+
 /// Representation of the window and associated data of type `D`.
 pub struct Panel<D> {
     pub width: u32,
@@ -26,9 +74,18 @@ pub struct Panel<D> {
     // that we actually use this `foreground` meaningfully anywhere.
     pub foreground: Pixel,
     pub background: Pixel,
+    /// Whether anything changed since the last time the [`Panel<D>`] was drawn.
+    dirty: bool,
 
     data: D,
     pub elements: Element<D>,
+    /// The [`Action`]s that the elements asked for during updates, which have not been taken yet.
+    actions: Vec<Action>,
+    /// Called with the parsed lines of every page that finishes loading, if set.
+    on_page_loaded: Option<PageLoaded>,
+    /// When the [`Panel<D>`] is to be drawn again at the latest, as the elements and the
+    /// application asked for.
+    redraw_deadline: Option<Instant>,
 }
 
 impl<D> Panel<D> {
@@ -41,11 +98,20 @@ impl<D> Panel<D> {
             height,
             foreground,
             background,
+            dirty: true,
             data,
             elements,
+            actions: Vec::new(),
+            on_page_loaded: None,
+            redraw_deadline: None,
         }
     }
 
+    /// Returns a reference to the data of this [`Panel<D>`].
+    pub fn data(&self) -> &D {
+        &self.data
+    }
+
     /// Returns a mutable reference to the data of this [`Panel<D>`].
     pub fn data_mut(&mut self) -> &mut D {
         &mut self.data
@@ -53,29 +119,372 @@ impl<D> Panel<D> {
 
     /// Update all elements in this [`Panel<D>`] with the internal `data`.
     pub fn update(&mut self) {
-        self.elements.update(&self.data);
-        self.elements.bake_size(Some(self.width));
+        self.elements.measure(&self.data, Some(self.width));
+        self.take_requests();
+    }
+
+    /// Take the [`Action`]s and redraw intervals that the elements asked for while they were
+    /// updated.
+    fn take_requests(&mut self) {
+        self.actions.append(&mut self.elements.actions);
+        // A deadline that has passed was met by this update, and the elements ask again if they
+        // need another.
+        let now = Instant::now();
+        self.redraw_deadline = self.redraw_deadline.filter(|&deadline| deadline > now);
+        if let Some(interval) = self.elements.redraw_interval.take() {
+            self.request_redraw_interval(interval);
+        }
+    }
+
+    /// Returns the [`Action`]s that the elements asked for since they were last taken, in the
+    /// order they were asked for.
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.actions)
+    }
+
+    /// Returns the height of the laid out elements, which may be more than fits in the
+    /// [`Panel<D>`].
+    ///
+    /// Like [`Panel::update`], this brings the elements up to date with the data.
+    pub fn content_height(&mut self) -> u32 {
+        let height = self.elements.measure(&self.data, Some(self.width)).height;
+        self.take_requests();
+        height
     }
 
     /// Draw the [`Panel<D>`] onto a pixel buffer.
     ///
     /// The pixel buffer is provided as a mutable slice of bytes. It is assumed that this buffer
-    /// uses the same pixel representation as [`Block`], which is 32-bit RGBA pixels.
+    /// uses the same pixel representation as [`Block`], which is 32-bit RGBA pixels. The pixels are
+    /// written with [premultiplied](premultiply) alpha, so a `background` with an alpha below
+    /// `0xff` lets whatever is behind the window shine through when blended accordingly.
+    ///
+    /// The rows of the buffer are as wide as the `Panel`. When the buffer holds fewer rows than the
+    /// `Panel` is tall, the rows that do not fit are left out.
     ///
     /// See also: [`Pixel`].
     pub fn draw(&self, pixels: &mut [u8]) {
         let mut block = Block::new(self.width, self.height, self.background);
 
         // Draw onto our block.
-        block.paint(&self.elements.block(), 0, 0);
+        let elements_block = self.elements.block();
+        self.elements.set_bounds(elements::Rect {
+            x: 0,
+            y: 0,
+            width: elements_block.width,
+            height: elements_block.height,
+        });
+        block.paint(&elements_block, 0, 0);
 
         // Draw the block onto the pixels.
-        block.draw_onto_pixels(pixels);
+        block.draw_onto_pixels(pixels, self.width);
+    }
+
+    /// Returns the deepest [`Element`] under the point (`x`, `y`) of this [`Panel<D>`], as of the
+    /// last time it was drawn.
+    pub fn hit_test(&self, x: u32, y: u32) -> Option<&Element<D>> {
+        self.elements.hit_test(x, y)
+    }
+
+    /// Mark the [`Element`]s under the `pointer` as hovered, as of the last time the [`Panel<D>`]
+    /// was drawn. Returns whether any of them changed, in which case the `Panel` needs to be drawn
+    /// again.
+    ///
+    /// See [`Element::hover`].
+    pub fn hover(&mut self, pointer: Option<(u32, u32)>) -> bool {
+        let changed = self.elements.hover(pointer);
+        self.dirty |= changed;
+        changed
+    }
+
+    /// Set the callback that is called with the parsed lines of every page that finishes loading,
+    /// or stop calling one with `None`.
+    pub fn set_on_page_loaded(&mut self, callback: Option<PageLoaded>) {
+        self.on_page_loaded = callback;
+    }
+
+    /// Note that a new page finished loading with the `text`, which calls the [`PageLoaded`]
+    /// callback with its parsed lines. The text is only parsed if there is a callback to call, and
+    /// only as gemtext if it is `gemtext`.
+    pub fn page_loaded(&self, text: &str, gemtext: bool) {
+        if let Some(callback) = &self.on_page_loaded {
+            let lines: Vec<_> = gemtext::parse_as(text, gemtext)
+                .map(|(_, line)| line)
+                .collect();
+            callback(&lines);
+        }
+    }
+
+    /// Ask for the [`Panel<D>`] to be drawn again after `interval` at the latest, for content that
+    /// changes by itself, such as a blinking cursor.
+    ///
+    /// Requests are coalesced, such that the earliest of them is when the next redraw is due. The
+    /// request is met by the first update after that, so live content asks again every time.
+    pub fn request_redraw_interval(&mut self, interval: Duration) {
+        let deadline = Instant::now() + interval;
+        self.redraw_deadline = self.redraw_deadline.into_iter().chain([deadline]).min();
+    }
+
+    /// Returns when the [`Panel<D>`] is to be drawn again, if anything asked for that, so that an
+    /// event loop can sleep until then.
+    ///
+    /// See [`Panel::request_redraw_interval`].
+    pub fn next_redraw(&self) -> Option<Instant> {
+        self.redraw_deadline
     }
 
     /// Resize the [`Panel<D>`].
     pub fn resize(&mut self, width: u32, height: u32) {
         self.width = width;
         self.height = height;
+        self.dirty = true;
+    }
+
+    /// Returns whether the [`Panel<D>`] needs to be updated and drawn again.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Note that the data changed in a way that should show, such that the [`Panel<D>`] is drawn
+    /// again.
+    ///
+    /// Changing the data through [`Panel::data_mut`] does not do this by itself.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Note that the [`Panel<D>`] has been drawn as it is now.
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+}
+
+impl<D: Navigable> Panel<D> {
+    /// Show the `page`, which finished loading, and call the [`PageLoaded`] callback with it.
+    ///
+    /// This is where every page comes in, however it was loaded.
+    pub fn set_content(&mut self, page: FetchOutcome) {
+        self.data.show_page(page);
+        self.dirty = true;
+        self.page_loaded(self.data.text(), self.data.is_gemtext());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use elements::builder::ElementBuilder;
+    use elements::Content;
+    use source::{MockSource, PageSource};
+    use url::Url;
+
+    use super::*;
+
+    fn font() -> Rc<Font> {
+        let bytes: [u8; fleck::FILE_SIZE] = std::array::from_fn(|i| (i % 13) as u8);
+        Rc::new(Font::new(&bytes))
+    }
+
+    #[test]
+    fn premultiply_scales_colors_by_alpha() {
+        assert_eq!(
+            premultiply([0x12, 0x34, 0x56, 0xff]),
+            [0x12, 0x34, 0x56, 0xff]
+        );
+        assert_eq!(
+            premultiply([0xff, 0x80, 0x00, 0x80]),
+            [0x80, 0x40, 0x00, 0x80]
+        );
+        assert_eq!(
+            premultiply([0xff, 0xff, 0xff, 0x00]),
+            [0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn half_transparent_background_is_drawn_premultiplied() {
+        let background = [0xff, 0x80, 0x00, 0x80];
+        let font = Rc::new(Font::new(&[0; fleck::FILE_SIZE]));
+        let elements = Element::still(font, Content::Stack(Vec::new())).with_background(background);
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], background, ());
+        panel.resize(3, 2);
+        let mut pixels = vec![0; 3 * 2 * PIXEL_SIZE];
+        panel.draw(&mut pixels);
+        assert!(pixels
+            .chunks_exact(PIXEL_SIZE)
+            .all(|pixel| pixel == [0x80, 0x40, 0x00, 0x80]));
+    }
+
+    #[test]
+    fn panel_is_clean_until_something_changes() {
+        let font = font();
+        let elements = Element::text("Hello", &font).build();
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], [0xff; PIXEL_SIZE], ());
+        assert!(panel.is_dirty());
+        panel.resize(40, 20);
+        panel.update();
+        panel.mark_clean();
+        assert!(!panel.is_dirty());
+        panel.update();
+        assert!(!panel.is_dirty());
+        panel.resize(40, 20);
+        assert!(panel.is_dirty());
+        panel.mark_clean();
+        panel.mark_dirty();
+        assert!(panel.is_dirty());
+    }
+
+    #[test]
+    fn update_without_changes_leaves_the_pixels_alone() {
+        let font = font();
+        let elements = Element::text("Hello", &font).build();
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], [0xff; PIXEL_SIZE], ());
+        panel.resize(40, 20);
+        let mut pixels = vec![0; 40 * 20 * PIXEL_SIZE];
+        panel.update();
+        panel.draw(&mut pixels);
+        panel.mark_clean();
+        let before = pixels.clone();
+        panel.update();
+        panel.draw(&mut pixels);
+        assert_eq!(pixels, before);
+        assert!(!panel.is_dirty());
+    }
+
+    #[test]
+    fn actions_emitted_by_elements_are_taken_from_the_panel() {
+        fn follow(element: &mut Element<Option<String>>, link: &Option<String>) {
+            if let Some(link) = link {
+                element.emit(Action::Navigate(link.clone()));
+            }
+        }
+        fn quit(element: &mut Element<Option<String>>, _: &Option<String>) {
+            element.emit(Action::Quit);
+        }
+        let font = Rc::new(font::fallback());
+        let elements = Element::stack_builder(&font)
+            .add_child(Element::text("Link", &font).with_update(follow).build())
+            .add_child(Element::text("Quit", &font).with_update(quit).build())
+            .build();
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], [0xff; PIXEL_SIZE], None);
+        panel.resize(40, 40);
+        panel.update();
+        assert_eq!(panel.take_actions(), [Action::Quit]);
+        assert_eq!(panel.take_actions(), []);
+        *panel.data_mut() = Some("gemini://example.org/".to_string());
+        panel.update();
+        let navigate = Action::Navigate("gemini://example.org/".to_string());
+        assert_eq!(panel.take_actions(), [navigate, Action::Quit]);
+    }
+
+    #[test]
+    fn page_loaded_is_called_once_with_the_parsed_lines() {
+        let body = "# Title\n=> next.gmi Next\nSome text";
+        let elements = Element::still(font(), Content::Stack(Vec::new()));
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], [0xff; PIXEL_SIZE], ());
+        let calls = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let seen = Rc::clone(&calls);
+        panel.set_on_page_loaded(Some(Box::new(move |lines: &[Line]| {
+            seen.borrow_mut().push(format!("{lines:?}"));
+        })));
+        panel.page_loaded(body, true);
+        let expected: Vec<_> = gemtext::parse(body).map(|(_, line)| line).collect();
+        assert_eq!(*calls.borrow(), [format!("{expected:?}")]);
+
+        // Without a callback, nothing is called.
+        panel.set_on_page_loaded(None);
+        panel.page_loaded(body, true);
+        assert_eq!(calls.borrow().len(), 1);
+    }
+
+    #[test]
+    fn next_redraw_is_the_earliest_request() {
+        let elements = Element::still(font(), Content::Stack(Vec::new()));
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], [0xff; PIXEL_SIZE], ());
+        assert_eq!(panel.next_redraw(), None);
+        let before = Instant::now();
+        panel.request_redraw_interval(Duration::from_secs(60));
+        panel.request_redraw_interval(Duration::from_secs(10));
+        panel.request_redraw_interval(Duration::from_secs(30));
+        let due = panel.next_redraw().unwrap();
+        assert!(due >= before + Duration::from_secs(10));
+        assert!(due < before + Duration::from_secs(30));
+
+        // Elements ask through the update, and the earliest of them wins there too.
+        fn blink(element: &mut Element<()>, _: &()) {
+            element.request_redraw_interval(Duration::from_millis(500));
+        }
+        fn animate(element: &mut Element<()>, _: &()) {
+            element.request_redraw_interval(Duration::from_millis(100));
+        }
+        let font = font();
+        let elements = Element::stack_builder(&font)
+            .add_child(Element::text("cursor", &font).with_update(blink).build())
+            .add_child(Element::text("graph", &font).with_update(animate).build())
+            .build();
+        let mut panel = Panel::new(elements, [0x00; PIXEL_SIZE], [0xff; PIXEL_SIZE], ());
+        panel.resize(40, 40);
+        let before = Instant::now();
+        panel.update();
+        let due = panel.next_redraw().unwrap();
+        assert!(due >= before + Duration::from_millis(100));
+        assert!(due < before + Duration::from_millis(500));
+    }
+
+    /// A page as the panel shows it, for trying out [`Navigable`].
+    #[derive(Default)]
+    struct Page {
+        address: String,
+        text: String,
+        gemtext: bool,
+    }
+
+    impl Navigable for Page {
+        fn text(&self) -> &str {
+            &self.text
+        }
+
+        fn is_gemtext(&self) -> bool {
+            self.gemtext
+        }
+
+        fn show_page(&mut self, page: FetchOutcome) {
+            self.gemtext = page.meta.starts_with("text/gemini");
+            self.address = page.address;
+            self.text = page.body;
+        }
+    }
+
+    #[test]
+    fn set_content_shows_pages_from_a_source() {
+        let source = MockSource::new()
+            .with_page("gemini://example.org/", 20, "text/gemini", "# Home")
+            .with_page("gemini://example.org/docs/", 20, "text/plain", "# Docs");
+        let elements = Element::still(font(), Content::Stack(Vec::new()));
+        let mut panel = Panel::new(
+            elements,
+            [0x00; PIXEL_SIZE],
+            [0xff; PIXEL_SIZE],
+            Page::default(),
+        );
+        let calls = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let seen = Rc::clone(&calls);
+        panel.set_on_page_loaded(Some(Box::new(move |lines: &[Line]| {
+            seen.borrow_mut().push(lines.len());
+        })));
+
+        for (address, gemtext) in [
+            ("gemini://example.org/", true),
+            ("gemini://example.org/docs/", false),
+        ] {
+            panel.mark_clean();
+            let page = source.fetch(&Url::parse(address).unwrap()).unwrap();
+            panel.set_content(page);
+            assert_eq!(panel.data().address, address);
+            assert_eq!(panel.data().gemtext, gemtext);
+            assert!(panel.is_dirty());
+        }
+        assert_eq!(*calls.borrow(), [1, 1]);
     }
 }
@@ -0,0 +1,204 @@
+//! Picking a link on a page by typing part of its label, and how links are shown.
+
+use url::Url;
+
+/// What a link line is shown starting with, before its label. Lines that the label wraps onto hang
+/// under the label, past it.
+pub const LINK_PREFIX: &str = "=> ";
+
+/// The extensions of the files that each [`LinkKind`] is made up of, in lowercase.
+const GEMTEXT_EXTENSIONS: &[&str] = &["gmi", "gemini"];
+const TEXT_EXTENSIONS: &[&str] = &["txt", "md", "csv"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "ogg", "opus", "flac", "wav", "m4a"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "mkv", "avi", "mov"];
+const BINARY_EXTENSIONS: &[&str] = &[
+    "zip", "gz", "tgz", "xz", "bz2", "zst", "tar", "7z", "pdf", "epub", "exe", "bin", "iso", "deb",
+    "rpm", "apk", "jar", "wasm",
+];
+
+/// What a link leads to, as far as can be told from the extension of its URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// Another page, which is what a link without a telling extension is taken to be.
+    Page,
+    /// A gemtext document.
+    Gemtext,
+    /// Plain text.
+    Text,
+    Image,
+    Audio,
+    Video,
+    /// An archive, a document, a program, or any other file that can only be downloaded.
+    Binary,
+}
+
+impl LinkKind {
+    /// Returns the marker that a link of this kind is annotated with, unless it is an ordinary
+    /// page.
+    pub fn marker(self) -> Option<&'static str> {
+        match self {
+            Self::Page => None,
+            Self::Gemtext => Some("[gmi]"),
+            Self::Text => Some("[txt]"),
+            Self::Image => Some("[img]"),
+            Self::Audio => Some("[audio]"),
+            Self::Video => Some("[video]"),
+            Self::Binary => Some("[bin]"),
+        }
+    }
+}
+
+/// Returns what the link to `url` leads to, judging by the extension of the last segment of its
+/// path, ignoring case. The host, query and fragment are no part of the extension.
+pub fn link_kind(url: &str) -> LinkKind {
+    let path = match Url::parse(url) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => url.split(['?', '#']).next().unwrap_or_default().to_string(),
+    };
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let Some((stem, extension)) = name.rsplit_once('.') else {
+        return LinkKind::Page;
+    };
+    if stem.is_empty() {
+        return LinkKind::Page;
+    }
+    let extension = extension.to_lowercase();
+    let kinds = [
+        (GEMTEXT_EXTENSIONS, LinkKind::Gemtext),
+        (TEXT_EXTENSIONS, LinkKind::Text),
+        (IMAGE_EXTENSIONS, LinkKind::Image),
+        (AUDIO_EXTENSIONS, LinkKind::Audio),
+        (VIDEO_EXTENSIONS, LinkKind::Video),
+        (BINARY_EXTENSIONS, LinkKind::Binary),
+    ];
+    kinds
+        .into_iter()
+        .find(|(extensions, _)| extensions.contains(&extension.as_str()))
+        .map_or(LinkKind::Page, |(_, kind)| kind)
+}
+
+/// Returns whether `label` contains the `query`, ignoring case.
+pub fn label_matches(label: &str, query: &str) -> bool {
+    label.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// Returns the indices of the `links` whose label contains the `query`, ignoring case, in order.
+///
+/// The `links` are `(label, url)` pairs, as returned by
+/// [`gemtext::links`](gemininini::gemtext::links). An empty `query` matches every link.
+pub fn filter_links(links: &[(&str, &str)], query: &str) -> Vec<usize> {
+    links
+        .iter()
+        .enumerate()
+        .filter(|(_, (label, _))| label_matches(label, query))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Returns what a link to `url` with the `label` is shown as, which is its label, or its URL if it
+/// has none, along with a hint of what it leads to.
+///
+/// The hint starts with the marker of the [`LinkKind`] of the link, if it has one. If there are to
+/// be `hints` of where links lead, that is followed by the scheme and host of the link in brackets,
+/// such as `[gemini://example.org]`, once it is resolved against the `base` address of the page,
+/// such that relative links get one too. A link without a host, such as a `data:` one, is hinted at
+/// by its scheme alone. A link that is shown by its URL already needs no such hint.
+pub fn link_text<'t>(
+    url: &'t str,
+    label: Option<&'t str>,
+    base: &str,
+    hints: bool,
+) -> (&'t str, Option<String>) {
+    let marker = link_kind(url).marker();
+    let (text, hint) = match label {
+        Some(label) if hints => (label, link_hint(url, base)),
+        Some(label) => (label, None),
+        None => (url, None),
+    };
+    let hint = match (marker, hint) {
+        (Some(marker), Some(hint)) => Some(format!("{marker} {hint}")),
+        (Some(marker), None) => Some(marker.to_string()),
+        (None, hint) => hint,
+    };
+    (text, hint)
+}
+
+/// Returns the scheme and host that `url` leads to in brackets, as resolved against `base`.
+fn link_hint(url: &str, base: &str) -> Option<String> {
+    let target = match Url::parse(url) {
+        Ok(target) => target,
+        Err(_) => Url::parse(base).ok()?.join(url).ok()?,
+    };
+    let hint = match (target.host_str(), target.port()) {
+        (Some(host), Some(port)) => format!("[{}://{}:{}]", target.scheme(), host, port),
+        (Some(host), None) => format!("[{}://{}]", target.scheme(), host),
+        (None, _) => format!("[{}]", target.scheme()),
+    };
+    Some(hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "gemini://example.org/dir/page.gmi";
+
+    #[test]
+    fn links_are_filtered_by_their_labels_ignoring_case() {
+        let links = [
+            ("Gemini news", "news.gmi"),
+            ("News archive", "archive/"),
+            ("About", "about.gmi"),
+            ("Newsletter", "letter.gmi"),
+        ];
+        assert_eq!(filter_links(&links, ""), [0, 1, 2, 3]);
+        assert_eq!(filter_links(&links, "news"), [0, 1, 3]);
+        assert_eq!(filter_links(&links, "NEWS A"), [1]);
+        assert_eq!(filter_links(&links, "s ar"), [1]);
+        assert_eq!(filter_links(&links, "letter.gmi"), Vec::<usize>::new());
+        assert!(label_matches("über uns", "ÜBER"));
+        assert!(!label_matches("About", "abouts"));
+    }
+
+    #[test]
+    fn link_is_shown_by_its_label_with_a_hint_of_where_it_leads() {
+        let text = |url, label, hints| link_text(url, label, PAGE, hints);
+        let (label, hint) = text("gemini://other.org/news", Some("News"), true);
+        assert_eq!(
+            (label, hint.as_deref()),
+            ("News", Some("[gemini://other.org]"))
+        );
+        // A relative link is hinted at as it is resolved against the page.
+        let (_, hint) = text("/about", Some("About"), true);
+        assert_eq!(hint.as_deref(), Some("[gemini://example.org]"));
+        let (_, hint) = text("gemini://other.org:300/", Some("Elsewhere"), true);
+        assert_eq!(hint.as_deref(), Some("[gemini://other.org:300]"));
+        let (_, hint) = text("data:,hello", Some("Hello"), true);
+        assert_eq!(hint.as_deref(), Some("[data]"));
+        // Without a label, the URL is shown, which needs no hint.
+        assert_eq!(text("/about", None, true), ("/about", None));
+        // Hints may be turned off.
+        assert_eq!(text("/about", Some("About"), false), ("About", None));
+    }
+
+    #[test]
+    fn link_kind_is_told_by_the_extension() {
+        assert_eq!(link_kind("gemini://example.org/photo.JPG"), LinkKind::Image);
+        assert_eq!(link_kind("song.ogg?download#start"), LinkKind::Audio);
+        assert_eq!(link_kind("/files/release.tar.gz"), LinkKind::Binary);
+        assert_eq!(link_kind("notes.gmi"), LinkKind::Gemtext);
+        assert_eq!(link_kind("readme.md"), LinkKind::Text);
+        assert_eq!(link_kind("clip.webm"), LinkKind::Video);
+        // Without a known extension, a link leads to another page.
+        assert_eq!(link_kind("gemini://example.org/"), LinkKind::Page);
+        assert_eq!(link_kind("gemini://example.png/about"), LinkKind::Page);
+        assert_eq!(link_kind("/.png"), LinkKind::Page);
+        assert_eq!(link_kind("file.unknown"), LinkKind::Page);
+        assert_eq!(LinkKind::Page.marker(), None);
+        assert_eq!(LinkKind::Image.marker(), Some("[img]"));
+        // The marker comes before the hint.
+        let (_, hint) = link_text("logo.png", Some("Logo"), PAGE, true);
+        assert_eq!(hint.as_deref(), Some("[img] [gemini://example.org]"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Loading pages from files and from standard input, without going over the network.
+
+use std::fs::File;
+use std::io::Read;
+
+use anyhow::{anyhow, bail, Result};
+use url::Url;
+
+/// The address that stands for standard input, rather than for a page somewhere.
+pub const STDIN_ADDRESS: &str = "-";
+/// The scheme of the addresses of files.
+const FILE_SCHEME: &str = "file";
+/// The media type of files and of standard input, unless they are plain text.
+const GEMTEXT_MIME: &str = "text/gemini";
+/// The media type of files that are plain text.
+const PLAIN_TEXT_MIME: &str = "text/plain";
+/// The extensions of files that are plain text rather than gemtext.
+const PLAIN_TEXT_EXTENSIONS: &[&str] = &["txt"];
+
+/// Where an address came from, which decides whether it may be a file or standard input.
+///
+/// Only the user gets to open what is on their own machine. A capsule that could link or redirect
+/// to a file could read whatever it likes, such as the keys of the identities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// The page to start with, as given on the command line or in the config file. It may be `-`
+    /// for standard input, as well as a file.
+    Start,
+    /// An address that the user typed or configured, which may be a file.
+    Typed,
+    /// A link on a page, or a redirect, which may only be somewhere out there.
+    Page,
+}
+
+/// Returns the media type and contents of the page at `address`, if it is a file or standard input
+/// rather than a page that is fetched. No more than `max_size` bytes and one more are read, such
+/// that a larger page can be told apart.
+///
+/// Only the [`Origin::Start`] address may be standard input. A file that a page links or redirects
+/// to is not loaded, but fails.
+pub fn load_local(
+    address: &str,
+    origin: Origin,
+    max_size: usize,
+) -> Option<Result<(String, String)>> {
+    if address == STDIN_ADDRESS && origin == Origin::Start {
+        return Some(read_stdin(max_size));
+    }
+    match Url::parse(address) {
+        Ok(url) if url.scheme() == FILE_SCHEME && origin == Origin::Page => Some(Err(anyhow!(
+            "{} is a local file, which a link or a redirect may not open",
+            url
+        ))),
+        Ok(url) if url.scheme() == FILE_SCHEME => Some(load_file(&url, max_size)),
+        _ => None,
+    }
+}
+
+/// Returns the media type and contents of the file at the `file://` `url`, of which no more than
+/// `max_size` bytes and one more are read.
+///
+/// A file is taken to be gemtext unless its extension says it is plain text. A directory is not
+/// a page, so it cannot be loaded.
+pub fn load_file(url: &Url, max_size: usize) -> Result<(String, String)> {
+    let path = url
+        .to_file_path()
+        .map_err(|()| anyhow!("{} is not the address of a local file", url))?;
+    if path.is_dir() {
+        bail!("{} is a directory, not a page", path.display())
+    }
+    let mut contents = Vec::new();
+    File::open(&path)
+        .and_then(|file| file.take(max_size as u64 + 1).read_to_end(&mut contents))
+        .map_err(|err| anyhow!("could not read {}: {}", path.display(), err))?;
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let mime = match extension {
+        Some(extension) if PLAIN_TEXT_EXTENSIONS.contains(&extension) => PLAIN_TEXT_MIME,
+        _ => GEMTEXT_MIME,
+    };
+    Ok((
+        mime.to_string(),
+        String::from_utf8_lossy(&contents).into_owned(),
+    ))
+}
+
+/// Returns the media type and contents of standard input, which is taken to be gemtext. It is read
+/// until it ends, or until there is more of it than `max_size` bytes.
+fn read_stdin(max_size: usize) -> Result<(String, String)> {
+    let mut contents = Vec::new();
+    std::io::stdin()
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut contents)
+        .map_err(|err| anyhow!("could not read standard input: {}", err))?;
+    Ok((
+        GEMTEXT_MIME.to_string(),
+        String::from_utf8_lossy(&contents).into_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn files_are_not_loaded_from_pages() {
+        let result = load_local("file:///etc/hosts", Origin::Page, 1024);
+        assert!(matches!(result, Some(Err(_))));
+    }
+
+    #[test]
+    fn standard_input_is_only_read_at_start() {
+        assert!(load_local(STDIN_ADDRESS, Origin::Typed, 1024).is_none());
+        assert!(load_local(STDIN_ADDRESS, Origin::Page, 1024).is_none());
+    }
+
+    #[test]
+    fn large_files_are_read_just_past_the_limit() {
+        let name = format!("gemininini-large-file-{}.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "x".repeat(4096)).unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+        let (mime, contents) = load_file(&url, 100).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mime, PLAIN_TEXT_MIME);
+        assert_eq!(contents.len(), 101);
+    }
+}
@@ -0,0 +1,97 @@
+//! Writing log messages to stderr, as verbosely as asked for.
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The environment variable that sets the level of the log, such as `debug` or `warn`.
+const LEVEL_VAR: &str = "RUST_LOG";
+/// The level that is logged at unless asked otherwise.
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// The levels from quietest to most verbose.
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+static LOGGER: Logger = Logger;
+
+/// Writes every message that passes the [maximum level](log::max_level) to stderr.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = format!("{}:", record.level());
+            eprintln!("{level:<6} {}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Start logging, at the level that the [`LEVEL_VAR`] environment variable asks for.
+///
+/// Until [`set_verbosity`] is called, this is the level that is logged at.
+pub fn init() {
+    log::set_logger(&LOGGER).expect("the logger is only set once");
+    log::set_max_level(level_for(0, env_level().as_deref()));
+}
+
+/// Log at the level for `verbosity`, as given on the command line.
+pub fn set_verbosity(verbosity: i8) {
+    log::set_max_level(level_for(verbosity, env_level().as_deref()));
+}
+
+/// Returns the value of the [`LEVEL_VAR`] environment variable, if it is set.
+fn env_level() -> Option<String> {
+    std::env::var(LEVEL_VAR).ok()
+}
+
+/// Returns the level to log at for the `verbosity` from the command line, and the level from the
+/// environment, if any.
+///
+/// Each step of `verbosity` makes the log one level more verbose, or less verbose if negative,
+/// starting from the [`DEFAULT_LEVEL`]. Without any, the level from the environment is used if it
+/// names one.
+pub fn level_for(verbosity: i8, env: Option<&str>) -> LevelFilter {
+    if verbosity == 0 {
+        if let Some(level) = env.and_then(|env| env.trim().parse().ok()) {
+            return level;
+        }
+    }
+    let default = DEFAULT_LEVEL as usize;
+    let idx = (default as isize + verbosity as isize).clamp(0, LEVELS.len() as isize - 1);
+    LEVELS[idx as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_step_of_verbosity_is_a_level() {
+        assert_eq!(level_for(0, None), LevelFilter::Info);
+        assert_eq!(level_for(1, None), LevelFilter::Debug);
+        assert_eq!(level_for(2, None), LevelFilter::Trace);
+        assert_eq!(level_for(9, None), LevelFilter::Trace);
+        assert_eq!(level_for(-1, None), LevelFilter::Warn);
+        assert_eq!(level_for(-3, None), LevelFilter::Off);
+        assert_eq!(level_for(i8::MIN, None), LevelFilter::Off);
+    }
+
+    #[test]
+    fn environment_is_heeded_without_flags() {
+        assert_eq!(level_for(0, Some("debug")), LevelFilter::Debug);
+        assert_eq!(level_for(0, Some(" WARN\n")), LevelFilter::Warn);
+        assert_eq!(level_for(0, Some("loud")), LevelFilter::Info);
+        assert_eq!(level_for(-1, Some("trace")), LevelFilter::Warn);
+    }
+}
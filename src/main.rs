@@ -1,26 +1,125 @@
 #![feature(array_chunks)]
 
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
 
+mod browser;
+mod clipboard;
+mod columns;
+mod command;
+mod complete;
+mod config;
+mod debounce;
+mod dump;
+mod elide;
+mod failure;
+mod favicon;
+mod grip;
+mod gutter;
+mod identity;
+mod keymap;
+mod links;
+mod local;
+mod logging;
+mod reading;
 mod request;
+mod retry;
+mod save;
+mod scroll;
+mod session;
+mod tables;
+mod tabs;
+mod tint;
+mod zoom;
 
-use request::fetch_page;
+use browser::{open_in_browser, opens_externally};
+use command::{parse_command, Command, Setting};
+use complete::complete;
+use columns::{cap_width, centering_indent, column_count, column_width, split_columns, COLUMN_GAP};
+use config::{configure, user_dir, Config};
+use debounce::Debounce;
+use elide::{elide_end, elide_middle, elide_start, host_end};
+use failure::{explain_status, is_failure};
+use favicon::{capsule_host, favicon_url, parse_favicon};
+use grip::{draw_resize_grip, in_resize_grip};
+use gutter::{gutter_width, GUTTER_GAP};
+use identity::{Identities, SelfSigned};
+use keymap::{Action, Count};
+use links::{filter_links, label_matches, link_text, LINK_PREFIX};
+use local::Origin;
+use reading::reading_stats;
+use request::{
+    build_input_url, fetch_page, is_gemtext, normalize_url, parse_meta, resolve_address,
+    resolve_link, spawn_fetch, spawn_titan_upload, FetchEvent, GeminiSource, Lang, SizeLimit,
+};
+use retry::RetryPolicy;
+use save::{add_bookmark, load_bookmarks, save_page};
+use scroll::ScrollSpeed;
+use session::Session;
+use tables::{column_widths, parse_table, TableLine};
+use tabs::Tabs;
+use tint::{classify, Tint};
+use zoom::{clamp_zoom, zoom_by};
 use pixels::wgpu::BlendState;
 use pixels::{PixelsBuilder, SurfaceTexture};
 use gemininini::elements::builder::ElementBuilder;
-use gemininini::elements::{Alignment, Content, Element, SizingStrategy, WrappedText};
-use gemininini::Font;
-use gemininini::Panel;
+use gemininini::elements::{
+    Alignment, Content, Direction, Element, Padding, Rect, Style, VAlignment, WrappedText,
+};
+use gemininini::font;
+use gemininini::gemtext::{self, outline, Line};
+use gemininini::source::{FetchOutcome, PageSource, STATUS_SENSITIVE_INPUT, STATUS_SUCCESS};
+use gemininini::{premultiply, Action as PanelAction, Navigable, Panel, Pixel};
+use log::{debug, error, info, trace, warn};
+use url::Url;
 use winit::dpi::{LogicalSize, PhysicalSize};
-use winit::event::{Event, VirtualKeyCode};
+use winit::event::Event;
 use winit::event_loop::EventLoop;
-use winit::window::{Window, WindowBuilder};
+use winit::window::{ResizeDirection, Window, WindowBuilder};
 use winit_input_helper::{TextChar, WinitInputHelper};
 
 
 const WINDOW_NAME: &str = env!("CARGO_BIN_NAME");
 
-const SCROLL_STEP: usize = 8;
+/// The tag of the element that the lines of the page are scrolled in.
+const SCROLL_CONTAINER_TAG: &str = "scroll_container";
+
+/// What is shown in place of a page that has nothing to show.
+const EMPTY_PAGE_PLACEHOLDER: &str = "(empty page)";
+/// What the outline shows for a page without any headings.
+const NO_HEADINGS_PLACEHOLDER: &str = "(no headings)";
+/// What the outline shows for a page without any sections.
+const NO_SECTIONS_PLACEHOLDER: &str = "(no sections)";
+/// How far the headings in the outline are indented for every level below the first.
+const OUTLINE_INDENT: &str = "  ";
+
+/// The character that the width of the text is counted in when it is capped, like the `ch` unit of
+/// CSS.
+const MEASURING_CHARACTER: &str = "0";
+
+/// What is drawn between the cells of a row of a lined-up table.
+const TABLE_CELL_SEPARATOR: &str = "|";
+/// What is drawn where a line between the rows of a lined-up table crosses a column edge.
+const TABLE_CROSSING: &str = "+";
+
+/// How often to check on a page that is loading in the background.
+const FETCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The environment variable that holds the token for Titan uploads, if the capsule requires one.
+const TITAN_TOKEN_VAR: &str = "GEMININI_TITAN_TOKEN";
+const TITAN_MIME: &str = "text/gemini";
+/// How long a Titan upload may take until the server responds before it is given up on.
+const TITAN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The directory under the user directory that client certificates are kept in.
+const IDENTITIES_DIR: &str = "identities";
 
 fn setup_window(min_size: PhysicalSize<u32>, event_loop: &EventLoop<()>) -> Window {
     let builder = WindowBuilder::new()
@@ -34,73 +133,503 @@ fn setup_window(min_size: PhysicalSize<u32>, event_loop: &EventLoop<()>) -> Wind
     builder.build(event_loop).expect("could not build window")
 }
 
-fn setup_elements(font: Rc<Font>) -> Element<Data> {
+fn setup_elements(style: Style, margin: Padding) -> Element<Data> {
     fn display_address(element: &mut Element<Data>, data: &Data) {
         // TODO: This whole practice is a mess and is horrible and oh no.
-        let Content::Text(text, _) = &mut element.content else {
-            unreachable!()
+        let style = &element.style;
+        let font = &style.font;
+        // The favicon of a capsule goes in front of its address, but not of one that is typed.
+        // Nor does one that the font has no glyphs for, which would leave only the space after it.
+        let favicon = match data.mode {
+            Mode::Insert => None,
+            _ => data
+                .favicon()
+                .filter(|favicon| favicon.chars().all(|ch| font::covers(font, ch)))
+                .map(|favicon| format!("{favicon} ")),
         };
-        text.clear();
-        text.push_str(data.address.as_str())
+        let favicon_width = favicon.as_deref().map_or(0, |f| font.determine_width(f));
+        let available = (data.inner_width() as usize).saturating_sub(favicon_width);
+        let (text, suggestion) = match data.mode {
+            // The end of an address that is being typed is kept in view, since that is where the
+            // caret is. The meta is about the page, not about an address that is being typed.
+            Mode::Insert => {
+                let text = elide_start(&data.address, available, font);
+                let room = available.saturating_sub(font.determine_width(&text));
+                let suggestion = data.address_completion().map(|completion| {
+                    elide_end(&suggested(&data.address, &completion), room, font)
+                });
+                (text, suggestion)
+            }
+            _ if data.meta.is_empty() => {
+                let text = elide_middle(&data.address, host_end(&data.address), available, font);
+                (text, None)
+            }
+            _ => {
+                let shown = format!("{} [{}]", data.address, data.meta);
+                let text = elide_middle(&shown, host_end(&data.address), available, font);
+                (text, None)
+            }
+        };
+        let text = match favicon {
+            Some(favicon) => favicon + &text,
+            None => text,
+        };
+        let mut children = vec![Element::text(&text, font)
+            .with_alignment(Alignment::Left)
+            .build()
+            .with_style(style.clone())];
+        // A suggested completion is dimmed, to tell it apart from what is actually typed.
+        if let Some(suggestion) = suggestion.filter(|suggestion| !suggestion.is_empty()) {
+            let mut dimmed = style.clone();
+            dimmed.foreground = mix(style.foreground, style.background);
+            let suggestion = Element::text(&suggestion, font)
+                .with_alignment(Alignment::Left)
+                .build()
+                .with_style(dimmed);
+            children.push(suggestion);
+        }
+        element.content = Content::Row(children);
+    }
+
+    fn display_tabs(element: &mut Element<Data>, data: &Data) {
+        // A single tab needs no bar to tell it apart.
+        let count = data.tabs.len() as u32;
+        if count == 1 {
+            element.content = Content::Row(Vec::new());
+            return;
+        }
+        let style = &element.style;
+        let width = data.inner_width() / count;
+        let tabs = data
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(idx, tab)| {
+                // The current tab is out of date, since its page is the one being shown.
+                let current = idx == data.tabs.current();
+                let title = match current {
+                    true => tab_title(&data.address, &data.text, is_gemtext(&data.meta)),
+                    false => tab_title(&tab.address, &tab.text, is_gemtext(&tab.meta)),
+                };
+                let title = elide_end(title, width as usize, &style.font);
+                let tab = Element::text(&title, &style.font)
+                    .with_alignment(Alignment::Left)
+                    .build()
+                    .with_style(style.clone())
+                    .with_fixedwidth(width);
+                match current {
+                    true => tab.with_background(mix(style.foreground, style.background)),
+                    false => tab,
+                }
+            })
+            .collect();
+        element.content = Content::Row(tabs);
     }
 
     fn display_text(element: &mut Element<Data>, data: &Data) {
-        // TODO: This whole practice is a mess and is horrible and oh no.
-        let Content::Paragraph(text, _) = &mut element.content else {
-            unreachable!()
+        // While composing an upload, show what is being written instead of the page.
+        let shown = match data.mode {
+            Mode::Titan => &data.upload,
+            _ => &data.text,
+        };
+
+        if data.mode == Mode::Outline {
+            display_outline(element, data);
+            return;
+        }
+        if let Some(status) = data.failure.filter(|_| data.mode != Mode::Titan) {
+            display_failure(element, data, status);
+            return;
+        }
+        if is_empty_page(data) {
+            display_placeholder(element, data, EMPTY_PAGE_PLACEHOLDER);
+            return;
+        }
+
+        // Plain text is shown line by line as it is, without links, headings, or blocks, and so is
+        // the source of a page.
+        let gemtext = data.mode == Mode::Titan || data.shows_gemtext();
+
+        // Every line gets its own paragraph, such that it can be styled on its own.
+        let style = &element.style;
+        // Long lines are hard to read, so in a wide window the text is kept narrower and centered.
+        // A cap too large to count in pixels is as good as none.
+        let char_width = style.font.determine_width(MEASURING_CHARACTER) as u32;
+        let cap = data.max_content_width.saturating_mul(char_width);
+        let columns = column_count(data.inner_width(), data.two_columns);
+        let width = cap_width(column_width(data.inner_width(), columns), cap);
+        let indent = centering_indent(data.inner_width(), width, columns);
+        let gutter = match data.line_numbers {
+            true => gutter_width(shown.lines().count(), &style.font),
+            false => 0,
+        };
+        let text_width = match data.line_numbers {
+            true => width.saturating_sub(gutter + GUTTER_GAP),
+            false => width,
+        };
+        let mut number_style = style.clone();
+        number_style.foreground = mix(style.foreground, style.background);
+        let mut widest_preformatted = 0;
+        // The lines of preformatted blocks that are tables, with the widths of their columns.
+        let mut table_lines = HashMap::new();
+        if data.align_tables && gemtext {
+            for (start, block) in gemtext::preformatted_blocks(shown) {
+                if let Some(table) = parse_table(&block) {
+                    let widths = column_widths(&table, |cell| style.font.determine_width(cell));
+                    let widths = Rc::new(widths);
+                    for (idx, line) in table.into_iter().enumerate() {
+                        table_lines.insert(start + idx, (line, Rc::clone(&widths)));
+                    }
+                }
+            }
+        }
+        // The link that Enter follows in link mode stands out from the others that match.
+        let highlighted = match data.mode {
+            Mode::Link => {
+                let links: Vec<_> = gemtext::links_as(shown, gemtext, data.linkify).collect();
+                filter_links(&links, &data.link_filter).first().copied()
+            }
+            _ => None,
+        };
+        let mut link_idx = 0;
+        // The alt text of the preformatted block that the lines are in, which may name a language.
+        let mut block_alt = "";
+        let mut lines: Vec<_> = gemtext::parse_as(shown, gemtext)
+            .enumerate()
+            .map(|(idx, (line, parsed))| {
+                let mut line_style = style.clone();
+                if let Line::PreformatToggle(alt) = parsed {
+                    block_alt = alt;
+                }
+                // A line of text is as many links as it has bare URLs, and stands out when any of
+                // them is highlighted.
+                let line_links = match parsed {
+                    Line::Link { .. } => 1,
+                    Line::Text(_) if data.linkify && gemtext => parsed.bare_urls().len(),
+                    _ => 0,
+                };
+                let is_highlighted =
+                    highlighted.is_some_and(|idx| (link_idx..link_idx + line_links).contains(&idx));
+                link_idx += line_links;
+                match parsed {
+                    Line::Link { .. } | Line::Text(_) if is_highlighted => {
+                        line_style.foreground = style.background;
+                        line_style.background = data.link_color;
+                        line_style.own_background = true;
+                    }
+                    // While picking a link by its label, the links that do not match are dimmed.
+                    Line::Link { url, label }
+                        if data.mode == Mode::Link
+                            && !label_matches(label.unwrap_or(url), &data.link_filter) =>
+                    {
+                        line_style.foreground = mix(style.foreground, style.background);
+                    }
+                    Line::Link { url, .. } => {
+                        line_style.foreground = data.link_color;
+                        match link_state(&data.address, url, &data.visited) {
+                            LinkState::Current => {
+                                line_style.background = mix(style.foreground, style.background);
+                                line_style.own_background = true;
+                            }
+                            LinkState::Visited => {
+                                line_style.foreground = mix(data.link_color, style.background)
+                            }
+                            LinkState::Unvisited => {}
+                        }
+                    }
+                    Line::Heading { .. } => line_style.foreground = data.heading_color,
+                    Line::Preformatted(text) if data.tint_code => match classify(block_alt, text) {
+                        Some(Tint::Added) => line_style.foreground = data.added_color,
+                        Some(Tint::Removed) => line_style.foreground = data.removed_color,
+                        Some(Tint::Comment) => line_style.foreground = data.comment_color,
+                        None => {}
+                    },
+                    _ => {}
+                }
+                let preformatted = matches!(parsed, Line::Preformatted(_));
+                // Preformatted text is laid out as it is, whatever the language of the page.
+                let alignment = if data.rtl && !preformatted {
+                    line_style.direction = Direction::RightToLeft;
+                    Alignment::Right
+                } else {
+                    Alignment::Left
+                };
+                // Links are shown by their label, with the hint of where they lead next to it.
+                let (line, hint) = match parsed {
+                    Line::Link { url, label } => {
+                        link_text(url, label, &data.address, data.link_hints)
+                    }
+                    _ => (line, None),
+                };
+                let cleaned = gemtext::clean(line, preformatted);
+                // A link goes after the prefix of a link line, and the lines it wraps onto hang
+                // under it.
+                let (cleaned, hanging_indent) = match parsed {
+                    Line::Link { .. } => {
+                        let indent = style.font.determine_width(LINK_PREFIX) as u32;
+                        (format!("{LINK_PREFIX}{cleaned}").into(), indent)
+                    }
+                    _ => (cleaned, 0),
+                };
+                // Preformatted lines are not wrapped, but scrolled sideways instead.
+                let paragraph = if let Some((table_line, widths)) = table_lines.remove(&idx) {
+                    table_row(&table_line, &widths, &line_style)
+                } else if preformatted {
+                    let line_width = style.font.determine_width(&cleaned) as u32;
+                    widest_preformatted = widest_preformatted.max(line_width);
+                    Element::text(&cleaned, &style.font)
+                        .with_alignment(alignment)
+                        .build()
+                        .with_style(line_style)
+                        .with_hscroll(data.hscroll)
+                } else {
+                    Element::paragraph(&cleaned, &style.font)
+                        .with_alignment(alignment)
+                        .with_hanging_indent(hanging_indent)
+                        .build()
+                        .with_style(line_style)
+                };
+                let paragraph = paragraph.with_fixedwidth(text_width);
+                // Links stand out while the pointer is over them, to show they can be clicked.
+                let paragraph = match parsed {
+                    Line::Link { .. } => {
+                        let mut hover_style = paragraph.style.clone();
+                        hover_style.foreground = style.background;
+                        hover_style.background = data.link_color;
+                        hover_style.own_background = true;
+                        paragraph.with_hover_style(hover_style)
+                    }
+                    _ => paragraph,
+                };
+                let paragraph = match hint {
+                    Some(hint) => {
+                        link_with_hint(paragraph, &cleaned, &hint, text_width, &number_style)
+                    }
+                    None => paragraph,
+                };
+                if !data.line_numbers {
+                    return paragraph;
+                }
+                // Only the first of the wrapped lines is numbered, since they make up one line.
+                let number = Element::text(&(idx + 1).to_string(), &style.font)
+                    .with_alignment(Alignment::Right)
+                    .build()
+                    .with_style(number_style.clone())
+                    .with_fixedwidth(gutter)
+                    .with_padding_right(GUTTER_GAP);
+                let row = Content::Row(vec![number, paragraph]);
+                Element::still(Rc::clone(&style.font), row).with_style(style.clone())
+            })
+            .collect();
+        let max = max_hscroll(widest_preformatted, text_width);
+        data.max_hscroll.set(max);
+        if columns == 1 {
+            let lines = lines
+                .into_iter()
+                .map(|line| line.with_padding_left(indent))
+                .collect();
+            element.content = Content::Stack(lines);
+            return;
+        }
+
+        // How tall the lines are is only known once they are wrapped.
+        let heights: Vec<_> = lines
+            .iter_mut()
+            .map(|line| line.measure(data, None).height)
+            .collect();
+        let right = lines.split_off(split_columns(&heights));
+        let style = &element.style;
+        let column = |lines| {
+            Element::still(Rc::clone(&style.font), Content::Stack(lines))
+                .with_style(style.clone())
+                .with_fixedwidth(width)
         };
+        let row = Content::Row(vec![
+            column(lines),
+            column(right).with_padding_left(COLUMN_GAP),
+        ]);
+        let row = Element::still(Rc::clone(&style.font), row)
+            .with_style(style.clone())
+            .with_padding_left(indent);
+        element.content = Content::Stack(vec![row]);
+    }
+
+    /// Show the `text` in the middle of the scroll container `element`, instead of any lines.
+    fn display_placeholder(element: &mut Element<Data>, data: &Data, text: &str) {
+        let height = element.size.maxheight.unwrap_or_default();
+        let placeholder = Element::text(text, &element.style.font)
+            .with_alignment(Alignment::Center)
+            .build()
+            .with_style(element.style.clone())
+            .with_fixedwidth(data.inner_width())
+            .with_fixedheight(height)
+            .with_valign(VAlignment::Center);
+        element.content = Content::Stack(vec![placeholder]);
+        data.max_hscroll.set(0);
+    }
 
-        element.size.maxwidth = Some(data.width);
-        element.size.minwidth = Some(data.width);
+    /// Show why the page could not be loaded in the scroll container `element`, as a banner with
+    /// the failure `status` and the meta of the response, followed by what the status means.
+    fn display_failure(element: &mut Element<Data>, data: &Data, status: u8) {
+        let style = &element.style;
+        let mut banner_style = style.clone();
+        banner_style.foreground = style.background;
+        banner_style.background = data.error_color;
+        banner_style.own_background = true;
+        let banner = format!("{status} {}", gemtext::clean(&data.meta, false));
+        let banner = Element::paragraph(&banner, &style.font)
+            .build()
+            .with_style(banner_style)
+            .with_fixedwidth(data.inner_width());
+        let explanation = Element::paragraph(explain_status(status), &style.font)
+            .build()
+            .with_style(style.clone())
+            .with_fixedwidth(data.inner_width());
+        element.content = Content::Stack(vec![banner, explanation]);
+        data.max_hscroll.set(0);
+    }
 
-        *text = WrappedText::new(data.text.clone(), data.width, &element.style.font)
+    /// Show the headings or sections of the page in the scroll container `element`, one per line
+    /// and headings indented by their level, with the selected one standing out.
+    fn display_outline(element: &mut Element<Data>, data: &Data) {
+        let entries = outline_entries(&data.text, is_gemtext(&data.meta), data.outline_sections);
+        if entries.is_empty() {
+            let placeholder = match data.outline_sections {
+                true => NO_SECTIONS_PLACEHOLDER,
+                false => NO_HEADINGS_PLACEHOLDER,
+            };
+            display_placeholder(element, data, placeholder);
+            return;
+        }
+        let style = &element.style;
+        let entries = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, (level, text, _))| {
+                let color = match level {
+                    0 => style.foreground,
+                    _ => data.heading_color,
+                };
+                let mut entry_style = style.clone();
+                entry_style.foreground = color;
+                if idx == data.outline_selected {
+                    entry_style.foreground = style.background;
+                    entry_style.background = color;
+                    entry_style.own_background = true;
+                }
+                let indent = OUTLINE_INDENT.repeat(level.saturating_sub(1));
+                Element::text(&format!("{indent}{text}"), &style.font)
+                    .build()
+                    .with_style(entry_style)
+                    .with_fixedwidth(data.inner_width())
+            })
+            .collect();
+        element.content = Content::Stack(entries);
+        data.max_hscroll.set(0);
     }
 
     fn update_scroll_container(element: &mut Element<Data>, data: &Data) {
-        // Set scroll position.
-        element.scroll = Some(data.scroll_pos as u32);
         // Update the height of the scroll container.
-        element.size.maxheight = data
-            .height
-            .checked_sub(2 * element.style.font.height() as u32);
-        element.size.minheight = data
-            .height
-            .checked_sub(2 * element.style.font.height() as u32);
+        // The address and mode lines take up one line each, and so does the tab bar if it is shown.
+        let lines = match data.tabs.len() {
+            1 => 2,
+            _ => 3,
+        };
+        let height = data
+            .inner_height()
+            .checked_sub(lines * element.style.line_height());
+        element.size.maxheight = height;
+        element.size.minheight = height;
+        // Set scroll position. The outline is scrolled just far enough to show the selected
+        // heading, and leaves the scroll position of the page as it is.
+        element.scroll = Some(match data.mode {
+            Mode::Outline => {
+                let bottom = (data.outline_selected as u32 + 1) * element.style.line_height();
+                bottom.saturating_sub(height.unwrap_or_default())
+            }
+            _ => data.scroll_pos as u32,
+        });
+        // Scrolling alone leaves the lines as they are, so they need not be built again.
+        let fingerprint = text_fingerprint(data);
+        if data.laid_out.replace(Some(fingerprint)) != Some(fingerprint) {
+            display_text(element, data);
+        }
     }
 
     fn display_mode(element: &mut Element<Data>, data: &Data) {
-        // TODO: This whole practice is a mess and is horrible and oh no.
-        let Content::Text(text, _) = &mut element.content else {
-            unreachable!()
+        let Some(text) = element.as_text_mut() else {
+            return;
         };
         text.clear();
-        text.push_str(data.mode.to_string().as_str())
+        match data.mode {
+            Mode::Command => {
+                text.push(':');
+                text.push_str(&data.command);
+            }
+            Mode::Input => {
+                if let Some(prompt) = &data.prompt {
+                    // Of a sensitive answer, only how long it is shows.
+                    let answer = match prompt.sensitive {
+                        true => "*".repeat(data.answer.chars().count()),
+                        false => data.answer.clone(),
+                    };
+                    text.push_str(&format!("{}: {answer}", prompt.question));
+                }
+            }
+            Mode::Link => {
+                let gemtext = data.shows_gemtext();
+                let links: Vec<_> = gemtext::links_as(&data.text, gemtext, data.linkify).collect();
+                let matching = filter_links(&links, &data.link_filter).len();
+                text.push_str(&format!("link: {} ({matching} matching)", data.link_filter));
+                // Where Enter would go is the very address that following the link goes to.
+                if let Some(target) = data.link_target() {
+                    text.push_str(&format!(" -> {target}"));
+                }
+            }
+            _ => {
+                text.push_str(&data.status.render(&data.mode));
+                if data.private {
+                    text.push_str(" | private");
+                }
+                // With nothing else to say, the status line tells how long the page is.
+                if data.mode == Mode::Normal && data.status == Status::Idle {
+                    let (words, time) = data.reading_stats();
+                    if words > 0 {
+                        let minutes = time.as_secs().div_ceil(60).max(1);
+                        text.push_str(&format!(" | {words} words, {minutes} min read"));
+                    }
+                }
+            }
+        }
     }
 
     fn resize_height(element: &mut Element<Data>, data: &Data) {
-        element.size.maxheight = Some(data.height);
-        element.size.minheight = Some(data.height);
+        element.size.maxheight = Some(data.inner_height());
+        element.size.minheight = Some(data.inner_height());
     }
 
+    let font = Rc::clone(&style.font);
     Element::stack_builder(&font)
         .with_update(resize_height)
         .add_child(
-            Element::text("---", &font)
+            Element::row_builder(&font)
+                .with_update(display_tabs)
+                .build()
+                .with_style(style.clone()),
+        )
+        .add_child(
+            Element::row_builder(&font)
                 .with_update(display_address)
-                .with_alignment(Alignment::Left)
-                .build(),
+                .build()
+                .with_style(style.clone())
+                .with_valign(VAlignment::Center),
         )
         .add_child(
             Element::stack_builder(&font)
                 .with_update(update_scroll_container)
-                .add_child(
-                    Element::empty_paragraph(&font)
-                        .with_update(display_text)
-                        .with_alignment(Alignment::Left)
-                        .build()
-                        .with_strategy(SizingStrategy::Chonker)
-                )
+                .with_tag(SCROLL_CONTAINER_TAG)
                 .build()
+                .with_style(style.clone())
                 .with_scroll(0)
                 .with_minwidth(600)
                 .with_maxheight(400)
@@ -110,208 +639,2399 @@ fn setup_elements(font: Rc<Font>) -> Element<Data> {
             Element::text("---", &font)
                 .with_update(display_mode)
                 .with_alignment(Alignment::Left)
-                .build(),
+                .build()
+                .with_style(style.clone())
+                .with_valign(VAlignment::Center),
         )
         .build()
+        .with_style(style)
+        .with_padding(margin.top, margin.right, margin.bottom, margin.left)
 }
 
-struct Data {
-    text: String,
-    scroll_pos: usize,
-    address: String,
-    mode: Mode,
-    width: u32,
-    height: u32,
+/// Returns a fingerprint of everything that goes into the lines of the scroll container.
+fn text_fingerprint(data: &Data) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match data.mode {
+        Mode::Titan => data.upload.hash(&mut hasher),
+        _ => data.text.hash(&mut hasher),
+    }
+    data.mode.to_string().hash(&mut hasher);
+    data.address.hash(&mut hasher);
+    data.meta.hash(&mut hasher);
+    data.failure.hash(&mut hasher);
+    // Pages are only ever added to the visited ones.
+    data.visited.len().hash(&mut hasher);
+    data.rtl.hash(&mut hasher);
+    data.link_filter.hash(&mut hasher);
+    data.outline_selected.hash(&mut hasher);
+    data.outline_sections.hash(&mut hasher);
+    data.two_columns.hash(&mut hasher);
+    data.line_numbers.hash(&mut hasher);
+    data.source_view.hash(&mut hasher);
+    data.hscroll.hash(&mut hasher);
+    is_empty_page(data).hash(&mut hasher);
+    data.width.hash(&mut hasher);
+    data.height.hash(&mut hasher);
+    hasher.finish()
 }
 
-#[derive(PartialEq, Eq)]
-enum Mode {
-    Normal,
-    Insert,
-    Link,
+/// Returns whether the page has been loaded and turned out to have nothing to show, as opposed to
+/// still loading or having failed to load.
+fn is_empty_page(data: &Data) -> bool {
+    data.mode != Mode::Titan
+        && data.fetch.is_none()
+        && !matches!(data.status, Status::Error(_))
+        && gemtext::is_blank(&data.text)
 }
 
-impl ToString for Mode {
-    fn to_string(&self) -> String {
-        match self {
-            Mode::Normal => "normal".to_string(),
-            Mode::Insert => "insert".to_string(),
-            Mode::Link => "link".to_string(),
-        }
+/// Returns a color halfway between `foreground` and `background`.
+fn mix(foreground: Pixel, background: Pixel) -> Pixel {
+    let mut mixed = foreground;
+    for (channel, bg) in mixed.iter_mut().zip(background) {
+        *channel = ((*channel as u16 + bg as u16) / 2) as u8;
     }
+    mixed
 }
 
-fn main() -> Result<(), pixels::Error> {
-    let mut args = std::env::args().skip(1);
-    let font_path = args
-        .next()
-        .unwrap_or("/etc/tid/fonts/geneva14.uf2".to_string());
-    let font = match Font::load_from_file(&font_path) {
-        Ok(font) => font,
-        Err(err) => {
-            eprintln!("ERROR: Failed to load font from {font_path:?}: {err}");
-            std::process::exit(1);
+/// Where a link leads, as far as the pages that were seen go, which it is drawn according to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkState {
+    /// The link leads to the page that it is on.
+    Current,
+    /// The link leads to a page that was visited before.
+    Visited,
+    Unvisited,
+}
+
+/// Returns the [`LinkState`] of a link to `url` on the page at `address`, given the normalized
+/// URLs of the `visited` pages.
+fn link_state(address: &str, url: &str, visited: &HashSet<String>) -> LinkState {
+    match resolve_link(address, url) {
+        target if target.is_some() && target == resolve_link(address, address) => {
+            LinkState::Current
         }
-    };
-    let font = Rc::new(font);
+        Some(target) if visited.contains(&target) => LinkState::Visited,
+        _ => LinkState::Unvisited,
+    }
+}
 
-    let event_loop = EventLoop::new();
+/// Returns the scrolling container that holds the page text.
+fn scroll_container(elements: &Element<Data>) -> &Element<Data> {
+    elements
+        .find_by_tag(SCROLL_CONTAINER_TAG)
+        .expect("the scroll container is set up with its tag")
+}
 
-    let scale_factor = std::env::var("TID_SCALE_FACTOR")
-        .ok()
-        .and_then(|v| v.parse::<f32>().ok())
-        .map(|v| v.round() as u32)
-        .unwrap_or(1);
+/// Returns what [`Mode::Outline`] lists for a page with the `text`, each as its heading level, its
+/// label, and the index of its line. That is the headings of the page, or all of its `sections`,
+/// of which those that are not headings have level 0.
+fn outline_entries(text: &str, gemtext: bool, sections: bool) -> Vec<(usize, String, usize)> {
+    let lines: Vec<_> = gemtext::parse_as(text, gemtext)
+        .map(|(_, line)| line)
+        .collect();
+    if !sections {
+        return outline(&lines)
+            .into_iter()
+            .map(|(level, text, idx)| (level as usize, text, idx))
+            .collect();
+    }
+    gemtext::sections(&lines)
+        .into_iter()
+        .map(|(label, idx)| match lines[idx] {
+            Line::Heading { level, .. } => (level, label, idx),
+            _ => (0, label, idx),
+        })
+        .collect()
+}
 
-    let elements = setup_elements(font);
-    let data = Data {
-        text: fetch_page("gemini://gemini.cyberbot.space/", "gemini://gemini.cyberbot.space/"),
-        scroll_pos: 0,
-        address: "gemini://gemini.cyberbot.space/".to_string(),
-        mode: Mode::Normal,
-        width: 0,
-        height: 0,
-    };
-    let mut state = Panel::new(
-        elements,
-        [0x00, 0x00, 0x00, 0xff],
-        [0xff, 0xff, 0xff, 0xff],
-        data,
-    );
+/// Returns the title of the page at `address` with `text`, for its tab. That is its first
+/// heading, or its address if it has none, such as when it is not `gemtext`.
+fn tab_title<'t>(address: &'t str, text: &'t str, gemtext: bool) -> &'t str {
+    gemtext::parse_as(text, gemtext)
+        .find_map(|(_, line)| match line {
+            Line::Heading { text, .. } => Some(text),
+            _ => None,
+        })
+        .unwrap_or(address)
+}
 
-    let (width, height) = (state.width, state.height);
-    let size = PhysicalSize::new(width * scale_factor, height * scale_factor);
+/// Returns the index of the content line under the point (`x`, `y`) of the panel, as of the last
+/// time it was drawn.
+fn line_at(elements: &Element<Data>, x: u32, y: u32) -> Option<usize> {
+    /// Returns the point (`x`, `y`) of the parent relative to the `element`.
+    fn within(element: &Element<Data>, x: u32, y: u32) -> Option<(u32, u32)> {
+        let Rect { x: ex, y: ey, .. } = element.bounds();
+        let x = u32::try_from(x as i64 - ex).ok()?;
+        let y = u32::try_from(y as i64 - ey).ok()?;
+        Some((x, y))
+    }
 
-    state.data_mut().width = width;
-    state.data_mut().height = height;
+    let container = scroll_container(elements);
+    let (x, y) = within(container, x, y)?;
+    let idx = container.child_index_at(x, y)?;
+    // In two columns, the only child is a row with a column of lines on each side. Otherwise, each
+    // child is a line, which is a row itself when it is numbered.
+    let children = container.children()?;
+    let is_columns = |children: &[Element<Data>]| {
+        children
+            .iter()
+            .all(|child| matches!(child.content, Content::Stack(_)))
+    };
+    let columns = match &children[idx].content {
+        Content::Row(columns) if is_columns(columns) => columns,
+        _ => return Some(idx),
+    };
+    let (x, y) = within(&children[idx], x, y)?;
+    let col = children[idx].child_index_at(x, y)?;
+    let (x, y) = within(&columns[col], x, y)?;
+    let line = columns[col].child_index_at(x, y)?;
+    let before: usize = columns[..col]
+        .iter()
+        .map(|column| match &column.content {
+            Content::Stack(lines) => lines.len(),
+            _ => 0,
+        })
+        .sum();
+    Some(before + line)
+}
 
-    let mut input = WinitInputHelper::new();
-    let window = setup_window(size, &event_loop);
+/// Where reading is at: the index of the line at the top of the page, and the byte offset into
+/// its text of the first of its wrapped lines that is in view.
+type ReadingAnchor = (usize, usize);
 
-    let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        PixelsBuilder::new(width, height, surface_texture)
-            .clear_color({
-                let [r, g, b, a] = state.background.map(|v| v as f64 / u8::MAX as f64);
-                pixels::wgpu::Color { r, g, b, a }
-            })
-            .blend_state(BlendState::REPLACE) // TODO: Investigate rendering weirdness.
-            .build()?
+/// Returns the lines of the page in the scroll `container` in order, each with how far down its
+/// column it is, as of the last time it was drawn.
+fn laid_out_lines(container: &Element<Data>) -> Vec<(u32, &Element<Data>)> {
+    let Some(children) = container.children() else {
+        return Vec::new();
     };
+    // In two columns, the only child is a row with a column of lines on each side.
+    let columns: Vec<&[Element<Data>]> = match children {
+        [row] => match &row.content {
+            Content::Row(columns) => columns
+                .iter()
+                .map(|column| match &column.content {
+                    Content::Stack(lines) => Some(lines.as_slice()),
+                    _ => None,
+                })
+                .collect::<Option<_>>()
+                .unwrap_or_else(|| vec![children]),
+            _ => vec![children],
+        },
+        _ => vec![children],
+    };
+    let mut lines = Vec::new();
+    for column in columns {
+        let mut y = 0;
+        for line in column {
+            lines.push((y, line));
+            y += line.overall_size().height;
+        }
+    }
+    lines
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        control_flow.set_poll();
+/// Returns the wrapped text of a `line` of the page, unless it is not a paragraph. A numbered line
+/// has it next to its number, and a link next to the hint of where it leads.
+fn line_paragraph(line: &Element<Data>) -> Option<&WrappedText> {
+    match &line.content {
+        Content::Row(children) => children.iter().find_map(line_paragraph),
+        _ => line.as_paragraph(),
+    }
+}
 
-        match event {
-            // Event::NewEvents(winit::event::StartCause::ResumeTimeReached { .. }) => {
-            //     window.request_redraw()
-            // }
-            Event::RedrawRequested(_) => {
-                // Clear the screen before drawing.
-                pixels
-                    .frame_mut()
-                    .array_chunks_mut()
-                    .for_each(|px| *px = state.background);
+/// Returns where reading is at in the scroll `container` when it is scrolled to `scroll_pos`.
+fn reading_anchor(container: &Element<Data>, scroll_pos: usize) -> Option<ReadingAnchor> {
+    let scroll_pos = scroll_pos as u32;
+    let lines = laid_out_lines(container);
+    let (idx, &(y, line)) = lines
+        .iter()
+        .enumerate()
+        .find(|(_, (y, line))| scroll_pos < y + line.overall_size().height)?;
+    let wrapped_line = (scroll_pos - y) / container.style.line_height();
+    let offset =
+        line_paragraph(line).map_or(0, |wrapped| wrapped.line_start(wrapped_line as usize));
+    Some((idx, offset))
+}
 
-                eprintln!("INFO: Redrawing...");
-                // Update the state, then draw.
-                state.update();
-                state.draw(&mut pixels.frame_mut());
+/// Returns the scroll position that brings the text of the `anchor` to the top of the scroll
+/// `container`.
+fn anchor_position(container: &Element<Data>, (idx, offset): ReadingAnchor) -> Option<usize> {
+    let &(y, line) = laid_out_lines(container).get(idx)?;
+    let wrapped_line = line_paragraph(line).map_or(0, |wrapped| wrapped.line_of(offset));
+    Some((y + wrapped_line as u32 * container.style.line_height()) as usize)
+}
 
-                // Try to render.
-                if let Err(err) = pixels.render() {
-                    eprintln!("ERROR: {err}");
-                    control_flow.set_exit();
-                    return;
-                }
-            }
-            _ => (),
+/// Load the page at `address`, which may be relative to the current page, and came from the
+/// `origin`.
+///
+/// Addresses this client cannot show, such as web pages, are opened in the system browser instead.
+fn navigate(data: &mut Data, address: &str, origin: Origin) {
+    let Some(target) = resolve_address(&data.address, address, origin) else {
+        error!(
+            "Could not resolve {address:?} relative to {:?}",
+            data.address
+        );
+        return;
+    };
+    if opens_externally(&target) {
+        open_in_browser(&target);
+        return;
+    }
+    info!("Navigating to {target}");
+    // Any fetch that is still going is abandoned by dropping its receiver, along with where it was
+    // to be scrolled to.
+    data.restore_scroll = None;
+    let source = Arc::clone(&data.source);
+    let (limit, retry) = (data.size_limit, data.retry_policy);
+    let base = &data.address;
+    data.fetch = Some(spawn_fetch(&target, base, origin, source, limit, retry));
+    data.status = Status::Loading { bytes: 0, ticks: 0 };
+    request_favicon(data, &target);
+}
+
+/// Show the source of the page in `data`, or the page again if its source is shown. The same line
+/// is at a different height in either, so the view starts over at the top.
+fn toggle_source_view(data: &mut Data) {
+    data.source_view = !data.source_view;
+    data.scroll_pos = 0;
+    data.hscroll = 0;
+    let shown = if data.source_view { "source" } else { "page" };
+    data.status = Status::Message(format!("showing the {shown}"));
+}
+
+/// Hand the normalized address of the page to `copy`, which puts it on the clipboard, and tell
+/// how that went in the status line.
+fn copy_address(data: &mut Data, copy: impl FnOnce(&str) -> anyhow::Result<()>) {
+    // Standard input has no address that could be normalized, nor shared.
+    let address = normalize_url(&data.address).unwrap_or_else(|_| data.address.clone());
+    data.status = match copy(&address) {
+        Ok(()) => Status::Message(format!("copied {address}")),
+        Err(err) => {
+            error!("Could not copy {address}: {err}");
+            Status::Error(format!("could not copy: {err}"))
         }
+    };
+}
 
-        if input.update(&event) {
-            // Scroll around.
-            if input.key_pressed(VirtualKeyCode::Up) | input.key_pressed(VirtualKeyCode::K) {
-                let pos = &mut state.data_mut().scroll_pos;
-                *pos = pos.saturating_sub(SCROLL_STEP);
-                window.request_redraw();
-            }
+/// Start loading the favicon of the capsule that `address` is on, unless favicons are turned off
+/// or it was asked for before.
+fn request_favicon(data: &mut Data, address: &str) {
+    if !data.favicons {
+        return;
+    }
+    let (Some(host), Some(url)) = (capsule_host(address), favicon_url(address)) else {
+        return;
+    };
+    if data.favicon_cache.contains_key(&host) {
+        return;
+    }
+    debug!("Asking {host} for its favicon");
+    // The host is only ever asked once, even if it turns out not to have a favicon.
+    data.favicon_cache.insert(host.clone(), None);
+    let source = Arc::clone(&data.source);
+    let (limit, retry) = (data.size_limit, data.retry_policy);
+    let fetch = spawn_fetch(&url, &url, Origin::Page, source, limit, retry);
+    data.favicon_fetches.push((host, fetch));
+}
 
-            if input.key_pressed(VirtualKeyCode::Down) | input.key_pressed(VirtualKeyCode::J) {
-                state.data_mut().scroll_pos += SCROLL_STEP;
-                window.request_redraw();
+/// Take in the favicons that have arrived since the last call. Returns whether any of them are to
+/// be shown.
+///
+/// A capsule without a proper favicon simply goes without one, so failures are not reported.
+fn poll_favicons(data: &mut Data) -> bool {
+    let mut changed = false;
+    let cache = &mut data.favicon_cache;
+    data.favicon_fetches.retain(|(host, fetch)| loop {
+        match fetch.try_recv() {
+            Ok(FetchEvent::Progress { .. }) => continue,
+            Ok(FetchEvent::Done(Ok(page))) => {
+                let favicon = parse_favicon(&page.body).map(str::to_string);
+                changed |= favicon.is_some();
+                cache.insert(host.clone(), favicon);
+                return false;
             }
+            Ok(FetchEvent::Done(Err(err))) => {
+                debug!("No favicon for {host}: {err}");
+                return false;
+            }
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => return false,
+        }
+    });
+    changed
+}
 
-            // Set mode.
-            {
-                let data = state.data_mut();
-                let mode = &mut data.mode;
-
-                match mode {
-                    Mode::Normal => {
-                        if input.key_pressed(VirtualKeyCode::I) {
-                            *mode = Mode::Insert;
-                            window.request_redraw();
-                        }
-                        if input.key_pressed(VirtualKeyCode::F) {
-                            eprintln!(
-                                "TODO: The implementation of `Mode::Link` has been \
-                                left as an exercise to cute ppl. <3"
-                            );
-                            *mode = Mode::Link;
-                            window.request_redraw();
-                        }
-                    }
-                    Mode::Insert => {
-                        for ch in input.text() {
-                            match ch {
-                                TextChar::Char('\n') => {
-                                    data.address.clear();
-                                    eprintln!("Please pretend some other site's text is loading.")
-                                }
-                                TextChar::Char(ch) => data.address.push(ch),
-                                TextChar::Back => {
-                                    let _ = data.address.pop();
-                                }
-                            }
-                            window.request_redraw();
-                        }
-                    }
-                    Mode::Link => { /* TODO */ }
-                }
+/// Take in the response to the running Titan upload, and show it in the status line once it has
+/// arrived. Returns whether it has.
+fn poll_upload(data: &mut Data) -> bool {
+    let Some(upload) = &data.upload_response else {
+        return false;
+    };
+    let response = match upload.try_recv() {
+        Ok(response) => response,
+        Err(TryRecvError::Empty) => return false,
+        Err(TryRecvError::Disconnected) => Err(anyhow::anyhow!("the upload stopped")),
+    };
+    data.upload_response = None;
+    data.status = match response {
+        Ok(response) => {
+            info!("Titan upload response: {response}");
+            Status::Message(format!("uploaded: {response}"))
+        }
+        Err(err) => {
+            error!("Titan upload failed: {err}");
+            Status::Error(format!("upload failed: {err}"))
+        }
+    };
+    true
+}
 
-                if input.key_pressed(VirtualKeyCode::Escape) {
-                    *mode = Mode::Normal;
-                    window.request_redraw();
+/// Take in whatever the running fetch has reported since the last call, and show the page once it
+/// has arrived. Returns whether anything changed that should be redrawn.
+fn poll_fetch(state: &mut Panel<Data>) -> bool {
+    let mut changed = false;
+    loop {
+        let data = state.data_mut();
+        let Some(fetch) = &data.fetch else {
+            return changed;
+        };
+        match fetch.try_recv() {
+            Ok(FetchEvent::Progress { bytes }) => {
+                let ticks = match data.status {
+                    Status::Loading { ticks, .. } => ticks + 1,
+                    _ => 0,
+                };
+                data.status = Status::Loading { bytes, ticks };
+            }
+            // A question has no page, so the page that is shown stays while it is answered.
+            Ok(FetchEvent::Done(Ok(page))) if page.asks_for_input() => {
+                if let Ok(url) = Url::parse(&page.address) {
+                    data.prompt = Some(Prompt {
+                        url,
+                        question: page.meta,
+                        sensitive: page.status == STATUS_SENSITIVE_INPUT,
+                    });
+                    data.answer.clear();
+                    data.mode = Mode::Input;
                 }
+                data.status = Status::Idle;
+                data.fetch = None;
             }
-
-            // Close events.
-            if input.close_requested() {
-                eprintln!("INFO:  Close requested. Bye :)");
-                control_flow.set_exit();
-                return;
+            Ok(FetchEvent::Done(Ok(page))) => {
+                data.fetch = None;
+                state.set_content(page);
             }
-
-            // Resize the window.
-            if let Some(size) = input.window_resized() {
-                eprintln!("INFO:  Resize request {size:?}");
-                let ps = PhysicalSize {
-                    width: (size.width / scale_factor) * scale_factor,
-                    height: (size.height / scale_factor) * scale_factor,
-                };
-                let ls = LogicalSize {
-                    width: ps.width / scale_factor,
-                    height: ps.height / scale_factor,
-                };
-                pixels.resize_surface(ps.width, ps.height).unwrap();
-                pixels.resize_buffer(ls.width, ls.height).unwrap();
-                window.set_inner_size(ps);
-                state.resize(ls.width, ls.height);
-                state.data_mut().width = ls.width;
-                state.data_mut().height = ls.height;
-                window.request_redraw();
+            Ok(FetchEvent::Done(Err(err))) => {
+                error!("Fetching failed: {err}");
+                data.status = Status::Error(err.to_string());
+                data.fetch = None;
+            }
+            Err(TryRecvError::Empty) => return changed,
+            Err(TryRecvError::Disconnected) => {
+                error!("The fetch worker stopped without reporting back");
+                data.status = Status::Error("the fetch was interrupted".to_string());
+                data.fetch = None;
             }
         }
-    });
+        changed = true;
+    }
+}
+
+/// Remember the page that is shown, how far it is scrolled, and how far the window is zoomed in the
+/// session file at `path`, to pick up from there on the next start. Nothing is written while
+/// browsing privately.
+fn save_session(path: Option<&Path>, data: &Data) {
+    let Some(path) = path else {
+        return;
+    };
+    if data.private {
+        debug!("Not saving the session while browsing privately");
+        return;
+    }
+    let session = Session {
+        url: data.address.clone(),
+        scroll_pos: data.scroll_pos,
+        zoom: Some(data.zoom),
+    };
+    match session::store(path, &session) {
+        Ok(()) => debug!("Saved the session to {path:?}"),
+        Err(err) => error!("Could not save the session to {path:?}: {err}"),
+    }
+}
+
+/// Apply the color `setting` to the `element` and all of its children.
+fn recolor(element: &mut Element<Data>, setting: Setting) {
+    match setting {
+        Setting::Foreground(color) => element.style.foreground = color,
+        Setting::Background(color) => element.style.background = color,
+    }
+    if let Content::Row(children) | Content::Stack(children) = &mut element.content {
+        for child in children {
+            recolor(child, setting);
+        }
+    }
+}
+
+/// Returns the best completion of the `typed` address, from the pages that have been `visited` and
+/// the `bookmarks`.
+fn complete_address(
+    typed: &str,
+    visited: &HashSet<String>,
+    bookmarks: &[String],
+) -> Option<String> {
+    let sources: Vec<_> = visited
+        .iter()
+        .chain(bookmarks)
+        .map(String::as_str)
+        .collect();
+    complete(typed, &sources)
+}
+
+/// Returns the URL of the link in `text` that is followed in link mode with the `filter` typed,
+/// which is the first one whose label matches. Text that is not `gemtext` has no links, and bare
+/// URLs are only links if they are to be `linkify`'d.
+fn highlighted_link<'t>(
+    text: &'t str,
+    gemtext: bool,
+    linkify: bool,
+    filter: &str,
+) -> Option<&'t str> {
+    let links: Vec<_> = gemtext::links_as(text, gemtext, linkify).collect();
+    filter_links(&links, filter)
+        .first()
+        .map(|&idx| links[idx].1)
+}
+
+/// Returns what is shown after the `typed` address to suggest its `completion`.
+///
+/// That is the rest of the completion if it continues what is typed, or else the completion as a
+/// whole.
+fn suggested(typed: &str, completion: &str) -> String {
+    match completion.get(..typed.len()) {
+        Some(start) if start.eq_ignore_ascii_case(typed) => completion[typed.len()..].to_string(),
+        _ => format!(" {completion}"),
+    }
+}
+
+/// Returns how far lines can be scrolled sideways, which is as far as the `widest` of them sticks
+/// out past the `available` width.
+fn max_hscroll(widest: u32, available: u32) -> u32 {
+    widest.saturating_sub(available)
+}
+
+/// Returns the `size` of the window snapped down to a whole number of content pixels at `zoom`,
+/// along with the size of the content that fills it.
+fn zoomed_size(size: PhysicalSize<u32>, zoom: u32) -> (PhysicalSize<u32>, LogicalSize<u32>) {
+    let content = LogicalSize {
+        width: size.width / zoom,
+        height: size.height / zoom,
+    };
+    let snapped = PhysicalSize {
+        width: content.width * zoom,
+        height: content.height * zoom,
+    };
+    (snapped, content)
+}
+
+/// Returns the `label` of a link line, which reads `text`, followed by the `hint` of where it
+/// leads, together no wider than `width`. The label only takes up as much room as it needs, such
+/// that the hint comes right after it, unless it has to wrap.
+fn link_with_hint(
+    label: Element<Data>,
+    text: &str,
+    hint: &str,
+    width: u32,
+    hint_style: &Style,
+) -> Element<Data> {
+    let font = &hint_style.font;
+    let gap = font.determine_width(" ") as u32;
+    let hint_width = (font.determine_width(hint) as u32).min(width.saturating_sub(gap));
+    let label_room = width.saturating_sub(hint_width + gap);
+    let label_width = (font.determine_width(text) as u32).min(label_room);
+    let rtl = label.style.direction == Direction::RightToLeft;
+    let label = label.with_fixedwidth(label_width);
+    let hint = Element::text(hint, font)
+        .build()
+        .with_style(hint_style.clone())
+        .with_fixedwidth(hint_width);
+    // Right-to-left text reads from the other side, so its hint comes first.
+    let children = match rtl {
+        true => vec![hint.with_padding_right(gap), label],
+        false => vec![label, hint.with_padding_left(gap)],
+    };
+    Element::still(Rc::clone(font), Content::Row(children)).with_style(hint_style.clone())
+}
+
+/// Returns a `line` of a table in a preformatted block, with its cells made as wide as the
+/// `widths` of their columns in pixels, such that the columns line up.
+fn table_row(line: &TableLine, widths: &[usize], style: &Style) -> Element<Data> {
+    let padding = style.font.determine_width(" ") as u32;
+    let separator = match line {
+        TableLine::Row(_) => TABLE_CELL_SEPARATOR,
+        TableLine::Separator => TABLE_CROSSING,
+    };
+    let mut children = Vec::new();
+    for (col, &width) in widths.iter().enumerate() {
+        if col > 0 {
+            let separator = Element::text(separator, &style.font).build();
+            children.push(separator.with_style(style.clone()));
+        }
+        // A column that is empty throughout, such as the one before a leading pipe, takes no room.
+        let padding = if width > 0 { padding } else { 0 };
+        let cell = match line {
+            TableLine::Row(cells) => Element::text(cells[col], &style.font)
+                .build()
+                .with_fixedwidth(width as u32)
+                .with_padding_left(padding)
+                .with_padding_right(padding),
+            TableLine::Separator => Element::rule(&style.font)
+                .build()
+                .with_fixedwidth(width as u32 + 2 * padding)
+                .with_valign(VAlignment::Center),
+        };
+        children.push(cell.with_style(style.clone()));
+    }
+    Element::still(Rc::clone(&style.font), Content::Row(children)).with_style(style.clone())
+}
+
+/// Returns how far a page up or down scrolls the scroll `container`, which is as many whole lines
+/// as it shows but one, such that the last line of one page is the first of the next.
+fn page_step(container: &Element<Data>) -> usize {
+    let line_height = container.style.line_height() as usize;
+    container.visible_line_count().saturating_sub(1) * line_height
+}
+
+struct Data {
+    text: String,
+    scroll_pos: usize,
+    /// The largest `scroll_pos` that still shows content, as of the last update.
+    max_scroll: usize,
+    /// How far preformatted lines are scrolled sideways, in pixels.
+    hscroll: u32,
+    /// The largest `hscroll` that still shows content, as of the last time the lines were built.
+    max_hscroll: Cell<u32>,
+    /// How far a page up or down scrolls, which is a viewport minus one line of overlap.
+    page_step: usize,
+    /// The part of a mouse wheel scroll that did not yet add up to a whole pixel.
+    wheel_remainder: f32,
+    address: String,
+    /// The meta string of the response that the current page came with.
+    meta: String,
+    /// The status of the response, if it failed to bring a page, in which case its meta says why.
+    failure: Option<u8>,
+    /// The language of the current page, if its response said so.
+    lang: Option<Lang>,
+    /// Whether the current page is written from right to left.
+    rtl: bool,
+    /// The body that is uploaded to the current address in [`Mode::Titan`].
+    upload: String,
+    /// The command that is being typed in [`Mode::Command`].
+    command: String,
+    /// The page that asked the question that is being answered in [`Mode::Input`].
+    prompt: Option<Prompt>,
+    /// The answer that is being typed in [`Mode::Input`].
+    answer: String,
+    /// The part of a link label that is being typed in [`Mode::Link`].
+    link_filter: String,
+    /// The index of the heading that is selected in [`Mode::Outline`].
+    outline_selected: usize,
+    /// Whether [`Mode::Outline`] lists all sections of the page, not only its headings.
+    outline_sections: bool,
+    mode: Mode,
+    status: Status,
+    /// The page that is being loaded in the background, if any.
+    fetch: Option<Receiver<FetchEvent>>,
+    /// The Titan upload that is waiting for the response of the server, if any.
+    upload_response: Option<Receiver<anyhow::Result<String>>>,
+    /// The count typed before a motion in [`Mode::Normal`].
+    count: Count,
+    /// The scroll position to go to once the page that is loading has arrived, instead of the top.
+    restore_scroll: Option<usize>,
+    /// The text to bring back to the top once the lines are wrapped anew for a new window size.
+    reading_anchor: Option<ReadingAnchor>,
+    /// The normalized URLs of the pages that have been visited.
+    visited: HashSet<String>,
+    /// The addresses in the bookmarks file.
+    bookmarks: Vec<String>,
+    /// The client certificates for hosts that asked for one, if there is a place to keep them.
+    identities: Option<Identities>,
+    /// Where pages are loaded from.
+    source: Arc<dyn PageSource + Send + Sync>,
+    /// How large pages may be, and what becomes of larger ones.
+    size_limit: SizeLimit,
+    /// How often and how soon requests that failed transiently are tried again.
+    retry_policy: RetryPolicy,
+    /// Whether nothing about the session is written to disk.
+    private: bool,
+    /// Whether capsules are asked for their favicon, to show it next to their address.
+    favicons: bool,
+    /// The favicons of the capsules that were asked for one, by host. Capsules that have none, or
+    /// have yet to answer, have `None`.
+    favicon_cache: HashMap<String, Option<String>>,
+    /// The favicons that are being loaded in the background, with the host they are for.
+    favicon_fetches: Vec<(String, Receiver<FetchEvent>)>,
+    /// The [`text_fingerprint`] of the lines as they were last built.
+    laid_out: Cell<Option<u64>>,
+    /// The empty space around everything in the window.
+    margin: Padding,
+    /// How many pixels of the window wide and high every pixel of the content is drawn.
+    zoom: u32,
+    /// How many characters wide the text is wrapped at most, or 0 for no cap.
+    max_content_width: u32,
+    /// Whether the page is shown in two columns when the window is wide enough.
+    two_columns: bool,
+    /// Whether the lines are numbered in a gutter.
+    line_numbers: bool,
+    /// Whether the source of the page is shown as it is, instead of the page that it makes up.
+    source_view: bool,
+    /// Whether the columns of tables in preformatted blocks are lined up.
+    align_tables: bool,
+    /// Whether the lines of preformatted blocks are tinted after the language in their alt text.
+    tint_code: bool,
+    added_color: Pixel,
+    removed_color: Pixel,
+    comment_color: Pixel,
+    /// The color of link lines.
+    link_color: Pixel,
+    /// Whether links are followed by a dimmed hint of the scheme and host that they lead to.
+    link_hints: bool,
+    /// Whether bare URLs in the text of gemtext pages are links in [`Mode::Link`].
+    linkify: bool,
+    /// The color of heading lines.
+    heading_color: Pixel,
+    /// The color of the banner of a page that failed to load.
+    error_color: Pixel,
+    /// How many words are read per minute, for [`Data::reading_stats`].
+    words_per_minute: u32,
+    /// The open tabs. The current one is left empty while its page is the one being shown.
+    tabs: Tabs<Tab>,
+    width: u32,
+    height: u32,
+}
+
+impl Data {
+    /// Returns how many whole pixels the mouse `wheel` scrolls by, down being positive, given that
+    /// a line of the wheel is `step` pixels. The fractions of a pixel that are left add up with
+    /// those of later wheel turns, such that even the finest turns scroll in the end.
+    fn wheel_pixels(&mut self, wheel: f32, step: usize, natural: bool) -> i64 {
+        let direction = if natural { 1.0 } else { -1.0 };
+        self.wheel_remainder += direction * wheel * step as f32;
+        let whole = self.wheel_remainder.trunc();
+        self.wheel_remainder -= whole;
+        whole as i64
+    }
+
+    /// Returns the width of the window inside the margin.
+    fn inner_width(&self) -> u32 {
+        self.width
+            .saturating_sub(self.margin.left + self.margin.right)
+    }
+
+    /// Returns the height of the window inside the margin.
+    fn inner_height(&self) -> u32 {
+        self.height
+            .saturating_sub(self.margin.top + self.margin.bottom)
+    }
+
+    /// Returns the absolute address of the link that Enter follows in link mode with the filter
+    /// typed so far, which is also what link mode previews.
+    fn link_target(&self) -> Option<String> {
+        let gemtext = self.shows_gemtext();
+        let url = highlighted_link(&self.text, gemtext, self.linkify, &self.link_filter)?;
+        resolve_link(&self.address, url)
+    }
+
+    /// Returns whether the page is shown as gemtext, which it is if it is gemtext, unless its
+    /// source is shown instead.
+    fn shows_gemtext(&self) -> bool {
+        !self.source_view && is_gemtext(&self.meta)
+    }
+
+    /// Returns the number of words on the page, and about how long reading it takes.
+    fn reading_stats(&self) -> (usize, Duration) {
+        reading_stats(&self.text, self.words_per_minute)
+    }
+
+    /// Returns the favicon of the capsule that the current page is on, if it has one.
+    fn favicon(&self) -> Option<&str> {
+        self.favicon_cache
+            .get(&capsule_host(&self.address)?)?
+            .as_deref()
+    }
+
+    /// Returns the best completion of the address that is being typed.
+    fn address_completion(&self) -> Option<String> {
+        complete_address(&self.address, &self.visited, &self.bookmarks)
+    }
+
+    /// Returns the page that is being shown as a [`Tab`], leaving an empty page in its place.
+    fn take_tab(&mut self) -> Tab {
+        Tab {
+            address: std::mem::take(&mut self.address),
+            text: std::mem::take(&mut self.text),
+            meta: std::mem::take(&mut self.meta),
+            failure: self.failure.take(),
+            lang: self.lang.take(),
+            rtl: std::mem::take(&mut self.rtl),
+            scroll_pos: std::mem::take(&mut self.scroll_pos),
+            hscroll: std::mem::take(&mut self.hscroll),
+            status: std::mem::take(&mut self.status),
+            fetch: self.fetch.take(),
+        }
+    }
+
+    /// Show the page of `tab` in place of the one that is being shown.
+    fn show_tab(&mut self, tab: Tab) {
+        self.address = tab.address;
+        self.text = tab.text;
+        self.meta = tab.meta;
+        self.failure = tab.failure;
+        self.lang = tab.lang;
+        self.rtl = tab.rtl;
+        self.scroll_pos = tab.scroll_pos;
+        self.hscroll = tab.hscroll;
+        self.status = tab.status;
+        self.fetch = tab.fetch;
+    }
+
+    /// Change the current tab with `switch`, keeping the page that is being shown in its own tab.
+    fn switch_tab(&mut self, switch: impl FnOnce(&mut Tabs<Tab>)) {
+        *self.tabs.current_mut() = self.take_tab();
+        switch(&mut self.tabs);
+        let tab = std::mem::take(self.tabs.current_mut());
+        self.show_tab(tab);
+    }
+
+    /// Open a new tab next to the current one, showing the same page.
+    fn open_tab(&mut self) {
+        let tab = Tab {
+            address: self.address.clone(),
+            text: self.text.clone(),
+            meta: self.meta.clone(),
+            failure: self.failure,
+            lang: self.lang.clone(),
+            rtl: self.rtl,
+            ..Tab::default()
+        };
+        self.switch_tab(|tabs| tabs.open(tab));
+    }
+
+    /// Close the current tab and show the one that takes its place. Returns whether there was
+    /// another tab to show, since the last tab is not closed.
+    fn close_tab(&mut self) -> bool {
+        if self.tabs.close().is_none() {
+            return false;
+        }
+        let tab = std::mem::take(self.tabs.current_mut());
+        self.show_tab(tab);
+        true
+    }
+}
+
+impl Navigable for Data {
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn is_gemtext(&self) -> bool {
+        is_gemtext(&self.meta)
+    }
+
+    fn show_page(&mut self, page: FetchOutcome) {
+        // The meta of a failed request is an error message rather than a media type.
+        self.lang = parse_meta(&page.meta).ok().and_then(|(_, lang)| lang);
+        self.rtl = self.lang.as_ref().is_some_and(Lang::is_rtl);
+        // A failed response has no page, only its meta to say why.
+        self.failure = is_failure(page.status).then_some(page.status);
+        self.text = match self.failure {
+            Some(_) => String::new(),
+            None => page.body,
+        };
+        self.meta = page.meta;
+        self.address = page.address.clone();
+        self.scroll_pos = self.restore_scroll.take().unwrap_or(0);
+        self.hscroll = 0;
+        self.visited.insert(page.address);
+        self.status = Status::Idle;
+    }
+}
+
+/// A page that is open in a tab, along with how far along it was read.
+#[derive(Default)]
+struct Tab {
+    address: String,
+    text: String,
+    meta: String,
+    failure: Option<u8>,
+    lang: Option<Lang>,
+    rtl: bool,
+    scroll_pos: usize,
+    hscroll: u32,
+    status: Status,
+    /// The page that is being loaded in the background for this tab, if any.
+    fetch: Option<Receiver<FetchEvent>>,
+}
+
+/// A page that asked for input, which is loaded again with the answer.
+struct Prompt {
+    /// The URL of the page that asked.
+    url: Url,
+    /// What the page asked.
+    question: String,
+    /// Whether the answer is kept from view while it is typed, such as a password.
+    sensitive: bool,
+}
+
+#[derive(PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Insert,
+    Link,
+    Titan,
+    /// Typing a command at the `:` prompt.
+    Command,
+    /// Picking a heading or section of the page to go to from its outline.
+    Outline,
+    /// Answering the question that a page asked.
+    Input,
+}
+
+impl ToString for Mode {
+    fn to_string(&self) -> String {
+        match self {
+            Mode::Normal => "normal".to_string(),
+            Mode::Insert => "insert".to_string(),
+            Mode::Link => "link".to_string(),
+            Mode::Titan => "titan".to_string(),
+            Mode::Command => "command".to_string(),
+            Mode::Outline => "outline".to_string(),
+            Mode::Input => "input".to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Mode::Normal),
+            "insert" => Ok(Mode::Insert),
+            "link" => Ok(Mode::Link),
+            "titan" => Ok(Mode::Titan),
+            "command" => Ok(Mode::Command),
+            "outline" => Ok(Mode::Outline),
+            "input" => Ok(Mode::Input),
+            other => Err(format!("unknown mode '{other}'")),
+        }
+    }
+}
+
+/// What the status line shows next to the [`Mode`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum Status {
+    /// Nothing is going on.
+    #[default]
+    Idle,
+    /// A page is being loaded, of which `bytes` have arrived after `ticks` progress reports.
+    Loading { bytes: usize, ticks: usize },
+    /// The last command went well, and has something to say about it.
+    Message(String),
+    /// The last command failed.
+    Error(String),
+}
+
+impl Status {
+    const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+    /// Returns the text of the status line while in `mode`.
+    fn render(&self, mode: &Mode) -> String {
+        let mode = mode.to_string();
+        match self {
+            Status::Idle => mode,
+            Status::Loading { bytes, ticks } => {
+                let spinner = Self::SPINNER[ticks % Self::SPINNER.len()];
+                format!("{mode} | {spinner} loading, {bytes} bytes")
+            }
+            Status::Message(msg) => format!("{mode} | {msg}"),
+            Status::Error(msg) => format!("{mode} | error: {msg}"),
+        }
+    }
+}
+
+/// Load the start page of the `config` and print it to stdout, laid out as it is in the window.
+fn dump_page(config: &Config) -> anyhow::Result<()> {
+    let identities = user_dir().map(|dir| Identities::new(dir.join(IDENTITIES_DIR), SelfSigned));
+    let source = GeminiSource {
+        identities,
+        max_size: config.max_page_size,
+    };
+    let limit = SizeLimit {
+        max_size: config.max_page_size,
+        truncate: config.truncate_large_pages,
+    };
+    let address = &config.start_url;
+    let retry = RetryPolicy {
+        retries: config.retries,
+        backoff: Duration::from_millis(config.retry_delay_ms),
+    };
+    let origin = Origin::Start;
+    let page = fetch_page(&source, address, address, origin, limit, retry, &mut |_| {})?;
+    // Only a successful response has a page to show, the others have their meta to say why not.
+    if page.status / 10 != STATUS_SUCCESS / 10 {
+        anyhow::bail!("{} {}", page.status, page.meta)
+    }
+    let gemtext = is_gemtext(&page.meta);
+    let hints = config.link_hints;
+    let rendered = dump::render(&page.body, gemtext, &page.address, hints, config.dump_width);
+    print!("{rendered}");
+    Ok(())
+}
+
+fn main() -> Result<(), pixels::Error> {
+    logging::init();
+    let config = match configure() {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    };
+    logging::set_verbosity(config.verbosity);
+    // A page that is only dumped needs no window.
+    if config.dump {
+        let code = match dump_page(&config) {
+            Ok(()) => 0,
+            Err(err) => {
+                error!("{err}");
+                1
+            }
+        };
+        std::process::exit(code);
+    }
+    let (font, errors) = font::load_first_or_fallback(&config.font_path);
+    for (idx, err) in &errors {
+        let font_path = config.font_path[*idx].to_string_lossy();
+        warn!("Failed to load font from {font_path:?}: {err}");
+    }
+    if errors.len() == config.font_path.len() {
+        warn!("Using the built-in font");
+    }
+    let font = Rc::new(font);
+
+    let event_loop = EventLoop::new();
+
+    // The zoom that the window starts at, unless the session says otherwise, and is reset to.
+    let default_zoom = std::env::var("TID_SCALE_FACTOR")
+        .ok()
+        .and_then(|v| v.parse::<f32>().ok())
+        .map(|v| clamp_zoom(v.round() as u32))
+        .unwrap_or(1);
+
+    let mut style = Style::new(config.foreground, config.background, font);
+    style.line_spacing = config.line_spacing;
+    let elements = setup_elements(style, config.margin);
+    let session_path = session::session_path();
+    let session = match (&session_path, config.restore_session) {
+        (Some(path), true) => session::load(path).unwrap_or_else(|err| {
+            warn!("Could not restore the session from {path:?}, starting anew: {err}");
+            None
+        }),
+        _ => None,
+    };
+    let (start_url, restore_scroll, zoom) = match session {
+        Some(Session {
+            url,
+            scroll_pos,
+            zoom,
+        }) => (url, Some(scroll_pos), zoom),
+        None => (config.start_url.clone(), None, None),
+    };
+    let bookmarks = load_bookmarks(&config.download_dir).unwrap_or_else(|err| {
+        warn!(
+            "Could not read bookmarks from {:?}: {err}",
+            config.download_dir
+        );
+        Vec::new()
+    });
+    let identities = user_dir().map(|dir| Identities::new(dir.join(IDENTITIES_DIR), SelfSigned));
+    let mut data = Data {
+        text: String::new(),
+        scroll_pos: 0,
+        max_scroll: 0,
+        hscroll: 0,
+        max_hscroll: Cell::new(0),
+        page_step: 0,
+        wheel_remainder: 0.0,
+        address: start_url.clone(),
+        meta: String::new(),
+        failure: None,
+        lang: None,
+        rtl: false,
+        upload: String::new(),
+        command: String::new(),
+        prompt: None,
+        answer: String::new(),
+        link_filter: String::new(),
+        outline_selected: 0,
+        outline_sections: false,
+        mode: Mode::Normal,
+        status: Status::Idle,
+        fetch: None,
+        upload_response: None,
+        count: Count::default(),
+        restore_scroll: None,
+        reading_anchor: None,
+        visited: HashSet::new(),
+        bookmarks,
+        identities: identities.clone(),
+        source: Arc::new(GeminiSource {
+            identities,
+            max_size: config.max_page_size,
+        }),
+        size_limit: SizeLimit {
+            max_size: config.max_page_size,
+            truncate: config.truncate_large_pages,
+        },
+        retry_policy: RetryPolicy {
+            retries: config.retries,
+            backoff: Duration::from_millis(config.retry_delay_ms),
+        },
+        private: config.private,
+        favicons: config.favicons,
+        favicon_cache: HashMap::new(),
+        favicon_fetches: Vec::new(),
+        laid_out: Cell::new(None),
+        margin: config.margin,
+        zoom: clamp_zoom(zoom.unwrap_or(default_zoom)),
+        max_content_width: config.max_content_width,
+        two_columns: config.two_columns,
+        line_numbers: config.line_numbers,
+        source_view: false,
+        align_tables: config.align_tables,
+        tint_code: config.tint_code,
+        added_color: config.added_color,
+        removed_color: config.removed_color,
+        comment_color: config
+            .comment_color
+            .unwrap_or(mix(config.foreground, config.background)),
+        link_color: config.link_color.unwrap_or(config.foreground),
+        link_hints: config.link_hints,
+        linkify: config.linkify,
+        heading_color: config.heading_color.unwrap_or(config.foreground),
+        error_color: config.error_color,
+        words_per_minute: config.words_per_minute,
+        tabs: Tabs::new(Tab::default()),
+        width: 0,
+        height: 0,
+    };
+    navigate(&mut data, &start_url, Origin::Start);
+    data.restore_scroll = restore_scroll;
+    let mut state = Panel::new(elements, config.foreground, config.background, data);
+    state.set_on_page_loaded(Some(Box::new(|lines| {
+        let headings = lines
+            .iter()
+            .filter(|line| matches!(line, Line::Heading { .. }))
+            .count();
+        let count = lines.len();
+        debug!("Loaded a page of {count} lines with {headings} headings");
+    })));
+
+    let (width, height) = (state.width, state.height);
+    let zoom = state.data().zoom;
+    let size = PhysicalSize::new(width * zoom, height * zoom);
+
+    state.data_mut().width = width;
+    state.data_mut().height = height;
+
+    let natural_scrolling = config.natural_scrolling;
+    let scroll_step = config.scroll_step;
+    let max_step = config.scroll_max_step.unwrap_or(scroll_step);
+    let mut scroll_speed = ScrollSpeed::new(scroll_step, max_step);
+    let keymap = config.keymap;
+    let download_dir = config.download_dir;
+    let home = config.home;
+    let mut resize = Debounce::new(Duration::from_millis(config.resize_debounce_ms));
+    let mut input = WinitInputHelper::new();
+    let window = setup_window(size, &event_loop);
+
+    let mut pixels = {
+        let window_size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
+        PixelsBuilder::new(width, height, surface_texture)
+            .clear_color({
+                let [r, g, b, a] = premultiply(state.background).map(|v| v as f64 / u8::MAX as f64);
+                pixels::wgpu::Color { r, g, b, a }
+            })
+            // Panel::draw writes premultiplied pixels, so a translucent background blends with
+            // whatever is behind the (transparent) window, and an opaque one simply covers it.
+            .blend_state(BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+            .build()?
+    };
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            // Without any changes, the last frame is still good to show.
+            Event::RedrawRequested(_) if state.is_dirty() => {
+                // Clear the screen before drawing.
+                pixels
+                    .frame_mut()
+                    .array_chunks_mut()
+                    .for_each(|px| *px = premultiply(state.background));
+
+                trace!("Redrawing...");
+                // Update the state, then draw.
+                state.update();
+                // The page or window size may have changed, so the scroll bounds may have too. The
+                // outline is shown in place of the page, but has no bearing on them.
+                if state.data().mode != Mode::Outline {
+                    let container = scroll_container(&state.elements);
+                    let max_scroll = container.max_scroll() as usize;
+                    let page_step = page_step(container);
+                    // The text that is to be at the top, such as a heading picked from the outline
+                    // or what was there before the lines were wrapped anew, is brought there.
+                    let anchored = state
+                        .data()
+                        .reading_anchor
+                        .and_then(|anchor| anchor_position(container, anchor));
+                    let data = state.data_mut();
+                    data.max_scroll = max_scroll;
+                    data.page_step = page_step;
+                    data.reading_anchor = None;
+                    if let Some(scroll_pos) = anchored {
+                        data.scroll_pos = scroll_pos;
+                    }
+                    if anchored.is_some() || data.scroll_pos > max_scroll {
+                        data.scroll_pos = data.scroll_pos.min(max_scroll);
+                        state.update();
+                    }
+                }
+                state.draw(&mut pixels.frame_mut());
+                if window.is_resizable() {
+                    let color = premultiply(state.foreground);
+                    draw_resize_grip(pixels.frame_mut(), state.width, state.height, color);
+                }
+                state.mark_clean();
+
+                // Try to render.
+                if let Err(err) = pixels.render() {
+                    error!("{err}");
+                    control_flow.set_exit();
+                    return;
+                }
+            }
+            Event::RedrawRequested(_) => {
+                if let Err(err) = pixels.render() {
+                    error!("{err}");
+                    control_flow.set_exit();
+                    return;
+                }
+            }
+            _ => (),
+        }
+
+        if input.update(&event) {
+            let mut changed = false;
+
+            // Show how the page that is being loaded is coming along.
+            if state.data().fetch.is_some() && poll_fetch(&mut state) {
+                changed = true;
+            }
+            if !state.data().favicon_fetches.is_empty() && poll_favicons(state.data_mut()) {
+                changed = true;
+            }
+            if state.data().upload_response.is_some() && poll_upload(state.data_mut()) {
+                changed = true;
+            }
+
+            // Content that changes by itself asked to be drawn again by now.
+            if state.next_redraw().is_some_and(|due| due <= Instant::now()) {
+                changed = true;
+            }
+
+            // Scroll around. Holding a scroll key down may speed up the scrolling. In the outline,
+            // the same keys pick a heading instead.
+            if keymap.repeated(&input, Action::ScrollUp) && state.data().mode != Mode::Outline {
+                let data = state.data_mut();
+                let fresh = keymap.pressed(&input, Action::ScrollUp);
+                let step = scroll_speed.next(fresh).saturating_mul(data.count.take());
+                data.scroll_pos = data.scroll_pos.saturating_sub(step);
+                changed = true;
+            }
+
+            if keymap.repeated(&input, Action::ScrollDown) && state.data().mode != Mode::Outline {
+                let data = state.data_mut();
+                let fresh = keymap.pressed(&input, Action::ScrollDown);
+                let step = scroll_speed.next(fresh).saturating_mul(data.count.take());
+                data.scroll_pos = data.scroll_pos.saturating_add(step).min(data.max_scroll);
+                changed = true;
+            }
+
+            // Scroll preformatted lines sideways.
+            if keymap.repeated(&input, Action::ScrollLeft) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                let fresh = keymap.pressed(&input, Action::ScrollLeft);
+                let step = scroll_speed.next(fresh).saturating_mul(data.count.take()) as u32;
+                data.hscroll = data.hscroll.saturating_sub(step);
+                changed = true;
+            }
+
+            if keymap.repeated(&input, Action::ScrollRight) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                let fresh = keymap.pressed(&input, Action::ScrollRight);
+                let step = scroll_speed.next(fresh).saturating_mul(data.count.take()) as u32;
+                let max = data.max_hscroll.get();
+                data.hscroll = data.hscroll.saturating_add(step).min(max);
+                changed = true;
+            }
+
+            // Scroll with the mouse wheel. The deltas are in lines, and may be fractional.
+            let wheel = input.scroll_diff();
+            if wheel != 0.0 {
+                let data = state.data_mut();
+                let whole = data.wheel_pixels(wheel, scroll_step, natural_scrolling);
+                // With shift held, the wheel scrolls preformatted lines sideways.
+                if input.held_shift() {
+                    let max = data.max_hscroll.get() as i64;
+                    data.hscroll = (data.hscroll as i64 + whole).clamp(0, max) as u32;
+                } else {
+                    let max = data.max_scroll as i64;
+                    data.scroll_pos =
+                        (data.scroll_pos as i64 + whole).clamp(0, max) as usize;
+                }
+                changed = true;
+            }
+
+            // Resize the window by dragging the grip in its corner.
+            let zoom = state.data().zoom;
+            let mouse = input
+                .mouse()
+                .map(|(x, y)| (x as u32 / zoom, y as u32 / zoom));
+            let on_grip = window.is_resizable()
+                && mouse.is_some_and(|(x, y)| in_resize_grip(x, y, state.width, state.height));
+            // Highlight whatever the pointer is over.
+            if state.hover(mouse) {
+                changed = true;
+            }
+
+            if input.mouse_pressed(0) && on_grip {
+                if let Err(err) = window.drag_resize_window(ResizeDirection::SouthEast) {
+                    error!("Could not resize the window: {err}");
+                }
+            }
+
+            // Follow a link by clicking it.
+            if input.mouse_pressed(0) && !on_grip && state.data().mode == Mode::Normal {
+                let line = mouse.and_then(|(x, y)| line_at(&state.elements, x, y));
+                let data = state.data_mut();
+                let gemtext = data.shows_gemtext();
+                let link = line
+                    .and_then(|idx| gemtext::parse_as(&data.text, gemtext).nth(idx))
+                    .and_then(|(_, line)| match line {
+                        Line::Link { url, .. } => Some(url.to_string()),
+                        _ => None,
+                    });
+                if let Some(link) = link {
+                    navigate(data, &link, Origin::Page);
+                    changed = true;
+                }
+            }
+
+            // Load the current page again.
+            if keymap.pressed(&input, Action::Reload) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                let address = data.address.clone();
+                navigate(data, &address, Origin::Typed);
+                changed = true;
+            }
+
+            // Hand the current page over to the system browser.
+            if keymap.pressed(&input, Action::OpenExternal) && state.data().mode == Mode::Normal {
+                open_in_browser(&state.data().address);
+            }
+
+            // Copy the address of the page, to share it elsewhere.
+            if keymap.pressed(&input, Action::CopyAddress) && state.data().mode == Mode::Normal {
+                copy_address(state.data_mut(), clipboard::copy);
+                changed = true;
+            }
+
+            // Zoom in and out, which lays the page out anew in the room the window has at the new
+            // zoom.
+            if state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                let zoom = if keymap.pressed(&input, Action::ZoomIn) {
+                    Some(zoom_by(data.zoom, 1))
+                } else if keymap.pressed(&input, Action::ZoomOut) {
+                    Some(zoom_by(data.zoom, -1))
+                } else if keymap.pressed(&input, Action::ZoomReset) && !data.count.is_typed() {
+                    // While a count is being typed, a 0 goes on the count instead.
+                    Some(default_zoom)
+                } else {
+                    None
+                };
+                if let Some(zoom) = zoom {
+                    data.zoom = zoom;
+                    data.status = Status::Message(format!("zoom {zoom}x"));
+                    let (ps, ls) = zoomed_size(window.inner_size(), zoom);
+                    pixels.resize_surface(ps.width, ps.height).unwrap();
+                    window.set_inner_size(ps);
+                    resize.push(ls, Instant::now());
+                    changed = true;
+                }
+            }
+
+            // Go to the home page.
+            if keymap.pressed(&input, Action::Home) && state.data().mode == Mode::Normal {
+                match &home {
+                    Some(home) => {
+                        navigate(state.data_mut(), home, Origin::Typed);
+                        changed = true;
+                    }
+                    None => info!("No home page is configured"),
+                }
+            }
+
+            // Switch between one and two columns.
+            if keymap.pressed(&input, Action::Columns) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                data.two_columns = !data.two_columns;
+                changed = true;
+            }
+
+            // Show or hide the line numbers.
+            if keymap.pressed(&input, Action::LineNumbers) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                data.line_numbers = !data.line_numbers;
+                changed = true;
+            }
+
+            // Show the source of the page, or the page again.
+            if keymap.pressed(&input, Action::SourceView) && state.data().mode == Mode::Normal {
+                toggle_source_view(state.data_mut());
+                changed = true;
+            }
+
+            // Start or stop browsing privately.
+            if keymap.pressed(&input, Action::Private) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                data.private = !data.private;
+                let switched = if data.private { "on" } else { "off" };
+                data.status = Status::Message(format!("private browsing {switched}"));
+                changed = true;
+            }
+
+            // Open, close, and go through tabs.
+            if keymap.pressed(&input, Action::NewTab) && state.data().mode == Mode::Normal {
+                state.data_mut().open_tab();
+                changed = true;
+            }
+
+            if keymap.pressed(&input, Action::CloseTab) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                if !data.close_tab() {
+                    data.status = Status::Message("cannot close the last tab".to_string());
+                }
+                changed = true;
+            }
+
+            if keymap.pressed(&input, Action::NextTab) && state.data().mode == Mode::Normal {
+                state.data_mut().switch_tab(Tabs::next);
+                changed = true;
+            }
+
+            if keymap.pressed(&input, Action::PrevTab) && state.data().mode == Mode::Normal {
+                state.data_mut().switch_tab(Tabs::prev);
+                changed = true;
+            }
+
+            // Save the current page.
+            if keymap.pressed(&input, Action::Save) && state.data().mode == Mode::Normal {
+                let data = state.data_mut();
+                data.status = match save_page(&download_dir, &data.address, &data.text) {
+                    Ok(path) => Status::Message(format!("saved to {}", path.display())),
+                    Err(err) => {
+                        error!("Could not save {}: {err}", data.address);
+                        Status::Error(format!("could not save: {err}"))
+                    }
+                };
+                changed = true;
+            }
+
+            // Set mode.
+            let mut command = None;
+            let mut follow = None;
+            {
+                let data = state.data_mut();
+                let mode = &mut data.mode;
+
+                match mode {
+                    Mode::Normal => {
+                        for ch in input.text() {
+                            match ch {
+                                TextChar::Char(':') => {
+                                    *mode = Mode::Command;
+                                    data.command.clear();
+                                    data.count.reset();
+                                    changed = true;
+                                    break;
+                                }
+                                TextChar::Char(ch) => {
+                                    data.count.push(ch);
+                                }
+                                TextChar::Back => {}
+                            }
+                        }
+                        if keymap.pressed(&input, Action::PageUp) {
+                            let step = data.page_step.saturating_mul(data.count.take());
+                            data.scroll_pos = data.scroll_pos.saturating_sub(step);
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::PageDown) {
+                            let step = data.page_step.saturating_mul(data.count.take());
+                            data.scroll_pos =
+                                data.scroll_pos.saturating_add(step).min(data.max_scroll);
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::Top) {
+                            data.scroll_pos = 0;
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::Bottom) {
+                            data.scroll_pos = data.max_scroll;
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::InsertMode) {
+                            *mode = Mode::Insert;
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::LinkMode) {
+                            *mode = Mode::Link;
+                            data.link_filter.clear();
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::TitanMode) {
+                            *mode = Mode::Titan;
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::Outline) {
+                            *mode = Mode::Outline;
+                            data.outline_selected = 0;
+                            data.outline_sections = false;
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::Sections) {
+                            *mode = Mode::Outline;
+                            data.outline_selected = 0;
+                            data.outline_sections = true;
+                            changed = true;
+                        }
+                        if keymap.pressed(&input, Action::TitanUpload) {
+                            let token = std::env::var(TITAN_TOKEN_VAR).ok();
+                            match Url::parse(&data.address) {
+                                Ok(url) => {
+                                    data.upload_response = Some(spawn_titan_upload(
+                                        &url,
+                                        data.upload.as_bytes(),
+                                        token.as_deref(),
+                                        TITAN_MIME,
+                                        TITAN_TIMEOUT,
+                                    ));
+                                    data.status = Status::Message("uploading".to_string());
+                                }
+                                Err(err) => {
+                                    data.status = Status::Error(format!("upload failed: {err}"));
+                                }
+                            }
+                            changed = true;
+                        }
+                    }
+                    Mode::Insert => {
+                        for ch in input.text() {
+                            match ch {
+                                TextChar::Char('\n') => {
+                                    data.address.clear();
+                                    info!("Please pretend some other site's text is loading.")
+                                }
+                                // Tab takes up the suggested completion of the address.
+                                TextChar::Char('\t') => {
+                                    let completion = complete_address(
+                                        &data.address,
+                                        &data.visited,
+                                        &data.bookmarks,
+                                    );
+                                    if let Some(completion) = completion {
+                                        data.address = completion;
+                                    }
+                                }
+                                TextChar::Char(ch) => data.address.push(ch),
+                                TextChar::Back => {
+                                    let _ = data.address.pop();
+                                }
+                            }
+                            changed = true;
+                        }
+                    }
+                    Mode::Link => {
+                        let mut typed = false;
+                        let mut submitted = false;
+                        for ch in input.text() {
+                            match ch {
+                                TextChar::Char('\r' | '\n') => {
+                                    submitted = true;
+                                    break;
+                                }
+                                TextChar::Char(ch) => data.link_filter.push(ch),
+                                // Erasing past the start of the label leaves, like the prompt.
+                                TextChar::Back => {
+                                    if data.link_filter.pop().is_none() {
+                                        *mode = Mode::Normal;
+                                    }
+                                }
+                            }
+                            typed = true;
+                        }
+                        // Typing only previews where the first matching link goes, and Enter
+                        // follows it.
+                        if *mode == Mode::Link && submitted {
+                            *mode = Mode::Normal;
+                            follow = data.link_target();
+                            if follow.is_none() {
+                                data.status = Status::Message(format!(
+                                    "no link matches '{}'",
+                                    data.link_filter
+                                ));
+                            }
+                        }
+                        changed |= typed || submitted;
+                    }
+                    Mode::Command => {
+                        for ch in input.text() {
+                            match ch {
+                                TextChar::Char('\r' | '\n') => {
+                                    command = Some(parse_command(&data.command));
+                                    *mode = Mode::Normal;
+                                    changed = true;
+                                    break;
+                                }
+                                TextChar::Char(ch) => data.command.push(ch),
+                                // Erasing past the start of the prompt leaves it, like in vi.
+                                TextChar::Back => {
+                                    if data.command.pop().is_none() {
+                                        *mode = Mode::Normal;
+                                    }
+                                }
+                            }
+                            changed = true;
+                        }
+                    }
+                    Mode::Input => {
+                        for ch in input.text() {
+                            match ch {
+                                TextChar::Char('\r' | '\n') => {
+                                    if let Some(prompt) = data.prompt.take() {
+                                        let url = build_input_url(&prompt.url, &data.answer);
+                                        follow = Some(url.to_string());
+                                    }
+                                    *mode = Mode::Normal;
+                                    changed = true;
+                                    break;
+                                }
+                                TextChar::Char(ch) => data.answer.push(ch),
+                                TextChar::Back => {
+                                    let _ = data.answer.pop();
+                                }
+                            }
+                            changed = true;
+                        }
+                    }
+                    Mode::Outline => {
+                        let gemtext = is_gemtext(&data.meta);
+                        let entries = outline_entries(&data.text, gemtext, data.outline_sections);
+                        if keymap.repeated(&input, Action::ScrollUp) {
+                            data.outline_selected = data.outline_selected.saturating_sub(1);
+                            changed = true;
+                        }
+                        if keymap.repeated(&input, Action::ScrollDown) {
+                            let last = entries.len().saturating_sub(1);
+                            data.outline_selected = (data.outline_selected + 1).min(last);
+                            changed = true;
+                        }
+                        // Enter goes to the selected heading or section, which is brought to the
+                        // top once the page is laid out again.
+                        let submitted = input
+                            .text()
+                            .iter()
+                            .any(|ch| matches!(ch, TextChar::Char('\r' | '\n')));
+                        if submitted {
+                            if let Some(&(_, _, line)) = entries.get(data.outline_selected) {
+                                data.reading_anchor = Some((line, 0));
+                            }
+                            *mode = Mode::Normal;
+                            changed = true;
+                        }
+                        let closed = [Action::Outline, Action::Sections]
+                            .into_iter()
+                            .any(|action| keymap.pressed(&input, action));
+                        if closed {
+                            *mode = Mode::Normal;
+                            changed = true;
+                        }
+                    }
+                    Mode::Titan => {
+                        for ch in input.text() {
+                            match ch {
+                                TextChar::Char('\r') => data.upload.push('\n'),
+                                TextChar::Char(ch) => data.upload.push(ch),
+                                TextChar::Back => {
+                                    let _ = data.upload.pop();
+                                }
+                            }
+                            changed = true;
+                        }
+                    }
+                }
+
+                if keymap.pressed(&input, Action::NormalMode) {
+                    data.mode = Mode::Normal;
+                    data.count.reset();
+                    changed = true;
+                }
+            }
+
+            // Follow the link that was picked by its label, or take the answer to a question back
+            // to the page that asked it.
+            if let Some(link) = follow {
+                navigate(state.data_mut(), &link, Origin::Page);
+            }
+
+            // Carry out the command that was typed at the prompt.
+            match command {
+                Some(Ok(Command::Open(address))) => {
+                    navigate(state.data_mut(), &address, Origin::Typed)
+                }
+                Some(Ok(Command::Quit)) => {
+                    info!("Quit. Bye :)");
+                    save_session(session_path.as_deref(), state.data());
+                    control_flow.set_exit();
+                    return;
+                }
+                Some(Ok(Command::Reload)) => {
+                    let data = state.data_mut();
+                    let address = data.address.clone();
+                    navigate(data, &address, Origin::Typed);
+                }
+                Some(Ok(Command::Set(setting))) => {
+                    match setting {
+                        Setting::Foreground(color) => state.foreground = color,
+                        Setting::Background(color) => state.background = color,
+                    }
+                    recolor(&mut state.elements, setting);
+                    // The lines are styled after the scroll container, so they are built anew.
+                    state.data().laid_out.set(None);
+                }
+                Some(Ok(Command::Bookmark)) => {
+                    let data = state.data_mut();
+                    data.status = match add_bookmark(&download_dir, &data.address) {
+                        Ok(path) => {
+                            data.bookmarks.push(data.address.clone());
+                            Status::Message(format!("bookmarked in {}", path.display()))
+                        }
+                        Err(err) => {
+                            error!("Could not bookmark {}: {err}", data.address);
+                            Status::Error(format!("could not bookmark: {err}"))
+                        }
+                    };
+                }
+                Some(Ok(Command::Identity)) => {
+                    let data = state.data_mut();
+                    let url = Url::parse(&data.address).ok();
+                    let host = url.as_ref().and_then(Url::host_str).map(str::to_string);
+                    let error = match (&data.identities, host) {
+                        (Some(identities), Some(host)) => match identities.create(&host) {
+                            Ok(_) => {
+                                info!("Made a new identity for {host}");
+                                None
+                            }
+                            Err(err) => {
+                                error!("Could not make an identity for {host}: {err}");
+                                Some(format!("could not make an identity: {err}"))
+                            }
+                        },
+                        (None, _) => Some("nowhere to keep identities".to_string()),
+                        (_, None) => Some("this page has no host".to_string()),
+                    };
+                    match error {
+                        Some(error) => data.status = Status::Error(error),
+                        // The page is asked for again, this time with the identity.
+                        None => {
+                            let address = data.address.clone();
+                            navigate(data, &address, Origin::Typed);
+                        }
+                    }
+                }
+                Some(Err(err)) => state.data_mut().status = Status::Error(err),
+                None => {}
+            }
+
+            // Carry out what the elements asked for.
+            for action in state.take_actions() {
+                match action {
+                    PanelAction::Navigate(address) => {
+                        navigate(state.data_mut(), &address, Origin::Page)
+                    }
+                    PanelAction::Reload => {
+                        let data = state.data_mut();
+                        let address = data.address.clone();
+                        navigate(data, &address, Origin::Typed);
+                    }
+                    PanelAction::SetMode(name) => match name.parse() {
+                        Ok(mode) => state.data_mut().mode = mode,
+                        Err(err) => error!("An element asked for an {err}"),
+                    },
+                    PanelAction::Quit => {
+                        info!("Quit. Bye :)");
+                        save_session(session_path.as_deref(), state.data());
+                        control_flow.set_exit();
+                        return;
+                    }
+                }
+                changed = true;
+            }
+
+            // Close events.
+            if input.close_requested() {
+                info!("Close requested. Bye :)");
+                save_session(session_path.as_deref(), state.data());
+                control_flow.set_exit();
+                return;
+            }
+
+            // Resize the window.
+            if let Some(size) = input.window_resized() {
+                debug!("Resize request {size:?}");
+                let (ps, ls) = zoomed_size(size, state.data().zoom);
+                // Until the resizing settles, the old layout is stretched over the new surface.
+                pixels.resize_surface(ps.width, ps.height).unwrap();
+                window.set_inner_size(ps);
+                resize.push(ls, Instant::now());
+                window.request_redraw();
+            }
+
+            // Lay out the content for the new size once the resizing has settled.
+            if let Some(ls) = resize.take_ready(Instant::now()) {
+                let container = scroll_container(&state.elements);
+                // The outline is not the page, so there is nothing to keep in view.
+                if state.data().mode != Mode::Outline {
+                    let anchor = reading_anchor(container, state.data().scroll_pos);
+                    state.data_mut().reading_anchor = anchor;
+                }
+                pixels.resize_buffer(ls.width, ls.height).unwrap();
+                state.resize(ls.width, ls.height);
+                state.data_mut().width = ls.width;
+                state.data_mut().height = ls.height;
+                changed = true;
+            }
+
+            if changed {
+                state.mark_dirty();
+                window.request_redraw();
+            }
+
+            // Sleep until the next event, unless something needs to be looked at before then.
+            let data = state.data();
+            let waiting = data.fetch.is_some()
+                || !data.favicon_fetches.is_empty()
+                || data.upload_response.is_some();
+            let fetch_poll = waiting.then(|| Instant::now() + FETCH_POLL_INTERVAL);
+            let deadlines = resize.deadline().into_iter().chain(fetch_poll);
+            match deadlines.chain(state.next_redraw()).min() {
+                Some(deadline) => control_flow.set_wait_until(deadline),
+                None => control_flow.set_wait(),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use gemininini::source::MockSource;
+    use gemininini::{Pixel, PIXEL_SIZE};
+
+    use super::*;
+
+    const BLACK: Pixel = [0x00, 0x00, 0x00, 0xff];
+    const WHITE: Pixel = [0xff; PIXEL_SIZE];
+
+    /// Returns a [`Panel`] showing the `text`, set up as it is on startup but without a margin.
+    fn panel(text: &str) -> Panel<Data> {
+        panel_with_margin(text, Padding::default())
+    }
+
+    /// Returns a [`Panel`] showing the `text` inside the `margin`.
+    fn panel_with_margin(text: &str, margin: Padding) -> Panel<Data> {
+        let font = Rc::new(font::fallback());
+        let data = Data {
+            text: text.to_string(),
+            scroll_pos: 0,
+            max_scroll: 0,
+            page_step: 0,
+            address: String::new(),
+            upload: String::new(),
+            mode: Mode::Normal,
+            visited: HashSet::new(),
+            wheel_remainder: 0.0,
+            count: Count::default(),
+            status: Status::Idle,
+            fetch: None,
+            upload_response: None,
+            meta: String::new(),
+            lang: None,
+            rtl: false,
+            laid_out: Cell::new(None),
+            margin,
+            link_color: BLACK,
+            heading_color: BLACK,
+            two_columns: false,
+            line_numbers: false,
+            command: String::new(),
+            hscroll: 0,
+            max_hscroll: Cell::new(0),
+            tabs: Tabs::new(Tab::default()),
+            link_filter: String::new(),
+            restore_scroll: None,
+            words_per_minute: 200,
+            align_tables: false,
+            bookmarks: Vec::new(),
+            identities: None,
+            private: false,
+            favicons: false,
+            favicon_cache: HashMap::new(),
+            favicon_fetches: Vec::new(),
+            source: Arc::new(MockSource::new()),
+            reading_anchor: None,
+            outline_selected: 0,
+            size_limit: SizeLimit {
+                max_size: usize::MAX,
+                truncate: false,
+            },
+            added_color: BLACK,
+            removed_color: BLACK,
+            comment_color: BLACK,
+            tint_code: false,
+            link_hints: false,
+            max_content_width: 0,
+            error_color: BLACK,
+            failure: None,
+            linkify: false,
+            zoom: 1,
+            source_view: false,
+            prompt: None,
+            answer: String::new(),
+            retry_policy: RetryPolicy {
+                retries: 0,
+                backoff: Duration::ZERO,
+            },
+            outline_sections: false,
+            width: 0,
+            height: 0,
+        };
+        let style = Style::new(BLACK, WHITE, font);
+        Panel::new(setup_elements(style, data.margin), BLACK, WHITE, data)
+    }
+
+    /// Lay out and draw the `panel` at a size of `width` by `height` pixels, and return them.
+    fn draw(panel: &mut Panel<Data>, width: u32, height: u32) -> Vec<Pixel> {
+        panel.resize(width, height);
+        panel.data_mut().width = width;
+        panel.data_mut().height = height;
+        panel.update();
+        let mut pixels = vec![0; width as usize * height as usize * PIXEL_SIZE];
+        panel.draw(&mut pixels);
+        pixels
+            .chunks_exact(PIXEL_SIZE)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn page_step_leaves_a_line_of_overlap() {
+        let text: String = (0..200).map(|n| format!("line {n}\n")).collect();
+        let mut panel = panel(&text);
+        for height in [480, 240] {
+            draw(&mut panel, 640, height);
+            let container = scroll_container(&panel.elements);
+            let line_height = container.style.line_height() as usize;
+            let lines = container.visible_line_count();
+            // The address and mode lines take up the rest of the window.
+            assert_eq!(lines, height as usize / line_height - 2);
+            assert_eq!(page_step(container), (lines - 1) * line_height);
+        }
+    }
+
+    const PAGE: &str = "gemini://example.org/dir/page.gmi";
+
+    #[test]
+    fn visited_links_are_told_apart() {
+        let visited = HashSet::from(["gemini://example.org/dir/seen.gmi".to_string()]);
+        assert_eq!(link_state(PAGE, "seen.gmi", &visited), LinkState::Visited);
+        assert_eq!(
+            link_state(PAGE, "unseen.gmi", &visited),
+            LinkState::Unvisited
+        );
+        let absolute = "gemini://example.org/dir/seen.gmi";
+        assert_eq!(link_state(PAGE, absolute, &visited), LinkState::Visited);
+    }
+
+    #[test]
+    fn links_to_the_page_itself_are_current() {
+        let visited = HashSet::new();
+        assert_eq!(link_state(PAGE, "page.gmi", &visited), LinkState::Current);
+        assert_eq!(link_state(PAGE, "/", &visited), LinkState::Unvisited);
+    }
+
+    #[test]
+    fn clicked_point_is_mapped_to_the_line_under_it() {
+        let text: String = (0..100).map(|n| format!("=> /{n} Link {n}\n")).collect();
+        let mut panel = panel(&text);
+        draw(&mut panel, 640, 480);
+        let container = scroll_container(&panel.elements);
+        let origin = container.bounds();
+        let line_height = container.style.line_height();
+        let x = origin.x as u32 + 10;
+        let y_of = |line: u32| origin.y as u32 + line * line_height + line_height / 2;
+        assert_eq!(line_at(&panel.elements, x, y_of(0)), Some(0));
+        assert_eq!(line_at(&panel.elements, x, y_of(5)), Some(5));
+        // Above the page, there is no line.
+        assert_eq!(line_at(&panel.elements, x, origin.y as u32 - 1), None);
+
+        // Scrolled down by three lines, the same point is over another line.
+        panel.data_mut().scroll_pos = 3 * line_height as usize;
+        draw(&mut panel, 640, 480);
+        assert_eq!(line_at(&panel.elements, x, y_of(5)), Some(8));
+    }
+
+    #[test]
+    fn clicked_point_in_the_right_column_is_mapped_past_the_left_one() {
+        let mut panel = panel(&(0..20).map(|n| format!("line {n}\n")).collect::<String>());
+        panel.data_mut().two_columns = true;
+        draw(&mut panel, 2000, 480);
+        let container = scroll_container(&panel.elements);
+        let origin = container.bounds();
+        let y = origin.y as u32 + container.style.line_height() / 2;
+        let right = origin.x as u32 + container.fill_size().width - 10;
+        assert_eq!(line_at(&panel.elements, origin.x as u32 + 10, y), Some(0));
+        assert_eq!(line_at(&panel.elements, right, y), Some(10));
+    }
+
+    #[test]
+    fn fine_wheel_turns_add_up() {
+        let mut panel = panel("");
+        let data = panel.data_mut();
+        // Turning the wheel towards oneself scrolls down, unless scrolling is natural.
+        assert_eq!(data.wheel_pixels(-1.0, 20, false), 20);
+        assert_eq!(data.wheel_pixels(-1.0, 20, true), -20);
+        let pixels: Vec<_> = (0..8).map(|_| data.wheel_pixels(-0.01, 20, false)).collect();
+        assert_eq!(pixels.iter().sum::<i64>(), 1);
+        assert_eq!(pixels[..4], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn status_line_shows_every_status_after_the_mode() {
+        assert_eq!(Status::Idle.render(&Mode::Normal), "normal");
+        let loading = Status::Loading {
+            bytes: 2048,
+            ticks: 5,
+        };
+        assert_eq!(loading.render(&Mode::Link), "link | / loading, 2048 bytes");
+        let message = Status::Message("saved".to_string());
+        assert_eq!(message.render(&Mode::Normal), "normal | saved");
+        let error = Status::Error("not found".to_string());
+        assert_eq!(error.render(&Mode::Insert), "insert | error: not found");
+    }
+
+    #[test]
+    fn spinner_turns_with_every_report() {
+        let spinners: String = (0..5)
+            .map(|ticks| Status::Loading { bytes: 0, ticks }.render(&Mode::Normal))
+            .map(|line| line.chars().nth("normal | ".len()).unwrap())
+            .collect();
+        assert_eq!(spinners, "|/-\\|");
+    }
+
+    #[test]
+    fn everything_is_drawn_inside_the_margin() {
+        let show = |size, width, height| {
+            let margin = Padding {
+                top: size,
+                right: size,
+                bottom: size,
+                left: size,
+            };
+            let mut panel = panel_with_margin("# Hello\nworld", margin);
+            panel.data_mut().address = "gemini://example.org/".to_string();
+            draw(&mut panel, width, height)
+        };
+        let (width, height) = (640, 480);
+        let flush = show(0, width - 10, height - 10);
+        let inset = show(5, width, height);
+        assert!(flush.contains(&BLACK));
+        for (idx, pixel) in inset.iter().enumerate() {
+            let (x, y) = ((idx as u32 % width), (idx as u32 / width));
+            let inside = (5..width - 5).contains(&x) && (5..height - 5).contains(&y);
+            let expected = match inside {
+                true => flush[((y - 5) * (width - 10) + x - 5) as usize],
+                false => WHITE,
+            };
+            assert_eq!(*pixel, expected, "at ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn link_and_heading_lines_are_drawn_in_their_colors() {
+        const RED: Pixel = [0xff, 0x00, 0x00, 0xff];
+        const BLUE: Pixel = [0x00, 0x00, 0xff, 0xff];
+        let show = |link_color, heading_color| {
+            let mut panel = panel("# Hello\n=> gemini://example.org/ A link");
+            let data = panel.data_mut();
+            (data.link_color, data.heading_color) = (link_color, heading_color);
+            draw(&mut panel, 640, 480)
+        };
+        let pixels = show(RED, BLUE);
+        assert!(pixels.contains(&RED) && pixels.contains(&BLUE));
+        // Without colors of their own, they are drawn in the foreground.
+        let pixels = show(BLACK, BLACK);
+        assert!(!pixels.contains(&RED) && !pixels.contains(&BLUE));
+    }
+
+    #[test]
+    fn long_address_is_drawn_within_the_window() {
+        let (width, height) = (640, 480);
+        let margin = 2;
+        let sides = Padding {
+            top: margin,
+            right: margin,
+            bottom: margin,
+            left: margin,
+        };
+        let mut panel = panel_with_margin("", sides);
+        panel.data_mut().address = format!("gemini://example.org/{}", "long/".repeat(200));
+        let pixels = draw(&mut panel, width, height);
+        let inked_right_of_the_margin = pixels
+            .chunks_exact(width as usize)
+            .any(|row| row[(width - margin) as usize..].contains(&BLACK));
+        assert!(pixels.contains(&BLACK));
+        assert!(!inked_right_of_the_margin);
+    }
+
+    #[test]
+    fn sideways_scroll_stops_at_the_widest_preformatted_line() {
+        assert_eq!(max_hscroll(100, 300), 0);
+        assert_eq!(max_hscroll(300, 300), 0);
+        assert_eq!(max_hscroll(450, 300), 150);
+
+        // The lines are as wide as the window inside its margin.
+        let (wide, narrow) = ("x".repeat(300), "x".repeat(200));
+        let long_paragraph = "y".repeat(400);
+        let mut panel = panel(&format!("```\n{narrow}\n{wide}\n```\n{long_paragraph}"));
+        draw(&mut panel, 640, 480);
+        let data = panel.data();
+        let widest = font::fallback().determine_width(&wide) as u32;
+        assert_eq!(data.max_hscroll.get(), widest - data.inner_width());
+    }
+
+    #[test]
+    fn blank_page_shows_a_placeholder() {
+        let mut panel = panel("");
+        for blank in ["", "\n\n", "  \n\t\n"] {
+            panel.data_mut().text = blank.to_string();
+            draw(&mut panel, 640, 480);
+            assert_eq!(shown_lines(&panel), [EMPTY_PAGE_PLACEHOLDER], "{blank:?}");
+        }
+        panel.data_mut().text = "x".to_string();
+        draw(&mut panel, 640, 480);
+        assert_eq!(shown_lines(&panel), ["x"]);
+        // A page that failed to load is no empty page.
+        panel.data_mut().text = String::new();
+        panel.data_mut().status = Status::Error("not found".to_string());
+        draw(&mut panel, 640, 480);
+        assert!(!shown_lines(&panel).contains(&EMPTY_PAGE_PLACEHOLDER.to_string()));
+    }
+
+    #[test]
+    fn session_is_not_saved_while_browsing_privately() {
+        let name = format!("gemininini-private-{}-session", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("session");
+        let mut panel = panel("");
+        let data = panel.data_mut();
+        data.private = true;
+        data.address = "gemini://example.org/secret.gmi".to_string();
+        save_session(Some(&path), data);
+        assert!(!path.exists());
+        data.private = false;
+        save_session(Some(&path), data);
+        let saved = session::load(&path).unwrap().unwrap();
+        assert_eq!(saved.url, "gemini://example.org/secret.gmi");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn favicon_is_asked_for_once_per_host() {
+        let mut on = panel("");
+        let data = on.data_mut();
+        data.favicons = true;
+        request_favicon(data, "gemini://example.org/one.gmi");
+        request_favicon(data, "gemini://EXAMPLE.org/two.gmi");
+        request_favicon(data, "gemini://other.org/");
+        request_favicon(data, "https://example.net/");
+        let hosts: Vec<_> = data.favicon_fetches.iter().map(|(host, _)| host).collect();
+        assert_eq!(hosts, ["example.org", "other.org"]);
+
+        // Favicons are not asked for unless they are turned on.
+        let mut off = panel("");
+        let data = off.data_mut();
+        request_favicon(data, "gemini://example.org/");
+        assert!(data.favicon_fetches.is_empty() && data.favicon_cache.is_empty());
+    }
+
+    #[test]
+    fn link_mode_previews_where_enter_goes() {
+        let target = "gemini://example.org/docs/next.gmi";
+        let source = MockSource::new().with_page(target, 20, "text/gemini", "# Next");
+        let mut panel = panel("");
+        let data = panel.data_mut();
+        data.source = Arc::new(source);
+        data.address = "gemini://example.org/docs/index.gmi".to_string();
+        data.text = "=> ../up.gmi Up\n=> next.gmi Next\n".to_string();
+        data.mode = Mode::Link;
+        data.link_filter = "nex".to_string();
+        let preview = data.link_target().unwrap();
+        assert_eq!(preview, target);
+
+        navigate(data, &preview, Origin::Page);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while panel.data().fetch.is_some() && Instant::now() < deadline {
+            poll_fetch(&mut panel);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(panel.data().address, preview);
+        assert_eq!(panel.data().text, "# Next");
+    }
+
+    /// Returns the texts of the lines in the scroll container of the `panel`, as of the last
+    /// update.
+    fn shown_lines(panel: &Panel<Data>) -> Vec<String> {
+        fn text_of(element: &Element<Data>) -> String {
+            match &element.content {
+                Content::Text(text, _) => text.clone(),
+                Content::Paragraph(wrapped, _) => wrapped.lines().collect::<Vec<_>>().join(" "),
+                Content::Row(children) | Content::Stack(children) => {
+                    children.iter().map(text_of).collect::<Vec<_>>().join(" ")
+                }
+                _ => String::new(),
+            }
+        }
+        let Content::Stack(lines) = &scroll_container(&panel.elements).content else {
+            unreachable!()
+        };
+        lines.iter().map(text_of).collect()
+    }
+
+    #[test]
+    fn resizing_keeps_the_same_text_at_the_top() {
+        let mut panel = panel("");
+        let paragraph = (0..400).map(|n| format!("word{n}")).collect::<Vec<_>>().join(" ");
+        panel.data_mut().text = format!("# Start\n{paragraph}\n\n{paragraph}");
+        draw(&mut panel, 640, 480);
+        let container = scroll_container(&panel.elements);
+        let line_height = container.style.line_height() as usize;
+        let (idx, offset) = reading_anchor(container, 12 * line_height).unwrap();
+        assert!(offset > 0);
+
+        for width in [400, 900] {
+            draw(&mut panel, width, 480);
+            let container = scroll_container(&panel.elements);
+            let scroll_pos = anchor_position(container, (idx, offset)).unwrap();
+            // The top line is the one that the text at the anchor was wrapped into.
+            let (top_idx, top_offset) = reading_anchor(container, scroll_pos).unwrap();
+            let wrapped = line_paragraph(laid_out_lines(container)[idx].1).unwrap();
+            assert_eq!(top_idx, idx);
+            assert_eq!(top_offset, wrapped.line_start(wrapped.line_of(offset)));
+        }
+    }
+
+    #[test]
+    fn outline_entries_lead_to_their_headings() {
+        let filler = "text\n".repeat(40);
+        let mut panel = panel(&format!("# One\n{filler}## Two\n{filler}### Three\n"));
+        draw(&mut panel, 640, 480);
+        let lines: Vec<_> = gemtext::parse(&panel.data().text).map(|(_, line)| line).collect();
+        let entries = outline(&lines);
+        let headings: Vec<_> = entries.iter().map(|(_, text, _)| text.as_str()).collect();
+        assert_eq!(headings, ["One", "Two", "Three"]);
+        let lines = shown_lines(&panel);
+        let container = scroll_container(&panel.elements);
+        for (_, text, line) in &entries {
+            assert!(lines[*line].ends_with(text.as_str()), "{:?}", lines[*line]);
+            // Picking the heading brings its line to the top.
+            let scroll_pos = anchor_position(container, (*line, 0)).unwrap();
+            assert_eq!(reading_anchor(container, scroll_pos), Some((*line, 0)));
+        }
+
+        panel.data_mut().text = "no headings here".to_string();
+        panel.data_mut().mode = Mode::Outline;
+        draw(&mut panel, 640, 480);
+        assert_eq!(shown_lines(&panel), [NO_HEADINGS_PLACEHOLDER]);
+    }
+
+    #[test]
+    fn plain_text_is_shown_as_it_is() {
+        let text = "=> gemini://example.org/ Not a link\n# Not a heading\n* Not an item";
+        let mut panel = panel(text);
+        panel.data_mut().link_color = [0x00, 0x00, 0xff, 0xff];
+        let first_color = |panel: &Panel<Data>| {
+            let Content::Stack(lines) = &scroll_container(&panel.elements).content else {
+                unreachable!()
+            };
+            lines[0].style.foreground
+        };
+        panel.data_mut().meta = "text/plain; charset=utf-8".to_string();
+        draw(&mut panel, 640, 480);
+        assert_eq!(shown_lines(&panel), text.lines().collect::<Vec<_>>());
+        assert_ne!(first_color(&panel), panel.data().link_color);
+        assert_eq!(gemtext::links_as(text, false, false).count(), 0);
+
+        // The same text as gemtext has a link in it.
+        panel.data_mut().meta = "text/gemini".to_string();
+        draw(&mut panel, 640, 480);
+        assert_eq!(first_color(&panel), panel.data().link_color);
+        assert_eq!(gemtext::links_as(text, true, false).count(), 1);
+    }
+
+    #[test]
+    fn lines_are_no_wider_than_the_cap_in_a_huge_window() {
+        let paragraph = (0..300).map(|n| format!("w{n}")).collect::<Vec<_>>().join(" ");
+        let mut panel = panel(&format!("{paragraph}\n\n{paragraph}"));
+        panel.data_mut().max_content_width = 40;
+        draw(&mut panel, 4000, 480);
+        let font = font::fallback();
+        let cap = 40 * font.determine_width(MEASURING_CHARACTER) as u32;
+        let container = scroll_container(&panel.elements);
+        let wrapped: Vec<_> = laid_out_lines(container)
+            .into_iter()
+            .filter_map(|(_, line)| line_paragraph(line))
+            .flat_map(|wrapped| wrapped.lines())
+            .collect();
+        assert!(wrapped.len() > 2);
+        for line in wrapped {
+            assert!(font.determine_width(line) as u32 <= cap, "{line:?}");
+        }
+
+        // A cap too large to count in pixels is no cap at all, rather than an overflow.
+        panel.data_mut().max_content_width = u32::MAX;
+        panel.data().laid_out.set(None);
+        draw(&mut panel, 4000, 480);
+        let container = scroll_container(&panel.elements);
+        let widest = laid_out_lines(container)
+            .into_iter()
+            .filter_map(|(_, line)| line_paragraph(line))
+            .flat_map(|wrapped| wrapped.lines())
+            .map(|line| font.determine_width(line) as u32)
+            .max();
+        assert!(widest > Some(cap));
+    }
+
+    #[test]
+    fn failed_page_shows_its_status_and_what_it_means() {
+        let address = "gemini://example.org/gone";
+        let mut panel = panel("");
+        let data = panel.data_mut();
+        data.source = Arc::new(MockSource::new().with_page(address, 51, "Not found", "ignored"));
+        navigate(data, address, Origin::Typed);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while panel.data().fetch.is_some() && Instant::now() < deadline {
+            poll_fetch(&mut panel);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        draw(&mut panel, 640, 480);
+        assert_eq!(shown_lines(&panel), ["51 Not found", explain_status(51)]);
+        assert!(panel.data().text.is_empty());
+    }
+
+    #[test]
+    fn copied_address_is_normalized_and_failures_are_shown() {
+        let mut panel = panel("");
+        let data = panel.data_mut();
+        data.address = "gemini://Example.org:1965/a/../page.gmi#top".to_string();
+        let mut copied = None;
+        copy_address(data, |address| {
+            copied = Some(address.to_string());
+            Ok(())
+        });
+        let address = "gemini://example.org/page.gmi";
+        assert_eq!(copied.as_deref(), Some(address));
+        assert_eq!(data.status, Status::Message(format!("copied {address}")));
+
+        copy_address(data, |_| Err(anyhow::anyhow!("no clipboard")));
+        let error = Status::Error("could not copy: no clipboard".to_string());
+        assert_eq!(data.status, error);
+    }
+
+    #[test]
+    fn upload_response_is_shown_in_the_status_line() {
+        let mut panel = panel("");
+        let data = panel.data_mut();
+        assert!(!poll_upload(data));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        data.upload_response = Some(receiver);
+        assert!(!poll_upload(data));
+        sender.send(Ok("20 text/gemini".to_string())).unwrap();
+        assert!(poll_upload(data));
+        assert_eq!(data.status, Status::Message("uploaded: 20 text/gemini".to_string()));
+        assert!(data.upload_response.is_none());
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        data.upload_response = Some(receiver);
+        sender.send(Err(anyhow::anyhow!("no response"))).unwrap();
+        assert!(poll_upload(data));
+        assert_eq!(data.status, Status::Error("upload failed: no response".to_string()));
+    }
+
+    #[test]
+    fn source_view_shows_the_same_text_as_it_is() {
+        let mut panel = panel("");
+        let text = "# Title\n=> gemini://example.org/ Link";
+        panel.data_mut().text = text.to_string();
+        draw(&mut panel, 640, 480);
+        let rendered = shown_lines(&panel);
+        panel.data_mut().scroll_pos = 10;
+
+        toggle_source_view(panel.data_mut());
+        draw(&mut panel, 640, 480);
+        assert_eq!(shown_lines(&panel), text.lines().collect::<Vec<_>>());
+        let data = panel.data();
+        assert_eq!((data.text.as_str(), data.scroll_pos), (text, 0));
+        assert_eq!(data.status, Status::Message("showing the source".to_string()));
+
+        toggle_source_view(panel.data_mut());
+        draw(&mut panel, 640, 480);
+        assert_eq!(shown_lines(&panel), rendered);
+        assert_eq!(panel.data().text, text);
+    }
+
+    #[test]
+    fn section_picker_lists_headings_by_level_and_other_sections_at_level_0() {
+        let text = "# Title\nintro\n\nbody\n### Deep";
+        let entries = outline_entries(text, true, true);
+        let expected = [(1, "Title", 0), (0, "body", 3), (3, "Deep", 4)];
+        let expected = expected.map(|(level, label, line)| (level, label.to_string(), line));
+        assert_eq!(entries, expected);
+        assert_eq!(outline_entries(text, true, false).len(), 2);
+        // A page that is not gemtext has no headings, but it has paragraphs.
+        assert_eq!(outline_entries(text, false, true), [(0, "body".to_string(), 3)]);
+    }
 }
@@ -0,0 +1,63 @@
+//! Estimating how long a page takes to read.
+
+use std::time::Duration;
+
+use gemininini::gemtext::{self, Line};
+
+/// Returns the number of words in a gemtext document, and about how long reading them takes at
+/// `words_per_minute`.
+///
+/// Only prose counts: text, headings, and link labels. Preformatted blocks and the addresses of
+/// links are left out, as are marks such as the `*` of a list item, which are not words.
+pub fn reading_stats(text: &str, words_per_minute: u32) -> (usize, Duration) {
+    let words = gemtext::parse(text)
+        .map(|(_, line)| match line {
+            Line::Text(text) | Line::Heading { text, .. } => count_words(text),
+            Line::Link {
+                label: Some(label), ..
+            } => count_words(label),
+            _ => 0,
+        })
+        .sum();
+    let seconds = match words_per_minute {
+        0 => 0.0,
+        wpm => words as f64 * 60.0 / wpm as f64,
+    };
+    (words, Duration::from_secs_f64(seconds))
+}
+
+/// Returns the number of words in `text`, counting only those with a letter or digit in them.
+fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_prose_is_counted() {
+        let page = "# A title\n\
+                    Some words here, and 2 more.\n\
+                    * a list item\n\
+                    => gemini://example.org/ A link\n\
+                    => gemini://example.org/unlabeled\n\
+                    ```\n\
+                    let code = not_words();\n\
+                    ```\n\
+                    - - -";
+        let (words, _) = reading_stats(page, 200);
+        assert_eq!(words, 2 + 6 + 3 + 2);
+    }
+
+    #[test]
+    fn reading_time_follows_the_words_per_minute() {
+        let page = "word ".repeat(300);
+        assert_eq!(reading_stats(&page, 200), (300, Duration::from_secs(90)));
+        assert_eq!(reading_stats(&page, 600), (300, Duration::from_secs(30)));
+        assert_eq!(reading_stats(&page, 0), (300, Duration::ZERO));
+        assert_eq!(reading_stats("", 200), (0, Duration::ZERO));
+    }
+}
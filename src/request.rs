@@ -1,54 +1,1099 @@
-use url::Url;
-use gemini_fetch::Page;
-use anyhow::Result;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use url::{ParseError, Url};
+use gemini_fetch::Header;
+use anyhow::{anyhow, bail, Result};
+use gemininini::gemtext::normalize_line_endings;
+use gemininini::source::{FetchOutcome, PageSource, STATUS_SUCCESS};
+use log::{debug, warn};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rustls::{
+    Certificate, ClientConfig, PrivateKey, RootCertStore, ServerCertVerified, ServerCertVerifier,
+    TLSError,
+};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
 use tokio::runtime::Runtime;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use webpki::DNSNameRef;
+
+use crate::identity::{Identities, Identity};
+use crate::local::{load_local, Origin, STDIN_ADDRESS};
+use crate::retry::{is_transient, RetryPolicy};
+
+const DATA_SCHEME: &str = "data:";
+const GEMINI_SCHEME: &str = "gemini";
+const TITAN_SCHEME: &str = "titan";
+/// The ports of the schemes that have a well-known one, which are connected to unless a URL names
+/// another.
+const DEFAULT_PORTS: &[(&str, u16)] = &[
+    (GEMINI_SCHEME, 1965),
+    // Titan servers listen on the same port as Gemini servers by default.
+    (TITAN_SCHEME, 1965),
+    ("gopher", 70),
+    ("finger", 79),
+];
+/// The media type assumed for a `data:` URL that does not specify one (RFC 2397).
+const DATA_DEFAULT_MIME: &str = "text/plain;charset=US-ASCII";
+/// The media type of a successful response with an empty meta.
+const GEMINI_DEFAULT_MIME: &str = "text/gemini; charset=utf-8";
+/// The primary language subtags of languages that are written from right to left.
+const RTL_LANGUAGES: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "iw", "ks", "ps", "sd", "ug", "ur", "yi",
+];
+/// How many bytes of a response body are read at a time.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+/// How many redirects in a row are followed before giving up, as the specification recommends.
+const MAX_REDIRECTS: usize = 5;
+/// What is put at the end of a page that was cut off for being too large.
+const TRUNCATED_MARKER: &str = "\n(truncated)";
+/// The characters that are percent-encoded in the answer to an input prompt, which is all but the
+/// unreserved ones, such that none of the answer can be mistaken for a part of the URL.
+const INPUT_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// The runtime that all requests are made on, which is started when the first one is made.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Returns the runtime that requests are made on, starting it if this is the first request.
+fn runtime() -> Result<&'static Runtime> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime =
+        Runtime::new().map_err(|err| anyhow!("could not start the async runtime: {}", err))?;
+    debug!("Started the async runtime");
+    // Another request may have started one in the meantime, in which case that one is kept and
+    // this one is dropped.
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// What a fetch worker reports while it loads a page.
+#[derive(Debug)]
+pub enum FetchEvent {
+    /// Part of the body has arrived, `bytes` in total so far.
+    Progress { bytes: usize },
+    /// The fetch is over, one way or another. This is the last event.
+    Done(Result<FetchOutcome>),
+}
+
+/// How large the body of a page may be, and what becomes of one that is larger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimit {
+    /// The largest body in bytes that is taken in.
+    pub max_size: usize,
+    /// Whether a larger body is cut off at `max_size` and marked as such, rather than failing the
+    /// request.
+    pub truncate: bool,
+}
+
+impl SizeLimit {
+    /// Hold the body of the `outcome` to this [`SizeLimit`].
+    fn apply(&self, mut outcome: FetchOutcome) -> Result<FetchOutcome> {
+        if outcome.body.len() <= self.max_size {
+            return Ok(outcome);
+        }
+        if !self.truncate {
+            bail!("the page is larger than {} bytes", self.max_size)
+        }
+        // A character that does not fit as a whole is left out entirely.
+        let mut end = self.max_size;
+        while !outcome.body.is_char_boundary(end) {
+            end -= 1;
+        }
+        outcome.body.truncate(end);
+        outcome.body.push_str(TRUNCATED_MARKER);
+        Ok(outcome)
+    }
+}
+
+/// A media type as found in a `data:` URL or a response header, such as
+/// `text/gemini; charset=utf-8`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mime {
+    /// The top-level type, e.g. `text`.
+    pub kind: String,
+    /// The subtype, e.g. `gemini`.
+    pub subtype: String,
+    /// The `key=value` parameters following the type, in order of appearance.
+    pub params: Vec<(String, String)>,
+}
+
+impl Mime {
+    /// Parse a media type from its `type/subtype;key=value` representation.
+    ///
+    /// The type and subtype are lowercased, parameter values are kept as they are.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parts = s.split(';').map(str::trim);
+        // The first item of a split always exists, even if it is empty.
+        let essence = parts.next().unwrap();
+        let Some((kind, subtype)) = essence.split_once('/') else {
+            bail!("media type {:?} is missing a '/'", essence)
+        };
+        if kind.is_empty() || subtype.is_empty() {
+            bail!("media type {:?} has an empty type or subtype", essence)
+        }
+        let params = parts
+            .filter(|param| !param.is_empty())
+            .map(|param| match param.split_once('=') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim().to_string()),
+                None => (param.to_lowercase(), String::new()),
+            })
+            .collect();
+        Ok(Self {
+            kind: kind.to_lowercase(),
+            subtype: subtype.to_lowercase(),
+            params,
+        })
+    }
+
+    /// Returns whether this [`Mime`] is `text/gemini`.
+    pub fn is_gemtext(&self) -> bool {
+        self.kind == "text" && self.subtype == "gemini"
+    }
 
-async fn get_gemini_page(address: &Url) -> Result<String> {
-    match Page::fetch(address, None).await {
-        Ok(page) => {
-            // Handle the fetched Gemini page
-            println!("URL: {}", page.url);
-            println!("Status: {:?}", page.header.status);
-            println!("Meta: {}", page.header.meta);
-            if let Some(body) = page.body {
-                Ok(body)
-            } else {
-                Ok("No body found in the Gemini page".to_string())
-            }
+    /// Returns whether this [`Mime`] is of the `text` top-level type.
+    pub fn is_text(&self) -> bool {
+        self.kind == "text"
+    }
+}
+
+impl std::fmt::Display for Mime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.kind, self.subtype)?;
+        for (key, value) in &self.params {
+            write!(f, ";{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A language tag, such as `en` or `pt-BR`, as given by the `lang` parameter of a gemtext response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lang(pub String);
+
+impl Lang {
+    /// Returns the primary subtag, such as `pt` for `pt-BR`, lowercased.
+    pub fn primary(&self) -> String {
+        let primary = self.0.split(['-', '_']).next().unwrap_or_default();
+        primary.to_lowercase()
+    }
+
+    /// Returns whether the language is written from right to left.
+    pub fn is_rtl(&self) -> bool {
+        RTL_LANGUAGES.contains(&self.primary().as_str())
+    }
+}
+
+/// Parse the meta of a successful response into its media type and the language of the content.
+///
+/// An empty meta means `text/gemini; charset=utf-8`. The `lang` parameter may list several
+/// languages separated by commas, in which case the first one is taken.
+pub fn parse_meta(meta: &str) -> Result<(Mime, Option<Lang>)> {
+    let mime = match meta.trim() {
+        "" => Mime::parse(GEMINI_DEFAULT_MIME)?,
+        meta => Mime::parse(meta)?,
+    };
+    let lang = mime
+        .params
+        .iter()
+        .find(|(key, _)| key == "lang")
+        .and_then(|(_, value)| value.split(',').map(str::trim).find(|tag| !tag.is_empty()))
+        .map(|tag| Lang(tag.to_string()));
+    Ok((mime, lang))
+}
+
+/// Returns whether a page with the `meta` is gemtext, rather than text that is shown as it is, such
+/// as `text/plain`.
+///
+/// A meta that is not a media type, such as the error message of a failed request, is taken to be
+/// gemtext, which is what the page was shown as before.
+pub fn is_gemtext(meta: &str) -> bool {
+    parse_meta(meta).map_or(true, |(mime, _)| mime.is_gemtext())
+}
+
+/// Decode a `data:` URL into its media type and payload, without touching the network.
+///
+/// Both the base64 (`data:image/png;base64,...`) and the percent-encoded
+/// (`data:text/plain,hello%20there`) variants are supported. When the media type is omitted,
+/// `text/plain;charset=US-ASCII` is assumed.
+pub fn parse_data_url(url: &str) -> Result<(Mime, Vec<u8>)> {
+    let rest = match url.split_once(':') {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("data") => rest,
+        _ => bail!("{:?} is not a data URL", url),
+    };
+    let Some((header, payload)) = rest.split_once(',') else {
+        bail!("data URL is missing the ',' before its payload")
+    };
+
+    let (header, is_base64) = match header.rsplit_once(';') {
+        Some((before, last)) if last.trim().eq_ignore_ascii_case("base64") => (before, true),
+        _ => (header, false),
+    };
+    let mime = match header.trim() {
+        "" => Mime::parse(DATA_DEFAULT_MIME)?,
+        // Only parameters were given, so they apply to the default type.
+        params if params.starts_with(';') => Mime::parse(&format!("text/plain{params}"))?,
+        mediatype => Mime::parse(mediatype)?,
+    };
+
+    let decoded: Vec<u8> = percent_decode_str(payload).collect();
+    let data = if is_base64 {
+        let stripped: Vec<u8> = decoded
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        base64::decode(stripped).map_err(|err| anyhow::anyhow!("invalid base64 payload: {}", err))?
+    } else {
+        decoded
+    };
+
+    Ok((mime, data))
+}
+
+/// Turn a `data:` URL into something that can be displayed, along with its media type.
+///
+/// Text is shown as is, anything else is summarized by its media type and size.
+fn display_data_url(url: &str) -> (String, String) {
+    match parse_data_url(url) {
+        Ok((mime, data)) if mime.is_text() => {
+            (mime.to_string(), String::from_utf8_lossy(&data).into_owned())
         }
+        Ok((mime, data)) => (mime.to_string(), format!("[{mime} data, {} bytes]", data.len())),
         Err(err) => {
-            // Handle errors
-            eprintln!("Error: {}", err);
-            Ok("Error fetching Gemini page".to_string())
+            warn!("Could not decode data URL: {err}");
+            (String::new(), format!("Error decoding data URL: {err}"))
+        }
+    }
+}
+
+/// Returns the well-known port of the `scheme`, if it has one.
+fn default_port(scheme: &str) -> Option<u16> {
+    DEFAULT_PORTS
+        .iter()
+        .find(|(known, _)| scheme.eq_ignore_ascii_case(known))
+        .map(|&(_, port)| port)
+}
+
+/// Returns the port to connect to for `url`, which is the one it names, or else the default port
+/// of its scheme.
+fn port_of(url: &Url) -> Result<u16> {
+    url.port()
+        .or_else(|| default_port(url.scheme()))
+        .ok_or_else(|| anyhow!("no port to connect to for {}", url))
+}
+
+/// Open a TLS connection to the host of `url`, on the port that [`port_of`] picks for it.
+///
+/// The `identity`, if any, is presented to the host as the client certificate.
+async fn connect(url: &Url, identity: Option<&Identity>) -> Result<TlsStream<TcpStream>> {
+    let Some(host) = url.host_str() else {
+        bail!("missing host in URL {}", url)
+    };
+    let port = port_of(url)?;
+
+    let mut config = ClientConfig::new();
+    config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(AcceptAnyCertificate));
+    if let Some(identity) = identity {
+        let cert = Certificate(identity.cert.clone());
+        config.set_single_client_cert(vec![cert], PrivateKey(identity.key.clone()))?;
+    }
+    let dns_name = DNSNameRef::try_from_ascii_str(host)?;
+    let socket = TcpStream::connect((host, port)).await?;
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(dns_name, socket)
+        .await?;
+    Ok(stream)
+}
+
+/// Fetch the page at `address`.
+///
+/// The `progress` is called with the number of body bytes received so far every time more of the
+/// body arrives. No more of the body is read once it is larger than `max_size`, which leaves it to
+/// the caller to tell that it was.
+async fn get_gemini_page(
+    address: &Url,
+    identity: Option<&Identity>,
+    max_size: usize,
+    mut progress: impl FnMut(usize),
+) -> Result<FetchOutcome> {
+    if address.scheme() != GEMINI_SCHEME {
+        bail!("unsupported scheme {:?}, only gemini is supported", address.scheme())
+    }
+    let mut stream = connect(address, identity).await?;
+    stream
+        .write_all(format!("{address}\r\n").as_bytes())
+        .await?;
+    stream.flush().await?;
+
+    let mut stream = BufReader::new(stream);
+    let mut header_line = String::new();
+    stream.read_line(&mut header_line).await?;
+    let header: Header = header_line.parse()?;
+    // The header parsed, so it starts with a valid status.
+    let status = header_line[..2].parse()?;
+    debug!("URL: {address}");
+    debug!("Status: {:?}", header.status);
+    debug!("Meta: {}", header.meta);
+
+    let mut body = Vec::new();
+    let mut chunk = vec![0; READ_CHUNK_SIZE];
+    while body.len() <= max_size {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+        progress(body.len());
+    }
+
+    // An empty body is left as it is, for the page to show that there is nothing to show.
+    Ok(FetchOutcome {
+        address: address.to_string(),
+        status,
+        meta: header.meta,
+        body: String::from_utf8_lossy(&body).replace("\r\n", "\n"),
+    })
+}
+
+/// The [`PageSource`] of the pages out there, on Gemini capsules.
+#[derive(Debug, Clone, Default)]
+pub struct GeminiSource {
+    /// The client certificates to present to the hosts that were given one.
+    pub identities: Option<Identities>,
+    /// How large in bytes a body may get before no more of it is read.
+    pub max_size: usize,
+}
+
+impl PageSource for GeminiSource {
+    fn fetch(&self, url: &Url) -> Result<FetchOutcome> {
+        self.fetch_with_progress(url, &mut |_| {})
+    }
+
+    fn fetch_with_progress(
+        &self,
+        url: &Url,
+        progress: &mut dyn FnMut(usize),
+    ) -> Result<FetchOutcome> {
+        // A host that was given an identity is shown it again on every request.
+        let identity = match (&self.identities, url.host_str()) {
+            (Some(identities), Some(host)) => identities.for_host(host).unwrap_or_else(|err| {
+                warn!("Could not read the identity for {host}: {err}");
+                None
+            }),
+            _ => None,
+        };
+        let page = get_gemini_page(url, identity.as_ref(), self.max_size, progress);
+        runtime()?.block_on(page)
+    }
+}
+
+/// Build the request line that opens a Titan upload, including the trailing `\r\n`.
+///
+/// The `url` may use either the `titan` or the `gemini` scheme, the latter is rewritten to the
+/// former. The `size` must be equal to the length of the `body` that will follow this line.
+pub fn titan_request_line(
+    url: &Url,
+    size: usize,
+    token: Option<&str>,
+    mime: &str,
+    body: &[u8],
+) -> Result<String> {
+    if size != body.len() {
+        bail!("titan size ({}) does not match the payload length ({})", size, body.len())
+    }
+    let is_forbidden = |ch: char| ch == ';' || ch.is_whitespace() || ch.is_control();
+    if mime.is_empty() || mime.contains(is_forbidden) {
+        bail!("invalid titan mime type {:?}", mime)
+    }
+
+    let mut url = url.clone();
+    match url.scheme() {
+        TITAN_SCHEME => {}
+        "gemini" => {
+            url.set_scheme(TITAN_SCHEME)
+                .map_err(|_| anyhow::anyhow!("could not turn {} into a titan URL", url))?;
+        }
+        scheme => bail!("cannot upload over titan to a {:?} URL", scheme),
+    }
+    // Parameters are part of the path, so anything after them would end up in the wrong place.
+    url.set_query(None);
+    url.set_fragment(None);
+
+    let mut line = format!("{url};size={size}");
+    if let Some(token) = token {
+        if token.is_empty() || token.contains(is_forbidden) {
+            bail!("invalid titan token {:?}", token)
         }
+        line.push_str(&format!(";token={token}"));
+    }
+    line.push_str(&format!(";mime={mime}\r\n"));
+    Ok(line)
+}
+
+// TODO: Gemini capsules overwhelmingly use self-signed certificates, so for now we accept any
+// certificate here. This should become trust-on-first-use once we keep track of known hosts.
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        _presented_certs: &[Certificate],
+        _dns_name: DNSNameRef<'_>,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        Ok(ServerCertVerified::assertion())
     }
 }
 
-fn get_gemini_page_blocking(address: &Url) -> Result<String> {
-    Runtime::new().unwrap().block_on(get_gemini_page(address))
+async fn titan_upload(url: &Url, body: &[u8], token: Option<&str>, mime: &str) -> Result<String> {
+    let request = titan_request_line(url, body.len(), token, mime, body)?;
+    let mut stream = connect(url, None).await?;
+
+    stream.write_all(request.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+
+    // The response is a regular Gemini header, and that is all we are interested in.
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).await?;
+    Ok(response.trim_end().to_string())
+}
+
+/// Start uploading `body` to `url` over Titan in the background.
+///
+/// The returned [`Receiver`] gets the response line of the server once it arrives, or why it did
+/// not, which it does not if it takes longer than the `timeout`.
+pub fn spawn_titan_upload(
+    url: &Url,
+    body: &[u8],
+    token: Option<&str>,
+    mime: &str,
+    timeout: Duration,
+) -> Receiver<Result<String>> {
+    let (sender, receiver) = mpsc::channel();
+    let (url, body) = (url.clone(), body.to_vec());
+    let (token, mime) = (token.map(str::to_string), mime.to_string());
+    thread::spawn(move || {
+        let upload = titan_upload(&url, &body, token.as_deref(), &mime);
+        let response = runtime().and_then(|runtime| {
+            // The timer has to be started on the runtime, so it is only started once that runs.
+            runtime
+                .block_on(async { tokio::time::timeout(timeout, upload).await })
+                .unwrap_or_else(|_| bail!("no response within {:?}", timeout))
+        });
+        // Nobody may be waiting for the response anymore, in which case it is dropped.
+        let _ = sender.send(response);
+    });
+    receiver
+}
+
+/// Returns the URL of the page at `base` that asked for input, with the `answer` as its query.
+///
+/// The answer is percent-encoded, spaces and non-ASCII characters included, such that the server
+/// gets it back exactly as it was typed, even if it has characters that mean something in a URL,
+/// such as `&` or `=`. Any query that `base` had is replaced.
+pub fn build_input_url(base: &Url, answer: &str) -> Url {
+    let mut url = base.clone();
+    url.set_query(Some(&utf8_percent_encode(answer, INPUT_ENCODE_SET).to_string()));
+    url
 }
 
-fn handle_address(base_path: &str, address: &str) -> Result<String> {
-    if address.starts_with("gemini://") || address.starts_with("http://") || address.starts_with("https://") {
+/// Returns the normalized absolute URL of `address`, which may be relative to `base_path`.
+///
+/// Relative references such as `?query`, `#fragment`, `./page.gmi`, and `../` are resolved the
+/// way [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-5.2) describes.
+fn handle_address(base_path: &str, address: &str, origin: Origin) -> Result<String> {
+    // Standard input is not somewhere that anything could be relative to. Anywhere but at the
+    // start, `-` is just a page by that name.
+    if address == STDIN_ADDRESS && origin == Origin::Start {
         return Ok(address.to_string());
-    } else {
-        // relative path
-        let absolute_path = resolve_url_path(base_path, address);
-        Ok(absolute_path)
     }
+    let url = match Url::parse(address) {
+        Ok(url) => url,
+        Err(ParseError::RelativeUrlWithoutBase) => resolve_url_path(base_path, address)?,
+        Err(err) => bail!("invalid address {:?}: {}", address, err),
+    };
+    normalize_url(url.as_str())
 }
 
-fn resolve_url_path(base_path: &str, relative_path: &str) -> String {
-    let base_url = Url::parse(base_path).expect("Failed to parse base URL");
-    let resolved_url = base_url.join(relative_path).expect("Failed to resolve URL");
+fn resolve_url_path(base_path: &str, relative_path: &str) -> Result<Url> {
+    let base_url = match Url::parse(base_path) {
+        Ok(base_url) => base_url,
+        Err(err) => bail!("invalid base address {:?}: {}", base_path, err),
+    };
+    match base_url.join(relative_path) {
+        Ok(resolved_url) => Ok(resolved_url),
+        Err(err) => bail!(
+            "could not resolve {:?} against {:?}: {}",
+            relative_path,
+            base_path,
+            err
+        ),
+    }
+}
 
-    resolved_url.into_string()
+/// Returns `address` in a normal form, such that two addresses of the same page compare equal.
+///
+/// The host is lowercased, the default port is left out, an empty path becomes `/`, and dot
+/// segments are removed. The fragment is dropped too, since it is not sent along when fetching.
+pub fn normalize_url(address: &str) -> Result<String> {
+    let mut url = Url::parse(address)?;
+    // URLs such as `data:` ones have no host or path to normalize.
+    if url.cannot_be_a_base() {
+        return Ok(url.to_string());
+    }
+    if let Some(host) = url.host_str() {
+        let host = host.to_lowercase();
+        url.set_host(Some(&host))?;
+    }
+    if url.port().is_some() && url.port() == default_port(url.scheme()) {
+        // Only a URL without a host cannot have its port changed.
+        let _ = url.set_port(None);
+    }
+    // Parsing a URL already removes its dot segments, except from an empty path.
+    if url.path().is_empty() {
+        url.set_path("/");
+    }
+    url.set_fragment(None);
+    Ok(url.to_string())
+}
+
+/// Resolve a `link` on the page at `base` to an absolute URL.
+///
+/// The resulting URL is normalized, such that two links to the same page compare equal.
+pub fn resolve_link(base: &str, link: &str) -> Option<String> {
+    resolve_address(base, link, Origin::Page)
+}
+
+/// Resolve an `address` that came from the `origin` to an absolute URL, like [`resolve_link`]
+/// does, except that the [`Origin::Start`] address may be `-` for standard input.
+pub fn resolve_address(base: &str, address: &str, origin: Origin) -> Option<String> {
+    handle_address(base, address, origin).ok()
+}
+
+/// Load the page at `address`, which may be relative to `base_path`, from the `source`.
+///
+/// The address may only be a file or standard input as far as its `origin` allows, and what it
+/// redirects to may be neither. Redirects are followed, up to [`MAX_REDIRECTS`] of them in a row.
+/// The `progress` is called with the number of body bytes received so far every time more of the
+/// body arrives. A body that is larger than the `limit` allows is cut off or fails the fetch, as
+/// the `limit` has it. A request that fails transiently is tried again as the `retry` policy has
+/// it.
+///
+/// Whatever line endings the body came with, they are all newlines in the page that is returned.
+pub fn fetch_page(
+    source: &dyn PageSource,
+    address: &str,
+    base_path: &str,
+    origin: Origin,
+    limit: SizeLimit,
+    retry: RetryPolicy,
+    progress: &mut dyn FnMut(usize),
+) -> Result<FetchOutcome> {
+    let page = fetch_page_as_sent(source, address, base_path, origin, limit, retry, progress)?;
+    Ok(FetchOutcome {
+        body: normalize_line_endings(page.body),
+        ..page
+    })
+}
+
+/// Load the page at `address` like [`fetch_page`] does, but with its body as it was sent.
+fn fetch_page_as_sent(
+    source: &dyn PageSource,
+    address: &str,
+    base_path: &str,
+    mut origin: Origin,
+    limit: SizeLimit,
+    retry: RetryPolicy,
+    progress: &mut dyn FnMut(usize),
+) -> Result<FetchOutcome> {
+    let mut address = handle_address(base_path, address, origin)?;
+    for _ in 0..=MAX_REDIRECTS {
+        // Data URLs carry their own content, so there is nothing to fetch.
+        if address.starts_with(DATA_SCHEME) {
+            let (meta, body) = display_data_url(&address);
+            return limit.apply(FetchOutcome {
+                address,
+                status: STATUS_SUCCESS,
+                meta,
+                body,
+            });
+        }
+        // Neither do files and standard input, which are right here.
+        if let Some(local) = load_local(&address, origin, limit.max_size) {
+            let (meta, body) = local?;
+            return limit.apply(FetchOutcome {
+                address,
+                status: STATUS_SUCCESS,
+                meta,
+                body,
+            });
+        }
+        let outcome = fetch_with_retries(source, &Url::parse(&address)?, retry, progress)?;
+        if !outcome.is_redirect() {
+            return limit.apply(FetchOutcome { address, ..outcome });
+        }
+        debug!("Redirected from {address} to {}", outcome.meta);
+        address = handle_address(&address, &outcome.meta, Origin::Page)?;
+        origin = Origin::Page;
+    }
+    bail!("too many redirects, the last one to {}", address)
+}
+
+/// Load `url` from the `source`, and try again after a transient failure, as long as the `retry`
+/// policy allows. The last try is returned, whether it failed or not.
+fn fetch_with_retries(
+    source: &dyn PageSource,
+    url: &Url,
+    retry: RetryPolicy,
+    progress: &mut dyn FnMut(usize),
+) -> Result<FetchOutcome> {
+    let mut retried = 0;
+    loop {
+        let result = source.fetch_with_progress(url, progress);
+        if retried == retry.retries || !is_transient(&result) {
+            return result;
+        }
+        let delay = retry.delay(retried);
+        match &result {
+            Ok(outcome) => debug!("Retrying {url} in {delay:?} after status {}", outcome.status),
+            Err(err) => debug!("Retrying {url} in {delay:?} after: {err}"),
+        }
+        thread::sleep(delay);
+        retried += 1;
+    }
 }
 
-pub fn fetch_page(address: &str, base_path: &str) -> String {
-    let address = handle_address(base_path, address).unwrap();
-    let gemini_url = Url::parse(&address).expect("Invalid URL");
+/// Start loading the page at `address`, which may be relative to `base_path`, in the background.
+///
+/// The returned [`Receiver`] reports the progress of the fetch, and finally its outcome. The page
+/// is loaded from the `source` as far as its `origin` allows, held to the `limit`, and tried again
+/// as the `retry` policy has it.
+pub fn spawn_fetch(
+    address: &str,
+    base_path: &str,
+    origin: Origin,
+    source: Arc<dyn PageSource + Send + Sync>,
+    limit: SizeLimit,
+    retry: RetryPolicy,
+) -> Receiver<FetchEvent> {
+    let (sender, receiver) = mpsc::channel();
+    let (address, base_path) = (address.to_string(), base_path.to_string());
+    thread::spawn(move || {
+        // Sending fails once the receiver is gone, in which case nobody is waiting for this page
+        // anymore, and there is nothing left to do but finish.
+        let mut progress = |bytes| {
+            let _ = sender.send(FetchEvent::Progress { bytes });
+        };
+        let outcome = fetch_page(
+            source.as_ref(),
+            &address,
+            &base_path,
+            origin,
+            limit,
+            retry,
+            &mut progress,
+        );
+        let _ = sender.send(FetchEvent::Done(outcome));
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use gemininini::source::MockSource;
+
+    use super::*;
+
+    const LIMIT: SizeLimit = SizeLimit {
+        max_size: 1024,
+        truncate: false,
+    };
+    const NO_RETRIES: RetryPolicy = RetryPolicy {
+        retries: 0,
+        backoff: Duration::ZERO,
+    };
+
+    fn fetch(source: &MockSource, address: &str, origin: Origin) -> Result<FetchOutcome> {
+        fetch_page(
+            source,
+            address,
+            address,
+            origin,
+            LIMIT,
+            NO_RETRIES,
+            &mut |_| {},
+        )
+    }
+
+    #[test]
+    fn data_url_with_base64() {
+        let (mime, data) = parse_data_url("data:image/png;base64,iVBO Rw==").unwrap();
+        assert_eq!(mime.to_string(), "image/png");
+        assert_eq!(data, b"\x89PNG");
+    }
+
+    #[test]
+    fn data_url_with_percent_encoding() {
+        let (mime, data) = parse_data_url("data:text/gemini;charset=utf-8,%23%20Hi%0A").unwrap();
+        assert_eq!(mime.to_string(), "text/gemini;charset=utf-8");
+        assert_eq!(data, b"# Hi\n");
+    }
+
+    #[test]
+    fn data_url_without_media_type() {
+        let (mime, data) = parse_data_url("data:,hello%20there").unwrap();
+        assert_eq!(mime.to_string(), DATA_DEFAULT_MIME);
+        assert_eq!(data, b"hello there");
+        let (mime, _) = parse_data_url("data:;charset=utf-8,").unwrap();
+        assert_eq!(mime.to_string(), "text/plain;charset=utf-8");
+    }
+
+    #[test]
+    fn data_url_errors() {
+        assert!(parse_data_url("gemini://example.org/").is_err());
+        assert!(parse_data_url("data:text/plain").is_err());
+        assert!(parse_data_url("data:text/plain;base64,!!!!").is_err());
+        assert!(parse_data_url("data:nonsense,").is_err());
+    }
+
+    #[test]
+    fn titan_request_line_has_size_token_and_mime() {
+        let url = Url::parse("titan://example.org/notes.gmi").unwrap();
+        let line = titan_request_line(&url, 5, Some("secret"), "text/gemini", b"hello").unwrap();
+        assert_eq!(
+            line,
+            "titan://example.org/notes.gmi;size=5;token=secret;mime=text/gemini\r\n"
+        );
+    }
+
+    #[test]
+    fn titan_request_line_turns_gemini_into_titan() {
+        let url = Url::parse("gemini://example.org/notes.gmi?query#part").unwrap();
+        let line = titan_request_line(&url, 0, None, "text/plain", b"").unwrap();
+        assert_eq!(
+            line,
+            "titan://example.org/notes.gmi;size=0;mime=text/plain\r\n"
+        );
+    }
+
+    #[test]
+    fn titan_request_line_size_must_match_the_body() {
+        let url = Url::parse("titan://example.org/").unwrap();
+        let err = titan_request_line(&url, 4, None, "text/plain", b"hello").unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+    }
+
+    #[test]
+    fn titan_request_line_refuses_bad_parameters() {
+        let url = Url::parse("titan://example.org/").unwrap();
+        for mime in ["", "text/plain;charset=utf-8", "text/ plain"] {
+            assert!(
+                titan_request_line(&url, 0, None, mime, b"").is_err(),
+                "{mime:?}"
+            );
+        }
+        for token in ["", "a;b", "a b"] {
+            assert!(titan_request_line(&url, 0, Some(token), "text/plain", b"").is_err());
+        }
+        let https = Url::parse("https://example.org/").unwrap();
+        assert!(titan_request_line(&https, 0, None, "text/plain", b"").is_err());
+    }
+
+    #[test]
+    fn titan_upload_gives_up_on_a_server_that_does_not_answer() {
+        // The server takes the connection, but never as much as starts the handshake.
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let url = Url::parse(&format!("titan://localhost:{port}/notes.gmi")).unwrap();
+        let timeout = Duration::from_millis(100);
+        let upload = spawn_titan_upload(&url, b"hello", None, "text/plain", timeout);
+        let response = upload.recv_timeout(Duration::from_secs(5)).unwrap();
+        let err = response.unwrap_err();
+        assert_eq!(err.to_string(), "no response within 100ms");
+    }
 
-    let gemini_body = get_gemini_page_blocking(&gemini_url).expect("Error fetching Gemini page");
-    gemini_body
+    #[test]
+    fn data_url_is_shown_without_a_fetch() {
+        // The source has no pages, so anything but a data URL would fail to load.
+        let source = MockSource::new();
+        let body = |address| fetch(&source, address, Origin::Typed).unwrap().body;
+        assert_eq!(body("data:,hello"), "hello");
+        assert_eq!(
+            body("data:image/png;base64,iVBORw=="),
+            "[image/png data, 4 bytes]"
+        );
+    }
+
+    #[test]
+    fn redirect_to_file_is_not_followed() {
+        let source =
+            MockSource::new().with_page("gemini://example.org/", 30, "file:///etc/passwd", "");
+        let err = fetch(&source, "gemini://example.org/", Origin::Typed).unwrap_err();
+        assert!(err.to_string().contains("local file"), "{err}");
+    }
+
+    #[test]
+    fn dash_is_standard_input_only_at_start() {
+        let base = "gemini://example.org/dir/page.gmi";
+        assert_eq!(
+            resolve_link(base, "-").as_deref(),
+            Some("gemini://example.org/dir/-")
+        );
+        assert_eq!(
+            resolve_address(base, "-", Origin::Typed).as_deref(),
+            Some("gemini://example.org/dir/-")
+        );
+        assert_eq!(
+            resolve_address(base, "-", Origin::Start).as_deref(),
+            Some("-")
+        );
+    }
+
+    #[test]
+    fn meta_of_the_response_is_kept() {
+        let address = "gemini://example.org/";
+        let source = MockSource::new().with_page(address, 20, "text/gemini; lang=en", "# Hi");
+        let page = fetch(&source, address, Origin::Typed).unwrap();
+        assert_eq!(page.meta, "text/gemini; lang=en");
+        assert_eq!(page.status, STATUS_SUCCESS);
+        assert_eq!(page.body, "# Hi");
+    }
+
+    #[test]
+    fn meta_without_lang() {
+        let (mime, lang) = parse_meta("text/gemini").unwrap();
+        assert_eq!((mime.kind.as_str(), mime.subtype.as_str()), ("text", "gemini"));
+        assert_eq!(lang, None);
+        let (mime, lang) = parse_meta("").unwrap();
+        assert_eq!(mime.to_string(), "text/gemini;charset=utf-8");
+        assert_eq!(lang, None);
+    }
+
+    #[test]
+    fn meta_with_lang() {
+        let (_, lang) = parse_meta("text/gemini; charset=utf-8; lang=pt-BR").unwrap();
+        assert_eq!(lang, Some(Lang("pt-BR".to_string())));
+        assert_eq!(lang.unwrap().primary(), "pt");
+        // Of several languages, the first is taken.
+        let (_, lang) = parse_meta("text/gemini;LANG=, he,en").unwrap();
+        assert_eq!(lang, Some(Lang("he".to_string())));
+        assert!(lang.unwrap().is_rtl());
+        let (_, lang) = parse_meta("text/gemini; lang=en").unwrap();
+        assert!(!lang.unwrap().is_rtl());
+    }
+
+    #[test]
+    fn meta_that_is_no_media_type() {
+        assert!(parse_meta("text").is_err());
+        assert!(parse_meta("/gemini").is_err());
+    }
+
+    #[test]
+    fn host_is_lowercased() {
+        let normal = normalize_url("gemini://Example.ORG/Page.gmi").unwrap();
+        assert_eq!(normal, "gemini://example.org/Page.gmi");
+    }
+
+    #[test]
+    fn default_port_is_left_out() {
+        let normal = normalize_url("gemini://example.org:1965/").unwrap();
+        assert_eq!(normal, "gemini://example.org/");
+        let other = normalize_url("gemini://example.org:1966/").unwrap();
+        assert_eq!(other, "gemini://example.org:1966/");
+    }
+
+    #[test]
+    fn port_is_the_named_one_or_else_the_default_of_the_scheme() {
+        let port = |url: &str| port_of(&Url::parse(url).unwrap()).ok();
+        assert_eq!(port("gemini://example.org"), Some(1965));
+        assert_eq!(port("gemini://example.org:300/"), Some(300));
+        assert_eq!(port("titan://example.org/upload"), Some(1965));
+        assert_eq!(port("gopher://example.org/"), Some(70));
+        assert_eq!(port("finger://example.org/user"), Some(79));
+        assert_eq!(port("unknown://example.org/"), None);
+        let explicit = normalize_url("gemini://example.org:300").unwrap();
+        assert_eq!(explicit, "gemini://example.org:300/");
+    }
+
+    #[test]
+    fn empty_path_becomes_the_root() {
+        let normal = normalize_url("gemini://example.org").unwrap();
+        assert_eq!(normal, "gemini://example.org/");
+    }
+
+    #[test]
+    fn dot_segments_are_removed() {
+        let normal = normalize_url("gemini://example.org/a/./b/../c.gmi").unwrap();
+        assert_eq!(normal, "gemini://example.org/a/c.gmi");
+    }
+
+    #[test]
+    fn fragment_is_dropped_but_the_query_kept() {
+        let normal = normalize_url("gemini://example.org/search?q#results").unwrap();
+        assert_eq!(normal, "gemini://example.org/search?q");
+    }
+
+    #[test]
+    fn forms_of_the_same_page_are_equal() {
+        let forms = [
+            "gemini://example.org",
+            "gemini://example.org/",
+            "gemini://example.org:1965/",
+            "gemini://Example.org/",
+            "gemini://example.org/./#top",
+        ];
+        for form in forms {
+            assert_eq!(normalize_url(form).unwrap(), "gemini://example.org/");
+        }
+        assert!(normalize_url("not a url").is_err());
+        let data = "data:text/plain,Hi";
+        assert_eq!(normalize_url(data).unwrap(), data);
+    }
+
+    #[test]
+    fn relative_forms_resolve_against_a_base_with_a_path() {
+        let base = "gemini://example.org/dir/page.gmi?old";
+        let resolve = |link| resolve_link(base, link).unwrap();
+        assert_eq!(resolve("?new"), "gemini://example.org/dir/page.gmi?new");
+        assert_eq!(resolve("#part"), "gemini://example.org/dir/page.gmi?old");
+        assert_eq!(resolve("./other.gmi"), "gemini://example.org/dir/other.gmi");
+        assert_eq!(resolve("../up.gmi"), "gemini://example.org/up.gmi");
+        assert_eq!(resolve("../../../up.gmi"), "gemini://example.org/up.gmi");
+        assert_eq!(resolve("sub/"), "gemini://example.org/dir/sub/");
+        assert_eq!(resolve("/root.gmi"), "gemini://example.org/root.gmi");
+        assert_eq!(resolve("//Other.org/x"), "gemini://other.org/x");
+        assert_eq!(resolve(""), "gemini://example.org/dir/page.gmi?old");
+    }
+
+    #[test]
+    fn unresolvable_addresses_are_errors() {
+        let err = handle_address("not a base", "page.gmi", Origin::Page).unwrap_err();
+        assert!(err.to_string().contains("invalid base address"), "{err}");
+        let err = handle_address("gemini://example.org/", "gemini://[::", Origin::Page);
+        assert!(err.unwrap_err().to_string().contains("invalid address"));
+        assert_eq!(resolve_link("not a base", "page.gmi"), None);
+    }
+
+    #[test]
+    fn redirect_chain_is_followed_through_the_mock() {
+        let source = MockSource::new()
+            .with_page("gemini://example.org/old", 31, "/moved", "")
+            .with_page(
+                "gemini://example.org/moved",
+                30,
+                "gemini://other.org/new",
+                "",
+            )
+            .with_page("gemini://other.org/new", 20, "text/gemini", "# Here");
+        let page = fetch(&source, "gemini://example.org/old", Origin::Typed).unwrap();
+        assert_eq!(page.address, "gemini://other.org/new");
+        assert_eq!((page.status, page.body.as_str()), (20, "# Here"));
+    }
+
+    #[test]
+    fn too_many_redirects_are_an_error() {
+        // A page that redirects to itself would be followed forever.
+        let source = MockSource::new().with_page("gemini://example.org/loop", 30, "loop", "");
+        let address = "gemini://example.org/loop";
+        let err = fetch(&source, address, Origin::Typed).unwrap_err();
+        assert!(err.to_string().contains("too many redirects"), "{err}");
+    }
+
+    #[test]
+    fn missing_page_is_an_error() {
+        let source = MockSource::new();
+        let err = fetch(&source, "gemini://example.org/", Origin::Typed).unwrap_err();
+        assert!(err.to_string().contains("no page at"), "{err}");
+    }
+
+    #[test]
+    fn large_page_is_cut_off_or_fails_as_the_limit_has_it() {
+        let address = "gemini://example.org/big";
+        let source = MockSource::new().with_page(address, 20, "text/plain", &"é".repeat(8));
+        let fetch_with = |truncate| {
+            let limit = SizeLimit {
+                max_size: 5,
+                truncate,
+            };
+            let origin = Origin::Typed;
+            fetch_page(&source, address, address, origin, limit, NO_RETRIES, &mut |_| {})
+        };
+        // The character that is cut in half is left out.
+        let page = fetch_with(true).unwrap();
+        assert_eq!(page.body, format!("éé{TRUNCATED_MARKER}"));
+        let err = fetch_with(false).unwrap_err();
+        assert!(err.to_string().contains("larger than 5 bytes"), "{err}");
+        // A page within the limit is left alone.
+        let page = fetch(&source, address, Origin::Typed).unwrap();
+        assert_eq!(page.body, "é".repeat(8));
+    }
+
+    #[test]
+    fn every_request_shares_one_runtime() {
+        let runtimes: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(|| runtime().unwrap() as *const Runtime as usize))
+            .collect();
+        let first = runtime().unwrap() as *const Runtime as usize;
+        for runtime in runtimes {
+            assert_eq!(runtime.join().unwrap(), first);
+        }
+    }
+
+    #[test]
+    fn fetched_page_has_only_newlines() {
+        let address = "gemini://example.org/";
+        let body = "# Title\r\n=> /a A\rtext\n";
+        let source = MockSource::new().with_page(address, 20, "text/gemini", body);
+        let page = fetch(&source, address, Origin::Typed).unwrap();
+        assert_eq!(page.body, "# Title\n=> /a A\ntext\n");
+    }
+
+    #[test]
+    fn input_answer_survives_the_round_trip() {
+        let base = Url::parse("gemini://example.org/search?old query").unwrap();
+        for answer in ["two words", "a&b=c?d#e/f+g%20", "grüße, 日本", ""] {
+            let url = build_input_url(&base, answer);
+            assert_eq!(url.path(), "/search");
+            let query = url.query().unwrap();
+            assert!(!query.contains([' ', '&', '=', '#', '+']), "{query}");
+            assert!(query.is_ascii(), "{query}");
+            let decoded = percent_decode_str(query).decode_utf8().unwrap();
+            assert_eq!(decoded, answer);
+        }
+        let url = build_input_url(&base, "two words");
+        assert_eq!(url.as_str(), "gemini://example.org/search?two%20words");
+    }
+
+    #[test]
+    fn only_transient_failures_are_tried_again() {
+        let retry = RetryPolicy {
+            retries: 2,
+            backoff: Duration::ZERO,
+        };
+        let fetch_with = |status| {
+            let address = "gemini://example.org/";
+            let source = MockSource::new().with_page(address, status, "failed", "");
+            let origin = Origin::Typed;
+            let page = fetch_page(&source, address, address, origin, LIMIT, retry, &mut |_| {});
+            (page.unwrap().status, source.fetch_count())
+        };
+        assert_eq!(fetch_with(41), (41, 3));
+        assert_eq!(fetch_with(44), (44, 1));
+        assert_eq!(fetch_with(51), (51, 1));
+        assert_eq!(fetch_with(20), (20, 1));
+    }
 }
@@ -0,0 +1,134 @@
+//! Trying a fetch again when it failed in a way that may well go away by itself.
+
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+
+use anyhow::{Error, Result};
+use gemininini::source::FetchOutcome;
+
+/// The kinds of I/O errors of a connection that broke down along the way, rather than of one that
+/// could never have worked.
+const TRANSIENT_ERROR_KINDS: &[ErrorKind] = &[
+    ErrorKind::ConnectionReset,
+    ErrorKind::ConnectionAborted,
+    ErrorKind::BrokenPipe,
+    ErrorKind::TimedOut,
+    ErrorKind::UnexpectedEof,
+    ErrorKind::Interrupted,
+];
+/// The status of a response that asks to slow down, with how many seconds to wait in its meta.
+const STATUS_SLOW_DOWN: u8 = 44;
+
+/// Whether a fetch that failed may succeed when it is tried again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The failure may well be gone by the next try.
+    Transient,
+    /// The next try would fail the same way.
+    Permanent,
+}
+
+/// How often a fetch that failed transiently is tried again, and how long to wait in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many more times a fetch is tried after its first try failed.
+    pub retries: u32,
+    /// How long to wait before the first retry. The wait doubles with every retry after that.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns how long to wait before trying again after `retried` retries have failed already.
+    pub fn delay(&self, retried: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retried))
+    }
+}
+
+/// Returns whether the `error` that a fetch failed with is worth trying again.
+///
+/// A connection that was dropped or timed out may well work the next time. Anything else, such as
+/// a host that cannot be found or a certificate that does not match, is bound to fail again.
+pub fn classify_error(error: &Error) -> FailureKind {
+    let transient = error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|err| TRANSIENT_ERROR_KINDS.contains(&err.kind()));
+    match transient {
+        true => FailureKind::Transient,
+        false => FailureKind::Permanent,
+    }
+}
+
+/// Returns whether a response with `status` failed in a way that is worth trying again, unless it
+/// did not fail at all.
+///
+/// Temporary failures are worth trying again, except for being asked to slow down, which says how
+/// long to wait itself. Permanent failures and missing client certificates are not.
+pub fn classify_status(status: u8) -> Option<FailureKind> {
+    match status {
+        STATUS_SLOW_DOWN => Some(FailureKind::Permanent),
+        40..=49 => Some(FailureKind::Transient),
+        50..=69 => Some(FailureKind::Permanent),
+        _ => None,
+    }
+}
+
+/// Returns whether the `result` of a fetch is a failure that is worth trying again.
+pub fn is_transient(result: &Result<FetchOutcome>) -> bool {
+    let kind = match result {
+        Ok(outcome) => classify_status(outcome.status),
+        Err(err) => Some(classify_error(err)),
+    };
+    kind == Some(FailureKind::Transient)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn broken_connections_are_transient_and_the_rest_permanent() {
+        let reset = Error::from(io::Error::from(ErrorKind::ConnectionReset));
+        assert_eq!(classify_error(&reset), FailureKind::Transient);
+        // The cause is found however deep in the chain it is.
+        let timed_out = Error::from(io::Error::from(ErrorKind::TimedOut)).context("fetching");
+        assert_eq!(classify_error(&timed_out), FailureKind::Transient);
+        let not_found = Error::from(io::Error::from(ErrorKind::NotFound));
+        assert_eq!(classify_error(&not_found), FailureKind::Permanent);
+        let certificate = anyhow!("the certificate does not match the host");
+        assert_eq!(classify_error(&certificate), FailureKind::Permanent);
+    }
+
+    #[test]
+    fn temporary_failures_are_transient_except_slowing_down() {
+        assert_eq!(classify_status(20), None);
+        assert_eq!(classify_status(31), None);
+        assert_eq!(classify_status(41), Some(FailureKind::Transient));
+        assert_eq!(classify_status(44), Some(FailureKind::Permanent));
+        assert_eq!(classify_status(51), Some(FailureKind::Permanent));
+        assert_eq!(classify_status(60), Some(FailureKind::Permanent));
+        let outcome = |status| FetchOutcome {
+            address: "gemini://example.org/".to_string(),
+            status,
+            meta: String::new(),
+            body: String::new(),
+        };
+        assert!(is_transient(&Ok(outcome(40))));
+        assert!(!is_transient(&Ok(outcome(20))));
+        assert!(!is_transient(&Err(anyhow!("no such host"))));
+    }
+
+    #[test]
+    fn backoff_doubles_with_every_retry() {
+        let policy = RetryPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(100),
+        };
+        let delays: Vec<_> = (0..3).map(|retried| policy.delay(retried)).collect();
+        assert_eq!(delays, [100, 200, 400].map(Duration::from_millis));
+        // The wait stops growing rather than overflowing.
+        assert_eq!(policy.delay(u32::MAX), policy.backoff * u32::MAX);
+    }
+}
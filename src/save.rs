@@ -0,0 +1,140 @@
+//! Archiving fetched pages to disk.
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use gemininini::gemtext;
+use url::Url;
+
+/// The file name used for a page whose URL does not end in a path segment.
+const DEFAULT_FILE_NAME: &str = "index.gmi";
+
+/// Returns the name under which the page at `url` is saved.
+///
+/// This is the last segment of the path, so `gemini://example.org/notes/today.gmi` is saved as
+/// `today.gmi`, while `gemini://example.org/notes/` is saved as `index.gmi`.
+pub fn page_file_name(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let segment = url.path_segments()?.next_back()?;
+            (!segment.is_empty()).then(|| segment.to_string())
+        })
+        .unwrap_or_else(|| DEFAULT_FILE_NAME.to_string())
+}
+
+/// Returns the `n`th alternative to `name`, with a counter before the extension, such as
+/// `index-2.gmi` for `index.gmi`.
+fn numbered(name: &str, n: usize) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}-{n}.{ext}"),
+        _ => format!("{name}-{n}"),
+    }
+}
+
+/// Write the `body` of the page at `url` to a new file in `dir`, and return its path.
+///
+/// An existing file is never overwritten. Instead, a counter is appended to the name.
+pub fn save_page(dir: &Path, url: &str, body: &str) -> std::io::Result<PathBuf> {
+    let name = page_file_name(url);
+    for n in 0.. {
+        let path = match n {
+            0 => dir.join(&name),
+            n => dir.join(numbered(&name, n)),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(body.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!("ran out of file names")
+}
+
+/// The name of the file in which bookmarks are kept, as a gemtext list of links.
+const BOOKMARKS_FILE_NAME: &str = "bookmarks.gmi";
+
+/// Add a link to `url` to the bookmarks file in `dir`, and return the path of that file.
+pub fn add_bookmark(dir: &Path, url: &str) -> std::io::Result<PathBuf> {
+    let path = dir.join(BOOKMARKS_FILE_NAME);
+    let mut file = OpenOptions::new().append(true).create(true).open(&path)?;
+    writeln!(file, "=> {url}")?;
+    Ok(path)
+}
+
+/// Returns the addresses in the bookmarks file in `dir`, which is no bookmarks at all if there is
+/// no such file yet.
+pub fn load_bookmarks(dir: &Path) -> std::io::Result<Vec<String>> {
+    match std::fs::read_to_string(dir.join(BOOKMARKS_FILE_NAME)) {
+        Ok(contents) => Ok(gemtext::links(&contents)
+            .map(|(_, url)| url.to_string())
+            .collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory of its own for a test.
+    fn empty_dir(name: &str) -> PathBuf {
+        let name = format!("gemininini-save-{}-{name}", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn page_is_named_after_its_last_segment() {
+        let name = page_file_name("gemini://example.org/notes/today.gmi?q#top");
+        assert_eq!(name, "today.gmi");
+    }
+
+    #[test]
+    fn page_without_a_last_segment_is_the_index() {
+        assert_eq!(
+            page_file_name("gemini://example.org/notes/"),
+            DEFAULT_FILE_NAME
+        );
+        assert_eq!(page_file_name("gemini://example.org"), DEFAULT_FILE_NAME);
+        assert_eq!(page_file_name("data:,hello"), DEFAULT_FILE_NAME);
+    }
+
+    #[test]
+    fn saving_again_does_not_overwrite() {
+        let dir = empty_dir("again");
+        let url = "gemini://example.org/today.gmi";
+        let first = save_page(&dir, url, "first").unwrap();
+        let second = save_page(&dir, url, "second").unwrap();
+        assert_eq!(first, dir.join("today.gmi"));
+        assert_eq!(second, dir.join("today-1.gmi"));
+        assert_eq!(std::fs::read_to_string(first).unwrap(), "first");
+        assert_eq!(numbered("README", 2), "README-2");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bookmarks_are_added_in_order() {
+        let dir = empty_dir("bookmarks");
+        assert_eq!(load_bookmarks(&dir).unwrap(), Vec::<String>::new());
+        let path = add_bookmark(&dir, "gemini://example.org/").unwrap();
+        add_bookmark(&dir, "gemini://example.org/later.gmi").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "=> gemini://example.org/\n=> gemini://example.org/later.gmi\n"
+        );
+        let bookmarks = load_bookmarks(&dir).unwrap();
+        assert_eq!(
+            bookmarks,
+            ["gemini://example.org/", "gemini://example.org/later.gmi"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
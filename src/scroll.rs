@@ -0,0 +1,64 @@
+//! How far a press of a scroll key scrolls, which grows while the key is held down.
+
+/// The step of scroll keys, which grows by itself with every repeat of a held key, up to a cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollSpeed {
+    /// The step in pixels of a fresh key press.
+    step: usize,
+    /// The largest step in pixels that holding a key grows to.
+    max_step: usize,
+    /// The step in pixels of the last key press.
+    current: usize,
+}
+
+impl ScrollSpeed {
+    /// Creates a new [`ScrollSpeed`] that starts at `step` pixels and grows up to `max_step`.
+    ///
+    /// A `max_step` smaller than `step` means the step does not grow at all.
+    pub fn new(step: usize, max_step: usize) -> Self {
+        Self {
+            step,
+            max_step: max_step.max(step),
+            current: step,
+        }
+    }
+
+    /// Returns the step in pixels for a press of a scroll key.
+    ///
+    /// A `fresh` press starts over at the initial step, and a repeat of a held key goes one step
+    /// further than the last one.
+    pub fn next(&mut self, fresh: bool) -> usize {
+        self.current = match fresh {
+            true => self.step,
+            false => self.current.saturating_add(self.step).min(self.max_step),
+        };
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_press_scrolls_by_the_configured_step() {
+        let mut speed = ScrollSpeed::new(12, 12);
+        assert_eq!(speed.next(true), 12);
+        assert_eq!(speed.next(false), 12);
+        assert_eq!(speed.next(true), 12);
+    }
+
+    #[test]
+    fn held_key_speeds_up_to_the_cap() {
+        let mut speed = ScrollSpeed::new(8, 30);
+        let steps: Vec<_> = [true, false, false, false, false]
+            .into_iter()
+            .map(|fresh| speed.next(fresh))
+            .collect();
+        assert_eq!(steps, [8, 16, 24, 30, 30]);
+        assert_eq!(speed.next(true), 8);
+        // A cap below the step leaves the step as it is.
+        let mut speed = ScrollSpeed::new(8, 2);
+        assert_eq!((speed.next(true), speed.next(false)), (8, 8));
+    }
+}
@@ -0,0 +1,132 @@
+//! Remembering where reading left off, to pick up from there on the next start.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::config::user_dir;
+
+/// The name of the file that the session is kept in.
+const SESSION_FILE_NAME: &str = "session";
+
+/// Where reading left off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// The address of the page that was shown.
+    pub url: String,
+    /// How far that page was scrolled down, in pixels.
+    pub scroll_pos: usize,
+    /// How far the window was zoomed in, if the session file says.
+    pub zoom: Option<u32>,
+}
+
+/// Returns the path of the session file, in the [user directory](user_dir).
+pub fn session_path() -> Option<PathBuf> {
+    Some(user_dir()?.join(SESSION_FILE_NAME))
+}
+
+/// Returns the `session` written out in the format of the session file.
+pub fn serialize(session: &Session) -> String {
+    let mut serialized = format!("url {}\nscroll {}\n", session.url, session.scroll_pos);
+    if let Some(zoom) = session.zoom {
+        serialized.push_str(&format!("zoom {zoom}\n"));
+    }
+    serialized
+}
+
+/// Read a [`Session`] from the contents of a session file.
+///
+/// The file has the same format as the config file: a keyword and its argument on every line.
+pub fn deserialize(contents: &str) -> Result<Session, String> {
+    let mut url = None;
+    let mut scroll_pos = 0;
+    let mut zoom = None;
+    for line in contents.lines().map(str::trim).filter(|ln| !ln.is_empty()) {
+        let (keyword, argument) = line
+            .split_once(char::is_whitespace)
+            .ok_or(format!("expected argument after keyword in '{line}'"))?;
+        let argument = argument.trim();
+        match keyword {
+            "url" => url = Some(argument.to_string()),
+            "scroll" => scroll_pos = argument.parse().map_err(|e| format!("{e}"))?,
+            "zoom" => zoom = Some(argument.parse().map_err(|e| format!("{e}"))?),
+            unknown => return Err(format!("unknown keyword '{unknown}'")),
+        }
+    }
+    let url = url.ok_or("missing url")?;
+    Ok(Session {
+        url,
+        scroll_pos,
+        zoom,
+    })
+}
+
+/// Read the [`Session`] in the file at `path`, if there is one.
+pub fn load(path: &Path) -> Result<Option<Session>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => deserialize(&contents).map(Some),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("{err}")),
+    }
+}
+
+/// Write the `session` to the file at `path`, creating its directory if needed.
+pub fn store(path: &Path, session: &Session) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serialize(session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(zoom: Option<u32>) -> Session {
+        Session {
+            url: "gemini://example.org/page.gmi".to_string(),
+            scroll_pos: 120,
+            zoom,
+        }
+    }
+
+    #[test]
+    fn session_round_trips() {
+        for zoom in [None, Some(2)] {
+            assert_eq!(deserialize(&serialize(&session(zoom))), Ok(session(zoom)));
+        }
+        assert_eq!(
+            serialize(&session(Some(2))),
+            "url gemini://example.org/page.gmi\nscroll 120\nzoom 2\n"
+        );
+    }
+
+    #[test]
+    fn only_the_url_is_needed() {
+        let only_url = deserialize("\n  url gemini://example.org/page.gmi  \n\n").unwrap();
+        assert_eq!(only_url.scroll_pos, 0);
+        assert_eq!(only_url.zoom, None);
+    }
+
+    #[test]
+    fn corrupt_sessions_are_errors() {
+        assert_eq!(deserialize(""), Err("missing url".to_string()));
+        assert!(deserialize("scroll 10").is_err());
+        assert!(deserialize("url").is_err());
+        assert!(deserialize("url gemini://example.org/\nscroll far").is_err());
+        assert!(deserialize("url gemini://example.org/\ncolor red").is_err());
+    }
+
+    #[test]
+    fn stored_session_is_loaded_again() {
+        let name = format!("gemininini-session-{}-stored", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("nested").join(SESSION_FILE_NAME);
+        assert_eq!(load(&path), Ok(None));
+        store(&path, &session(Some(3))).unwrap();
+        assert_eq!(load(&path), Ok(Some(session(Some(3)))));
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Where pages are loaded from, such that navigation can be tried out without a network.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use url::Url;
+
+/// The status of a response that asks for input, to be sent as the query of the same URL.
+pub const STATUS_INPUT: u8 = 10;
+/// The status of a response that asks for input that is not to be shown while it is typed, such
+/// as a password.
+pub const STATUS_SENSITIVE_INPUT: u8 = 11;
+/// The status of a successful response.
+pub const STATUS_SUCCESS: u8 = 20;
+/// The status of a response that sends the client elsewhere for the time being.
+pub const STATUS_TEMPORARY_REDIRECT: u8 = 30;
+/// The status of a response that sends the client elsewhere for good.
+pub const STATUS_PERMANENT_REDIRECT: u8 = 31;
+
+/// A page that finished loading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchOutcome {
+    /// The address the page was loaded from.
+    pub address: String,
+    /// The two-digit status of the response, such as 20 for success.
+    pub status: u8,
+    /// The meta string of the response header, which is the media type of a successful response,
+    /// such as `text/gemini; lang=en`, and the new address of a redirect.
+    pub meta: String,
+    pub body: String,
+}
+
+impl FetchOutcome {
+    /// Returns whether the response asks for input instead of having a page, with the question in
+    /// its meta.
+    pub fn asks_for_input(&self) -> bool {
+        self.status / 10 == STATUS_INPUT / 10
+    }
+
+    /// Returns whether the response sends the client to the address in its meta instead.
+    pub fn is_redirect(&self) -> bool {
+        matches!(
+            self.status,
+            STATUS_TEMPORARY_REDIRECT | STATUS_PERMANENT_REDIRECT
+        )
+    }
+}
+
+/// Something that pages can be loaded from.
+pub trait PageSource {
+    /// Load the page at `url`.
+    fn fetch(&self, url: &Url) -> Result<FetchOutcome>;
+
+    /// Load the page at `url`, calling `progress` with the number of body bytes received so far
+    /// every time more of the body arrives.
+    ///
+    /// Sources that have the whole page at once need not report any progress, which is what
+    /// this does unless it is overridden.
+    fn fetch_with_progress(
+        &self,
+        url: &Url,
+        _progress: &mut dyn FnMut(usize),
+    ) -> Result<FetchOutcome> {
+        self.fetch(url)
+    }
+}
+
+/// A [`PageSource`] that serves pages from memory, by their address.
+#[derive(Debug, Clone, Default)]
+pub struct MockSource {
+    pub pages: HashMap<String, FetchOutcome>,
+    /// How many pages were asked for, which clones of this [`MockSource`] count together.
+    fetches: Arc<AtomicUsize>,
+}
+
+impl MockSource {
+    /// Creates a new [`MockSource`] without any pages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns this [`MockSource`] with a page at `address`, which responds with `status`,
+    /// `meta`, and `body`.
+    pub fn with_page(mut self, address: &str, status: u8, meta: &str, body: &str) -> Self {
+        let outcome = FetchOutcome {
+            address: address.to_string(),
+            status,
+            meta: meta.to_string(),
+            body: body.to_string(),
+        };
+        self.pages.insert(address.to_string(), outcome);
+        self
+    }
+
+    /// Returns how many times a page was asked for, whether this source had it or not.
+    pub fn fetch_count(&self) -> usize {
+        self.fetches.load(Ordering::SeqCst)
+    }
+}
+
+impl PageSource for MockSource {
+    fn fetch(&self, url: &Url) -> Result<FetchOutcome> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        self.pages
+            .get(url.as_str())
+            .cloned()
+            .ok_or_else(|| anyhow!("no page at {}", url))
+    }
+}
@@ -0,0 +1,119 @@
+//! Recognizing tables drawn with pipes and dashes in preformatted blocks, to line up their columns.
+
+/// The character that separates the cells of a row.
+const CELL_SEPARATOR: char = '|';
+/// The characters that a line between rows, such as `|---+---|`, is drawn with.
+const SEPARATOR_CHARS: &[char] = &['-', '=', '+', '|', ':', ' '];
+
+/// A line of a preformatted block that is part of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableLine<'t> {
+    /// A row of cells, trimmed of the spaces around them.
+    Row(Vec<&'t str>),
+    /// A line that separates rows, such as `|---+---|`.
+    Separator,
+}
+
+impl<'t> TableLine<'t> {
+    /// Determine what kind of [`TableLine`] the `line` is, if it could be part of a table at all.
+    fn parse(line: &'t str) -> Option<Self> {
+        let trimmed = line.trim();
+        if trimmed.contains(['-', '=']) && trimmed.chars().all(|ch| SEPARATOR_CHARS.contains(&ch)) {
+            return Some(Self::Separator);
+        }
+        if !trimmed.contains(CELL_SEPARATOR) {
+            return None;
+        }
+        let cells = line.split(CELL_SEPARATOR).map(str::trim).collect();
+        Some(Self::Row(cells))
+    }
+}
+
+/// Returns the `lines` of a preformatted block as a table, if they look like one.
+///
+/// That is, every line is either a row with the same number of cells separated by `|`, or a line
+/// drawn between rows with dashes. It takes at least two rows to make a table, so that a stray
+/// pipe in some ASCII art does not count.
+pub fn parse_table<'t>(lines: &[&'t str]) -> Option<Vec<TableLine<'t>>> {
+    let table = lines
+        .iter()
+        .map(|line| TableLine::parse(line))
+        .collect::<Option<Vec<_>>>()?;
+    let mut cell_counts = table.iter().filter_map(|line| match line {
+        TableLine::Row(cells) => Some(cells.len()),
+        TableLine::Separator => None,
+    });
+    let first = cell_counts.next()?;
+    let mut rows = 1;
+    for count in cell_counts {
+        if count != first {
+            return None;
+        }
+        rows += 1;
+    }
+    match rows >= 2 {
+        true => Some(table),
+        false => None,
+    }
+}
+
+/// Returns the width of the widest cell in every column of the `table`, as measured by `width`.
+pub fn column_widths(table: &[TableLine], width: impl Fn(&str) -> usize) -> Vec<usize> {
+    let mut widths = Vec::new();
+    for line in table {
+        if let TableLine::Row(cells) = line {
+            widths.resize(widths.len().max(cells.len()), 0);
+            for (widest, cell) in widths.iter_mut().zip(cells) {
+                *widest = (*widest).max(width(cell));
+            }
+        }
+    }
+    widths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_and_separators_make_a_table() {
+        let lines = ["name | size", "-----+-----", "a    | 10", "bcd  | 2"];
+        let table = parse_table(&lines).unwrap();
+        assert_eq!(
+            table,
+            [
+                TableLine::Row(vec!["name", "size"]),
+                TableLine::Separator,
+                TableLine::Row(vec!["a", "10"]),
+                TableLine::Row(vec!["bcd", "2"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_blocks_are_no_tables() {
+        // ASCII art, with pipes in only some of its lines, and a single row.
+        assert_eq!(parse_table(&["  /\\", " /| \\", "/_|__\\"]), None);
+        assert_eq!(parse_table(&["a | b"]), None);
+        // Rows of different lengths.
+        assert_eq!(parse_table(&["a | b", "a | b | c"]), None);
+        // A line without any pipe.
+        assert_eq!(parse_table(&["a | b", "just text", "c | d"]), None);
+        assert_eq!(parse_table(&[]), None);
+    }
+
+    #[test]
+    fn columns_are_as_wide_as_their_widest_cell() {
+        let lines = [
+            "| name | size |",
+            "|------|------|",
+            "| a | 1000 |",
+            "| bcd | 2 |",
+        ];
+        let table = parse_table(&lines).unwrap();
+        assert_eq!(column_widths(&table, str::len), [0, 4, 4, 0]);
+        // Cells are measured however they are asked to be.
+        let doubled = column_widths(&table, |cell| 2 * cell.chars().count());
+        assert_eq!(doubled, [0, 8, 8, 0]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Keeping several pages open at once, one of which is shown.
+
+/// A list of tabs of which one is the current one. There is always at least one tab.
+#[derive(Debug)]
+pub struct Tabs<T> {
+    tabs: Vec<T>,
+    current: usize,
+}
+
+impl<T> Tabs<T> {
+    /// Creates new [`Tabs`] with just the `first` tab.
+    pub fn new(first: T) -> Self {
+        Self {
+            tabs: vec![first],
+            current: 0,
+        }
+    }
+
+    /// Returns the number of tabs.
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    /// Returns the index of the current tab.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Returns a mutable reference to the current tab.
+    pub fn current_mut(&mut self) -> &mut T {
+        &mut self.tabs[self.current]
+    }
+
+    /// Returns an iterator over the tabs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tabs.iter()
+    }
+
+    /// Add the `tab` right after the current one, and make it the current tab.
+    pub fn open(&mut self, tab: T) {
+        self.current += 1;
+        self.tabs.insert(self.current, tab);
+    }
+
+    /// Remove the current tab and return it, unless it is the only one.
+    ///
+    /// The tab after it becomes the current one, or the one before it if it was the last.
+    pub fn close(&mut self) -> Option<T> {
+        if self.tabs.len() == 1 {
+            return None;
+        }
+        let closed = self.tabs.remove(self.current);
+        self.current = self.current.min(self.tabs.len() - 1);
+        Some(closed)
+    }
+
+    /// Make the tab after the current one the current tab, going around after the last.
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.tabs.len();
+    }
+
+    /// Make the tab before the current one the current tab, going around before the first.
+    pub fn prev(&mut self) {
+        self.current = (self.current + self.tabs.len() - 1) % self.tabs.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the tabs in order, with the current one in brackets.
+    fn shown(tabs: &Tabs<char>) -> String {
+        tabs.iter()
+            .enumerate()
+            .map(|(idx, tab)| match idx == tabs.current() {
+                true => format!("[{tab}]"),
+                false => tab.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tabs_open_after_the_current_one() {
+        let mut tabs = Tabs::new('a');
+        tabs.open('b');
+        assert_eq!(shown(&tabs), "a[b]");
+        tabs.prev();
+        tabs.open('c');
+        assert_eq!(shown(&tabs), "a[c]b");
+        *tabs.current_mut() = 'd';
+        assert_eq!(shown(&tabs), "a[d]b");
+        assert_eq!(tabs.len(), 3);
+    }
+
+    #[test]
+    fn cycling_goes_around() {
+        let mut tabs = Tabs::new('a');
+        tabs.next();
+        tabs.prev();
+        assert_eq!(shown(&tabs), "[a]");
+        tabs.open('b');
+        tabs.open('c');
+        tabs.next();
+        assert_eq!(shown(&tabs), "[a]bc");
+        tabs.prev();
+        assert_eq!(shown(&tabs), "ab[c]");
+    }
+
+    #[test]
+    fn closing_keeps_the_current_tab_in_range() {
+        let mut tabs = Tabs::new('a');
+        assert_eq!(tabs.close(), None);
+        tabs.open('b');
+        tabs.open('c');
+        tabs.prev();
+        assert_eq!(tabs.close(), Some('b'));
+        assert_eq!(shown(&tabs), "a[c]");
+        assert_eq!(tabs.close(), Some('c'));
+        assert_eq!(shown(&tabs), "[a]");
+        assert_eq!(tabs.close(), None);
+    }
+}
@@ -0,0 +1,68 @@
+//! Tinting the lines of preformatted blocks after the language their alt text names, by a few
+//! rules of thumb rather than by understanding the language.
+
+/// The languages whose lines are tinted, by the names they go by in alt text.
+const DIFF_NAMES: &[&str] = &["diff", "patch"];
+const SHELL_NAMES: &[&str] = &["shell", "sh", "bash"];
+
+/// What sets a line of a preformatted block apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tint {
+    /// A line that a diff adds.
+    Added,
+    /// A line that a diff removes.
+    Removed,
+    /// A comment in a shell script.
+    Comment,
+}
+
+/// Returns how the `line` of a preformatted block with the `alt` text is tinted, if at all.
+///
+/// The language is the first word of the alt text, in any case. Blocks in other languages, or
+/// without one, are not tinted.
+pub fn classify(alt: &str, line: &str) -> Option<Tint> {
+    let language = alt.split_whitespace().next()?.to_lowercase();
+    if DIFF_NAMES.contains(&language.as_str()) {
+        match line.chars().next() {
+            Some('+') => Some(Tint::Added),
+            Some('-') => Some(Tint::Removed),
+            _ => None,
+        }
+    } else if SHELL_NAMES.contains(&language.as_str()) {
+        line.trim_start().starts_with('#').then_some(Tint::Comment)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines_are_tinted_by_their_first_character() {
+        assert_eq!(classify("diff", "+added"), Some(Tint::Added));
+        assert_eq!(
+            classify("Patch for main.rs", "-removed"),
+            Some(Tint::Removed)
+        );
+        assert_eq!(classify("diff", " context"), None);
+        // A diff has no comments, only lines that happen to start with a hash.
+        assert_eq!(classify("diff", "# heading"), None);
+    }
+
+    #[test]
+    fn shell_comments_are_tinted_however_indented() {
+        assert_eq!(classify("sh", "# comment"), Some(Tint::Comment));
+        assert_eq!(classify("BASH", "    # indented"), Some(Tint::Comment));
+        assert_eq!(classify("shell", "echo # not at the start"), None);
+        assert_eq!(classify("shell", "+1"), None);
+    }
+
+    #[test]
+    fn other_languages_are_not_tinted() {
+        assert_eq!(classify("", "+added"), None);
+        assert_eq!(classify("python", "# comment"), None);
+        assert_eq!(classify("a diff of it", "+added"), None);
+    }
+}
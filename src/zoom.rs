@@ -0,0 +1,32 @@
+//! Zooming in and out, by drawing every pixel of the content as a square of several.
+
+/// How far the window can be zoomed out and in, as how many pixels wide each pixel is drawn.
+pub const MIN_ZOOM: u32 = 1;
+pub const MAX_ZOOM: u32 = 8;
+
+/// Returns the `zoom`, but within how far the window can be zoomed.
+pub fn clamp_zoom(zoom: u32) -> u32 {
+    zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+}
+
+/// Returns the zoom that is `steps` further in than `zoom`, or further out if `steps` is negative.
+/// Zooming past either end stays there.
+pub fn zoom_by(zoom: u32, steps: i32) -> u32 {
+    clamp_zoom(zoom.saturating_add_signed(steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooming_past_either_end_stays_there() {
+        assert_eq!(zoom_by(1, 1), 2);
+        assert_eq!(zoom_by(MAX_ZOOM, 1), MAX_ZOOM);
+        assert_eq!(zoom_by(MAX_ZOOM - 1, 5), MAX_ZOOM);
+        assert_eq!(zoom_by(MIN_ZOOM, -1), MIN_ZOOM);
+        assert_eq!(zoom_by(3, -10), MIN_ZOOM);
+        assert_eq!(clamp_zoom(0), MIN_ZOOM);
+        assert_eq!(clamp_zoom(100), MAX_ZOOM);
+    }
+}